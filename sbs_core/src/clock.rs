@@ -0,0 +1,153 @@
+/// Tick frequency in Hz assumed when the device doesn't report one and none is configured
+pub const DEFAULT_TICK_FREQUENCY: f64 = 1000.0;
+
+/// Time of a signal frame, both in device ticks since the start of the connection and in seconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timestamp {
+    pub ticks: u64,
+    pub seconds: f64,
+}
+
+/// Converts the raw 32-bit device tick counter into a monotonic 64-bit timeline
+///
+/// A backwards jump of less than half the counter range is taken as a device reset rather than a
+/// counter wrap. After a reset the timeline continues from the last timestamp, so consumers never
+/// see time going backwards.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    tick_frequency: f64,
+    last_raw: Option<u32>,
+    ticks: u64,
+    resets: u32,
+}
+
+impl Clock {
+    pub fn new(tick_frequency: f64) -> Clock {
+        Clock {
+            tick_frequency,
+            last_raw: None,
+            ticks: 0,
+            resets: 0,
+        }
+    }
+
+    pub fn tick_frequency(&self) -> f64 {
+        self.tick_frequency
+    }
+
+    pub fn set_tick_frequency(&mut self, tick_frequency: f64) {
+        self.tick_frequency = tick_frequency;
+    }
+
+    /// Number of device resets detected since the clock was created or restarted
+    pub fn resets(&self) -> u32 {
+        self.resets
+    }
+
+    /// Starts a new timeline, e.g. for a new connection
+    pub fn restart(&mut self) {
+        self.last_raw = None;
+        self.ticks = 0;
+        self.resets = 0;
+    }
+
+    /// Continues the timeline from the next timestamp, without taking a jump back as a device reset
+    ///
+    /// For when the device may have been reset while no timestamps arrived, e.g. while the link was down.
+    pub fn resync(&mut self) {
+        self.last_raw = None;
+    }
+
+    /// Unwraps a raw device timestamp, also returns whether a device reset was detected
+    pub fn update(&mut self, raw: u32) -> (Timestamp, bool) {
+        let mut reset = false;
+
+        match self.last_raw {
+            None => {}
+            Some(last_raw) => {
+                let delta = raw.wrapping_sub(last_raw);
+                if delta <= u32::MAX / 2 {
+                    self.ticks += delta as u64;
+                } else {
+                    // The counter restarted from zero, so at least `raw` ticks passed since the reset
+                    self.ticks += raw as u64;
+                    self.resets += 1;
+                    reset = true;
+                }
+            }
+        }
+
+        self.last_raw = Some(raw);
+        (self.timestamp(self.ticks), reset)
+    }
+
+    pub fn timestamp(&self, ticks: u64) -> Timestamp {
+        Timestamp {
+            ticks,
+            seconds: ticks as f64 / self.tick_frequency,
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new(DEFAULT_TICK_FREQUENCY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks(clock: &mut Clock, raw: &[u32]) -> Vec<(u64, bool)> {
+        raw.iter().map(|&r| {
+            let (ts, reset) = clock.update(r);
+            (ts.ticks, reset)
+        }).collect()
+    }
+
+    #[test]
+    fn starts_at_zero() {
+        let mut clock = Clock::new(1000.0);
+        assert_eq!(ticks(&mut clock, &[5000, 5010, 5500]), vec![(0, false), (10, false), (500, false)]);
+        assert_eq!(clock.timestamp(500).seconds, 0.5);
+    }
+
+    #[test]
+    fn unwraps_counter() {
+        let mut clock = Clock::new(1000.0);
+        let raw = [u32::MAX - 10, u32::MAX, 5, 100];
+        assert_eq!(ticks(&mut clock, &raw), vec![(0, false), (10, false), (16, false), (111, false)]);
+        assert_eq!(clock.resets(), 0);
+    }
+
+    #[test]
+    fn detects_reset() {
+        let mut clock = Clock::new(1000.0);
+        let raw = [100_000, 100_100, 20, 50];
+        assert_eq!(ticks(&mut clock, &raw), vec![(0, false), (100, false), (120, true), (150, false)]);
+        assert_eq!(clock.resets(), 1);
+
+        clock.restart();
+        assert_eq!(ticks(&mut clock, &[7]), vec![(0, false)]);
+        assert_eq!(clock.resets(), 0);
+    }
+
+    #[test]
+    fn continues_after_resync() {
+        let mut clock = Clock::new(1000.0);
+        assert_eq!(ticks(&mut clock, &[100_000, 100_100]), vec![(0, false), (100, false)]);
+
+        clock.resync();
+        assert_eq!(ticks(&mut clock, &[20, 50]), vec![(100, false), (130, false)]);
+        assert_eq!(clock.resets(), 0);
+    }
+
+    #[test]
+    fn converts_with_tick_frequency() {
+        let mut clock = Clock::new(32768.0);
+        clock.update(0);
+        let (ts, _) = clock.update(16384);
+        assert_eq!(ts, Timestamp { ticks: 16384, seconds: 0.5 });
+    }
+}
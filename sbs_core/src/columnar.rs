@@ -0,0 +1,760 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use arrow::array::{
+    ArrayRef, ArrowPrimitiveType, FixedSizeListArray, PrimitiveArray, RecordBatch, StructArray,
+};
+use arrow::datatypes::{
+    DataType, Field, Fields, Float32Type, Float64Type, Int16Type, Int32Type, Int8Type, Schema,
+    SchemaRef, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::FileWriter;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use crate::clock::Timestamp;
+use crate::export::Series;
+use crate::recording::{RecordingError, RecordingReader};
+use crate::sbs::{FrameId, SignalDescriptor, SignalFrameDescriptor};
+use crate::ty::{BitField, Type};
+use crate::value::{SignalFrameValue, Value};
+
+/// Number of rows buffered per frame before they are written as a record batch
+const BATCH_ROWS: usize = 65_536;
+
+/// Name of the device tick column of every table
+pub const TICKS_COLUMN: &str = "ticks";
+
+/// Name of the time column of every table, in seconds, which is the index of the table
+pub const TIME_COLUMN: &str = "time";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnarFormat {
+    Parquet,
+    /// Arrow IPC file, also known as Feather v2
+    ArrowIpc,
+}
+
+impl ColumnarFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ColumnarFormat::Parquet => "parquet",
+            ColumnarFormat::ArrowIpc => "arrow",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnarError {
+    Io(String),
+    Arrow(String),
+    Recording(RecordingError),
+    /// The frame is not part of the frames the writer was created with
+    UnknownFrame(FrameId),
+}
+
+impl Display for ColumnarError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnarError::Io(e) => write!(f, "Export I/O error: {e}"),
+            ColumnarError::Arrow(e) => write!(f, "Failed to write table: {e}"),
+            ColumnarError::Recording(e) => write!(f, "{e}"),
+            ColumnarError::UnknownFrame(id) => write!(f, "Frame {} is not part of the export", id.0),
+        }
+    }
+}
+
+impl Error for ColumnarError {}
+
+impl From<io::Error> for ColumnarError {
+    fn from(e: io::Error) -> Self {
+        ColumnarError::Io(e.to_string())
+    }
+}
+
+impl From<ArrowError> for ColumnarError {
+    fn from(e: ArrowError) -> Self {
+        ColumnarError::Arrow(e.to_string())
+    }
+}
+
+impl From<ParquetError> for ColumnarError {
+    fn from(e: ParquetError) -> Self {
+        ColumnarError::Arrow(e.to_string())
+    }
+}
+
+impl From<RecordingError> for ColumnarError {
+    fn from(e: RecordingError) -> Self {
+        ColumnarError::Recording(e)
+    }
+}
+
+/// Returns the Arrow type a signal of type `ty` is stored as
+///
+/// Integers and `float32` keep their type, fixed-point numbers are stored as their real value in a
+/// `float64`. Arrays become fixed-size lists, structs and bit fields become structs, and enums are
+/// stored as their underlying integer, with the labels in the `sbs.type` metadata of the column.
+pub fn data_type(ty: &Type) -> DataType {
+    match ty {
+        Type::Uint8 => DataType::UInt8,
+        Type::Uint16 => DataType::UInt16,
+        Type::Uint32 => DataType::UInt32,
+        Type::Int8 => DataType::Int8,
+        Type::Int16 => DataType::Int16,
+        Type::Int32 => DataType::Int32,
+        Type::Float32 => DataType::Float32,
+        Type::SFix(..) | Type::UFix(..) => DataType::Float64,
+        Type::Array(inner, len) => DataType::FixedSizeList(item_field(inner), *len as i32),
+        Type::Struct(fields) => DataType::Struct(fields.iter()
+            .map(|field| Field::new(&field.name, data_type(&field.ty), false))
+            .collect()),
+        Type::Bits(_, fields) => DataType::Struct(bit_fields(fields)),
+        Type::Enum(base, _) => data_type(base),
+    }
+}
+
+fn item_field(inner: &Type) -> Arc<Field> {
+    Arc::new(Field::new_list_field(data_type(inner), false))
+}
+
+fn bit_fields(fields: &[BitField]) -> Fields {
+    fields.iter()
+        .map(|field| Field::new(&field.name, bit_field_type(field.width), false))
+        .collect()
+}
+
+/// Smallest unsigned integer type that holds a bit field of the given width
+fn bit_field_type(width: u32) -> DataType {
+    match width {
+        0..=8 => DataType::UInt8,
+        9..=16 => DataType::UInt16,
+        17..=32 => DataType::UInt32,
+        _ => DataType::UInt64,
+    }
+}
+
+/// Returns the schema of the table of a frame: the tick and time index, followed by a column per signal
+///
+/// The unit, scale and offset of the signals are kept in the column metadata, so engineering
+/// units can be computed after loading.
+pub fn frame_schema(descriptor: &SignalFrameDescriptor) -> Schema {
+    let index = [
+        Field::new(TICKS_COLUMN, DataType::UInt64, false),
+        Field::new(TIME_COLUMN, DataType::Float64, false),
+    ];
+    let columns = descriptor.signals.iter().map(signal_field);
+
+    let metadata = HashMap::from([
+        ("sbs.frame_id".to_string(), descriptor.id.0.to_string()),
+        ("sbs.frame_name".to_string(), descriptor.name.clone()),
+        ("sbs.index".to_string(), TIME_COLUMN.to_string()),
+    ]);
+
+    Schema::new_with_metadata(index.into_iter().chain(columns).collect::<Vec<_>>(), metadata)
+}
+
+fn signal_field(signal: &SignalDescriptor) -> Field {
+    Field::new(&signal.name, data_type(&signal.ty), false).with_metadata(signal_metadata(signal))
+}
+
+fn signal_metadata(signal: &SignalDescriptor) -> HashMap<String, String> {
+    let mut metadata = HashMap::from([("sbs.type".to_string(), signal.ty.to_string())]);
+    if let Some(unit) = &signal.unit {
+        metadata.insert("unit".to_string(), unit.clone());
+    }
+    if let Some(scale) = signal.scale {
+        metadata.insert("scale".to_string(), scale.to_string());
+    }
+    if let Some(offset) = signal.offset {
+        metadata.insert("offset".to_string(), offset.to_string());
+    }
+    metadata
+}
+
+/// Builds the column of a signal of type `ty` from its values
+///
+/// Values that don't match the type, e.g. an array of the wrong length, are written as the default
+/// value of the type rather than failing the whole export.
+fn to_array(ty: &Type, values: &[Value]) -> ArrayRef {
+    match ty {
+        Type::Uint8 => primitive::<UInt8Type>(values, |x| x as u8),
+        Type::Uint16 => primitive::<UInt16Type>(values, |x| x as u16),
+        Type::Uint32 => primitive::<UInt32Type>(values, |x| x as u32),
+        Type::Int8 => primitive::<Int8Type>(values, |x| x as i8),
+        Type::Int16 => primitive::<Int16Type>(values, |x| x as i16),
+        Type::Int32 => primitive::<Int32Type>(values, |x| x as i32),
+        Type::Float32 => primitive::<Float32Type>(values, |x| x as f32),
+        Type::SFix(..) | Type::UFix(..) => primitive::<Float64Type>(values, |x| x),
+        // The f64 conversion of an enum is its raw value
+        Type::Enum(base, _) => to_array(base, values),
+        Type::Array(inner, len) => {
+            let items = values.iter()
+                .flat_map(|value| match value {
+                    Value::Array(items) if items.len() == *len => items.clone(),
+                    _ => vec![inner.default_value(); *len],
+                })
+                .collect::<Vec<_>>();
+
+            Arc::new(FixedSizeListArray::new(item_field(inner), *len as i32, to_array(inner, &items), None))
+        }
+        Type::Struct(fields) => {
+            let columns = fields.iter().enumerate()
+                .map(|(i, field)| {
+                    let items = values.iter()
+                        .map(|value| match value {
+                            Value::Struct(items) => items.get(i)
+                                .filter(|(name, _)| *name == field.name)
+                                .map(|(_, item)| item.clone()),
+                            _ => None,
+                        }.unwrap_or_else(|| field.ty.default_value()))
+                        .collect::<Vec<_>>();
+                    to_array(&field.ty, &items)
+                })
+                .collect();
+
+            struct_array(data_type(ty), columns, values.len())
+        }
+        Type::Bits(_, fields) => {
+            let columns = fields.iter().enumerate()
+                .map(|(i, field)| {
+                    let items = values.iter()
+                        .map(|value| match value {
+                            Value::Bits { fields, .. } => fields.get(i).map(|(_, item)| item.clone()),
+                            _ => None,
+                        }.unwrap_or(Value::UFix { w: field.width, e: 0, raw: 0 }))
+                        .collect::<Vec<_>>();
+
+                    match bit_field_type(field.width) {
+                        DataType::UInt8 => unsigned_array::<UInt8Type>(&items, |x| x as u8),
+                        DataType::UInt16 => unsigned_array::<UInt16Type>(&items, |x| x as u16),
+                        DataType::UInt32 => unsigned_array::<UInt32Type>(&items, |x| x as u32),
+                        _ => unsigned_array::<UInt64Type>(&items, |x| x),
+                    }
+                })
+                .collect();
+
+            struct_array(data_type(ty), columns, values.len())
+        }
+    }
+}
+
+fn primitive<T: ArrowPrimitiveType>(values: &[Value], convert: impl Fn(f64) -> T::Native) -> ArrayRef {
+    Arc::new(PrimitiveArray::<T>::from_iter_values(values.iter().map(|value| convert(value.clone().into()))))
+}
+
+/// Builds an unsigned column from the raw values, which an f64 can't hold exactly beyond 53 bits
+fn unsigned_array<T: ArrowPrimitiveType>(values: &[Value], convert: impl Fn(u64) -> T::Native) -> ArrayRef {
+    Arc::new(PrimitiveArray::<T>::from_iter_values(values.iter().map(|value| convert(unsigned(value)))))
+}
+
+fn unsigned(value: &Value) -> u64 {
+    match value {
+        Value::UFix { raw, .. } | Value::Bits { raw, .. } => *raw,
+        _ => f64::from(value.clone()) as u64,
+    }
+}
+
+fn struct_array(data_type: DataType, columns: Vec<ArrayRef>, len: usize) -> ArrayRef {
+    let DataType::Struct(fields) = data_type else {
+        unreachable!("struct_array called with {data_type}");
+    };
+
+    if fields.is_empty() {
+        Arc::new(StructArray::new_empty_fields(len, None))
+    } else {
+        Arc::new(StructArray::new(fields, columns, None))
+    }
+}
+
+enum TableSink {
+    Parquet(ArrowWriter<File>),
+    ArrowIpc(FileWriter<File>),
+}
+
+impl TableSink {
+    fn create(path: &Path, schema: &SchemaRef, format: ColumnarFormat) -> Result<TableSink, ColumnarError> {
+        let file = File::create(path)?;
+
+        Ok(match format {
+            ColumnarFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                TableSink::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(properties))?)
+            }
+            ColumnarFormat::ArrowIpc => TableSink::ArrowIpc(FileWriter::try_new(file, schema)?),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), ColumnarError> {
+        match self {
+            TableSink::Parquet(writer) => writer.write(batch)?,
+            TableSink::ArrowIpc(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), ColumnarError> {
+        match self {
+            TableSink::Parquet(writer) => {
+                writer.close()?;
+            }
+            TableSink::ArrowIpc(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+/// Rows of a single frame, written to its own file in batches
+struct TableWriter {
+    descriptor: SignalFrameDescriptor,
+    schema: SchemaRef,
+    timestamps: Vec<Timestamp>,
+    rows: Vec<Vec<Value>>,
+    sink: TableSink,
+}
+
+impl TableWriter {
+    fn push(&mut self, value: &SignalFrameValue) -> Result<(), ColumnarError> {
+        self.timestamps.push(value.timestamp);
+        self.rows.push(value.data.clone());
+
+        if self.rows.len() >= BATCH_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), ColumnarError> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let ticks = Arc::new(PrimitiveArray::<UInt64Type>::from_iter_values(self.timestamps.iter().map(|t| t.ticks)));
+        let seconds = Arc::new(PrimitiveArray::<Float64Type>::from_iter_values(self.timestamps.iter().map(|t| t.seconds)));
+
+        let mut columns: Vec<ArrayRef> = vec![ticks, seconds];
+        for (i, signal) in self.descriptor.signals.iter().enumerate() {
+            let values = self.rows.iter()
+                .map(|row| row.get(i).cloned().unwrap_or_else(|| signal.ty.default_value()))
+                .collect::<Vec<_>>();
+            columns.push(to_array(&signal.ty, &values));
+        }
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.sink.write(&batch)?;
+
+        self.timestamps.clear();
+        self.rows.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), ColumnarError> {
+        self.flush()?;
+        self.sink.finish()
+    }
+}
+
+/// Writes decoded frames to a directory of Parquet or Arrow IPC files, a table per frame
+///
+/// Every table is named after its frame, e.g. `motor.parquet`, and has the columns described by
+/// [`frame_schema`]. Rows are written in batches, so captures don't have to fit in memory.
+pub struct ColumnarWriter {
+    tables: HashMap<FrameId, TableWriter>,
+}
+
+impl ColumnarWriter {
+    pub fn create(dir: impl AsRef<Path>, frames: &[SignalFrameDescriptor], format: ColumnarFormat) -> Result<ColumnarWriter, ColumnarError> {
+        std::fs::create_dir_all(dir.as_ref())?;
+
+        let mut tables = HashMap::new();
+        for (descriptor, path) in frames.iter().zip(table_paths(dir.as_ref(), frames, format)) {
+            let schema = Arc::new(frame_schema(descriptor));
+            let sink = TableSink::create(&path, &schema, format)?;
+
+            tables.insert(descriptor.id, TableWriter {
+                descriptor: descriptor.clone(),
+                schema,
+                timestamps: Vec::new(),
+                rows: Vec::new(),
+                sink,
+            });
+        }
+
+        Ok(ColumnarWriter { tables })
+    }
+
+    pub fn write(&mut self, value: &SignalFrameValue) -> Result<(), ColumnarError> {
+        let table = self.tables.get_mut(&value.descriptor.id)
+            .ok_or(ColumnarError::UnknownFrame(value.descriptor.id))?;
+        table.push(value)
+    }
+
+    /// Writes the remaining rows and the file footers, the files are unreadable until this is called
+    pub fn finish(self) -> Result<(), ColumnarError> {
+        for table in self.tables.into_values() {
+            table.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Names the table files after the frames, falling back to the frame id where names clash
+fn table_paths(dir: &Path, frames: &[SignalFrameDescriptor], format: ColumnarFormat) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    let ambiguous = frames.iter()
+        .map(|frame| sanitize(&frame.name))
+        .filter(|name| !seen.insert(name.clone()))
+        .collect::<HashSet<_>>();
+
+    frames.iter()
+        .map(|frame| {
+            let name = sanitize(&frame.name);
+            let name = if name.is_empty() || ambiguous.contains(&name) {
+                format!("frame_{}{}", frame.id.0, if name.is_empty() { String::new() } else { format!("_{name}") })
+            } else {
+                name
+            };
+            dir.join(format!("{name}.{}", format.extension()))
+        })
+        .collect()
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Writes the frames of a recording to a directory of tables, from its current position on
+pub fn export_recording(reader: RecordingReader, dir: impl AsRef<Path>, format: ColumnarFormat) -> Result<(), ColumnarError> {
+    let mut writer = ColumnarWriter::create(dir, reader.frames(), format)?;
+    for value in reader {
+        writer.write(&value?)?;
+    }
+    writer.finish()
+}
+
+/// Writes series, e.g. the samples of a plot, to a directory of tables, a table per frame
+///
+/// A table is named after its frame id, e.g. `frame_1.parquet`, and has the tick and time index
+/// followed by a column per leaf, named by its path. The leaves of a frame are aligned on the
+/// ticks of their samples, a leaf without a sample at the ticks of a row is null.
+pub fn write_series(dir: impl AsRef<Path>, series: &[Series], format: ColumnarFormat) -> Result<(), ColumnarError> {
+    std::fs::create_dir_all(dir.as_ref())?;
+
+    let mut frames = BTreeMap::<u32, Vec<&Series>>::new();
+    for leaf in series {
+        frames.entry(leaf.id.0.0).or_default().push(leaf);
+    }
+
+    for (frame_id, leaves) in frames {
+        let rows = leaves.iter()
+            .flat_map(|leaf| leaf.samples.iter().map(|(t, _)| (t.ticks, t.seconds)))
+            .collect::<BTreeMap<_, _>>();
+        let positions = rows.keys().enumerate()
+            .map(|(i, ticks)| (*ticks, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut fields = vec![
+            Field::new(TICKS_COLUMN, DataType::UInt64, false),
+            Field::new(TIME_COLUMN, DataType::Float64, false),
+        ];
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(PrimitiveArray::<UInt64Type>::from_iter_values(rows.keys().copied())),
+            Arc::new(PrimitiveArray::<Float64Type>::from_iter_values(rows.values().copied())),
+        ];
+
+        for leaf in leaves {
+            let mut values = vec![None; rows.len()];
+            for (t, value) in &leaf.samples {
+                values[positions[&t.ticks]] = Some(value);
+            }
+
+            let data_type = leaf_type(leaf);
+            columns.push(leaf_array(&data_type, &values));
+
+            let field = Field::new(&leaf.id.1, data_type, true);
+            fields.push(match &leaf.descriptor {
+                Some(descriptor) => field.with_metadata(signal_metadata(descriptor)),
+                None => field,
+            });
+        }
+
+        let metadata = HashMap::from([
+            ("sbs.frame_id".to_string(), frame_id.to_string()),
+            ("sbs.index".to_string(), TIME_COLUMN.to_string()),
+        ]);
+        let schema = Arc::new(Schema::new_with_metadata(fields, metadata));
+
+        let path = dir.as_ref().join(format!("frame_{frame_id}.{}", format.extension()));
+        let mut sink = TableSink::create(&path, &schema, format)?;
+        sink.write(&RecordBatch::try_new(schema, columns)?)?;
+        sink.finish()?;
+    }
+
+    Ok(())
+}
+
+/// Returns the Arrow type of a leaf, as it's stored in the frame tables, or `float64` if the leaf
+/// isn't part of its signal's type
+fn leaf_type(leaf: &Series) -> DataType {
+    let Some(descriptor) = &leaf.descriptor else {
+        return DataType::Float64;
+    };
+
+    let mut types = Vec::new();
+    collect_leaf_types(&descriptor.ty, descriptor.name.clone(), &mut types);
+    types.into_iter()
+        .find(|(path, _)| *path == leaf.id.1)
+        .map_or(DataType::Float64, |(_, data_type)| data_type)
+}
+
+/// Collects the Arrow types of the leaves, with the same paths as [`Type::leaves`]
+fn collect_leaf_types(ty: &Type, path: String, out: &mut Vec<(String, DataType)>) {
+    match ty {
+        Type::Array(inner, len) =>
+            for i in 0..*len {
+                collect_leaf_types(inner, format!("{path}[{i}]"), out);
+            },
+        Type::Struct(fields) =>
+            for field in fields {
+                collect_leaf_types(&field.ty, format!("{path}.{}", field.name), out);
+            },
+        Type::Bits(_, fields) =>
+            for field in fields {
+                out.push((format!("{path}.{}", field.name), bit_field_type(field.width)));
+            },
+        _ => out.push((path, data_type(ty))),
+    }
+}
+
+/// Builds the nullable column of a leaf from its values
+fn leaf_array(data_type: &DataType, values: &[Option<&Value>]) -> ArrayRef {
+    match data_type {
+        DataType::UInt8 => nullable::<UInt8Type>(values, |value| unsigned(value) as u8),
+        DataType::UInt16 => nullable::<UInt16Type>(values, |value| unsigned(value) as u16),
+        DataType::UInt32 => nullable::<UInt32Type>(values, |value| unsigned(value) as u32),
+        DataType::UInt64 => nullable::<UInt64Type>(values, unsigned),
+        DataType::Int8 => nullable::<Int8Type>(values, |value| f64::from(value.clone()) as i8),
+        DataType::Int16 => nullable::<Int16Type>(values, |value| f64::from(value.clone()) as i16),
+        DataType::Int32 => nullable::<Int32Type>(values, |value| f64::from(value.clone()) as i32),
+        DataType::Float32 => nullable::<Float32Type>(values, |value| f64::from(value.clone()) as f32),
+        _ => nullable::<Float64Type>(values, |value| value.clone().into()),
+    }
+}
+
+fn nullable<T: ArrowPrimitiveType>(values: &[Option<&Value>], convert: impl Fn(&Value) -> T::Native) -> ArrayRef {
+    Arc::new(values.iter().map(|value| value.map(&convert)).collect::<PrimitiveArray<T>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, AsArray};
+    use arrow::ipc::reader::FileReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use crate::export::SignalSelection;
+    use crate::sbs::SignalDescriptor;
+    use crate::ty::{EnumLabel, StructField};
+
+    fn frames() -> Vec<SignalFrameDescriptor> {
+        let mut current = SignalDescriptor::new("current", Type::SFix(16, -8));
+        current.unit = Some("A".to_string());
+
+        let motor = SignalFrameDescriptor {
+            id: FrameId(1),
+            name: "motor".to_string(),
+            enabled: true,
+            signals: vec![
+                current,
+                SignalDescriptor::new("phases", Type::Array(Box::new(Type::Int16), 3)),
+                SignalDescriptor::new("state", Type::Enum(Box::new(Type::Uint8), vec![EnumLabel { value: 1, label: "RUN".to_string() }])),
+            ],
+        };
+        let status = SignalFrameDescriptor {
+            id: FrameId(2),
+            name: "status word".to_string(),
+            enabled: true,
+            signals: vec![
+                SignalDescriptor::new("pos", Type::Struct(vec![
+                    StructField { name: "x".to_string(), ty: Type::Float32 },
+                    StructField { name: "y".to_string(), ty: Type::Float32 },
+                ])),
+                SignalDescriptor::new("flags", Type::Bits(16, vec![
+                    BitField { name: "fault".to_string(), width: 1 },
+                    BitField { name: "mode".to_string(), width: 12 },
+                ])),
+            ],
+        };
+
+        vec![motor, status]
+    }
+
+    fn write_frames(dir: &Path, format: ColumnarFormat) {
+        let frames = frames();
+        let mut writer = ColumnarWriter::create(dir, &frames, format).unwrap();
+
+        for ticks in 0..BATCH_ROWS as u64 + 10 {
+            let mut value = SignalFrameValue::new(frames[(ticks % 2) as usize].clone());
+            value.timestamp = Timestamp { ticks, seconds: ticks as f64 / 1000.0 };
+            if ticks % 2 == 0 {
+                value.data[0] = Value::SFix { w: 16, e: -8, raw: ticks as i64 };
+                value.data[1] = Value::Array(vec![Value::Int16(1), Value::Int16(2), Value::Int16(3)]);
+                value.data[2] = Type::enum_value(&[EnumLabel { value: 1, label: "RUN".to_string() }], 1);
+            } else {
+                value.data[0] = Value::Struct(vec![("x".to_string(), Value::Float32(1.5)), ("y".to_string(), Value::Float32(-2.0))]);
+                value.data[1] = Value::Bits {
+                    raw: 0,
+                    fields: vec![("fault".to_string(), Value::UFix { w: 1, e: 0, raw: 1 }), ("mode".to_string(), Value::UFix { w: 12, e: 0, raw: 300 })],
+                };
+            }
+            writer.write(&value).unwrap();
+        }
+
+        let mut other = SignalFrameValue::new(frames[0].clone());
+        other.descriptor.id = FrameId(3);
+        assert_eq!(writer.write(&other), Err(ColumnarError::UnknownFrame(FrameId(3))));
+
+        writer.finish().unwrap();
+    }
+
+    fn check_tables((schema, motor): (SchemaRef, Vec<RecordBatch>), (_, status): (SchemaRef, Vec<RecordBatch>)) {
+        assert_eq!(schema.metadata()["sbs.index"], TIME_COLUMN);
+        assert_eq!(schema.field(2).data_type(), &DataType::Float64);
+        assert_eq!(schema.field(2).metadata()["unit"], "A");
+        assert_eq!(schema.field(4).data_type(), &DataType::UInt8);
+        assert_eq!(schema.field(4).metadata()["sbs.type"], "enum(uint8){1:RUN}");
+        assert_eq!(motor.iter().map(RecordBatch::num_rows).sum::<usize>(), BATCH_ROWS / 2 + 5);
+
+        let ticks = motor[0].column(0).as_primitive::<UInt64Type>();
+        let current = motor[0].column(2).as_primitive::<Float64Type>();
+        assert_eq!((ticks.value(2), current.value(2)), (4, 4.0 / 256.0));
+
+        let phases = motor[0].column(3).as_fixed_size_list();
+        assert_eq!(phases.value(0).as_primitive::<Int16Type>().values(), &[1, 2, 3]);
+
+        let pos = status[0].column(2).as_struct();
+        assert_eq!(pos.column_by_name("y").unwrap().as_primitive::<Float32Type>().value(0), -2.0);
+
+        let flags = status[0].column(3).as_struct();
+        assert_eq!(flags.column(0).data_type(), &DataType::UInt8);
+        assert_eq!(flags.column(1).as_primitive::<UInt16Type>().value(0), 300);
+        assert_eq!(flags.len(), status[0].num_rows());
+    }
+
+    #[test]
+    fn writes_parquet_tables() {
+        let dir = std::env::temp_dir().join(format!("sbs_columnar_{}_parquet", std::process::id()));
+        write_frames(&dir, ColumnarFormat::Parquet);
+
+        let read = |name: &str| {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(dir.join(name)).unwrap()).unwrap();
+            (builder.schema().clone(), builder.build().unwrap().collect::<Result<Vec<_>, _>>().unwrap())
+        };
+        check_tables(read("motor.parquet"), read("status_word.parquet"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writes_arrow_ipc_tables() {
+        let dir = std::env::temp_dir().join(format!("sbs_columnar_{}_ipc", std::process::id()));
+        write_frames(&dir, ColumnarFormat::ArrowIpc);
+
+        let read = |name: &str| {
+            let reader = FileReader::try_new(File::open(dir.join(name)).unwrap(), None).unwrap();
+            (reader.schema(), reader.collect::<Result<Vec<_>, _>>().unwrap())
+        };
+        check_tables(read("motor.arrow"), read("status_word.arrow"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn read_parquet(path: &Path) -> (SchemaRef, RecordBatch) {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        let schema = builder.schema().clone();
+        let batches = builder.build().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        (schema.clone(), arrow::compute::concat_batches(&schema, &batches).unwrap())
+    }
+
+    #[test]
+    fn writes_wide_bit_fields_exactly() {
+        let dir = std::env::temp_dir().join(format!("sbs_columnar_{}_bits", std::process::id()));
+        let frame = SignalFrameDescriptor {
+            id: FrameId(1),
+            name: "counters".to_string(),
+            enabled: true,
+            signals: vec![SignalDescriptor::new("word", Type::Bits(64, vec![
+                BitField { name: "count".to_string(), width: 60 },
+                BitField { name: "flag".to_string(), width: 1 },
+            ]))],
+        };
+
+        let count = (1u64 << 60) - 1;
+        let mut value = SignalFrameValue::new(frame.clone());
+        value.data[0] = Value::Bits {
+            raw: count,
+            fields: vec![("count".to_string(), Value::UFix { w: 60, e: 0, raw: count }), ("flag".to_string(), Value::UFix { w: 1, e: 0, raw: 0 })],
+        };
+
+        let mut writer = ColumnarWriter::create(&dir, &[frame], ColumnarFormat::Parquet).unwrap();
+        writer.write(&value).unwrap();
+        writer.finish().unwrap();
+
+        let (_, batch) = read_parquet(&dir.join("counters.parquet"));
+        let word = batch.column(2).as_struct();
+        assert_eq!(word.column(0).as_primitive::<UInt64Type>().value(0), count);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writes_series_tables() {
+        let dir = std::env::temp_dir().join(format!("sbs_columnar_{}_series", std::process::id()));
+        let frames = frames();
+
+        let values = (0..3u64).map(|ticks| {
+            let mut value = SignalFrameValue::new(frames[1].clone());
+            value.timestamp = Timestamp { ticks, seconds: ticks as f64 / 1000.0 };
+            value.data[0] = Value::Struct(vec![("x".to_string(), Value::Float32(ticks as f32)), ("y".to_string(), Value::Float32(0.0))]);
+            value.data[1] = Value::Bits {
+                raw: 0,
+                fields: vec![("fault".to_string(), Value::UFix { w: 1, e: 0, raw: 0 }), ("mode".to_string(), Value::UFix { w: 12, e: 0, raw: 300 })],
+            };
+            value
+        });
+        let selection = SignalSelection::Signals(vec![(FrameId(2), "pos.x".to_string()), (FrameId(2), "flags.mode".to_string())]);
+        let mut series = Series::collect(values, &selection);
+        // As after the window of the buffer moved on
+        series[1].samples.remove(0);
+
+        write_series(&dir, &series, ColumnarFormat::Parquet).unwrap();
+
+        let (schema, batch) = read_parquet(&dir.join("frame_2.parquet"));
+        assert_eq!(schema.metadata()["sbs.frame_id"], "2");
+        assert_eq!(batch.column(0).as_primitive::<UInt64Type>().values(), &[0, 1, 2]);
+        assert_eq!(schema.field(2).name(), "pos.x");
+        assert_eq!(batch.column(2).as_primitive::<Float32Type>().value(2), 2.0);
+
+        let mode = batch.column(3).as_primitive::<UInt16Type>();
+        assert_eq!(mode.iter().collect::<Vec<_>>(), vec![None, Some(300), Some(300)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn names_tables_after_frames() {
+        let mut frames = frames();
+        frames[1].name = "motor".to_string();
+        let paths = table_paths(Path::new("out"), &frames, ColumnarFormat::Parquet);
+        assert_eq!(paths, vec![PathBuf::from("out/frame_1_motor.parquet"), PathBuf::from("out/frame_2_motor.parquet")]);
+    }
+}
@@ -19,6 +19,30 @@ impl<'s> BinaryReader<'s> {
             None
         }
     }
+
+    /// Reads an `n`-byte little-endian unsigned integer, zero-extended to 64 bits
+    pub fn read_uint_le(&mut self, n: usize) -> Option<u64> {
+        if n > 8 {
+            return None;
+        }
+
+        self.read(n).map(|data| {
+            let mut buf = [0u8; 8];
+            buf[..n].copy_from_slice(data);
+            u64::from_le_bytes(buf)
+        })
+    }
+}
+
+/// Returns the number of bytes a fixed-point number with the given word length occupies on the wire
+pub fn fix_container_size(w: u32) -> Option<usize> {
+    match w {
+        1..=8 => Some(1),
+        9..=16 => Some(2),
+        17..=32 => Some(4),
+        33..=64 => Some(8),
+        _ => None
+    }
 }
 
 impl Type {
@@ -36,27 +60,196 @@ impl Type {
                 .map(|data| Value::Int16(i16::from_le_bytes(<[u8; 2]>::try_from(data).unwrap()))),
             Type::Int32 => reader.read(4)
                 .map(|data| Value::Int32(i32::from_le_bytes(<[u8; 4]>::try_from(data).unwrap()))),
-            Type::UFix(w, e) if *w <= 8 => reader.read(1)
-                .map(|data| Value::UFix { w: *w, e: *e, raw: data[0] as u64 }),
-            Type::UFix(w, e) if *w <= 16 => reader.read(2)
-                .map(|data| Value::UFix {
-                    w: *w,
-                    e: *e,
-                    raw: u16::from_le_bytes(<[u8; 2]>::try_from(data).unwrap()) as u64,
-                }),
-            Type::UFix(w, e) if *w <= 32 => reader.read(4)
-                .map(|data| Value::UFix {
-                    w: *w,
-                    e: *e,
-                    raw: u32::from_le_bytes(<[u8; 4]>::try_from(data).unwrap()) as u64,
+            Type::Float32 => reader.read(4)
+                .map(|data| Value::Float32(f32::from_le_bytes(<[u8; 4]>::try_from(data).unwrap()))),
+            Type::SFix(w, e) => reader.read_uint_le(fix_container_size(*w)?)
+                .map(|bits| {
+                    // Sign-extend from bit w - 1, regardless of what the device put in the padding bits
+                    let shift = 64 - *w;
+                    Value::SFix { w: *w, e: *e, raw: ((bits << shift) as i64) >> shift }
                 }),
-            Type::UFix(w, e) if *w <= 64 => reader.read(8)
-                .map(|data| Value::UFix {
-                    w: *w,
-                    e: *e,
-                    raw: u64::from_le_bytes(<[u8; 8]>::try_from(data).unwrap()) as u64,
+            Type::UFix(w, e) => reader.read_uint_le(fix_container_size(*w)?)
+                .map(|bits| {
+                    let shift = 64 - *w;
+                    Value::UFix { w: *w, e: *e, raw: (bits << shift) >> shift }
                 }),
-            _ => todo!()
+            Type::Array(inner, len) => (0..*len)
+                .map(|_| inner.decode_bytes(reader))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array),
+            Type::Struct(fields) => fields.iter()
+                .map(|field| field.ty.decode_bytes(reader).map(|value| (field.name.clone(), value)))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Struct),
+            Type::Bits(width, fields) => reader.read_uint_le((*width / 8) as usize)
+                .map(|raw| Type::bits_value(fields, raw)),
+            Type::Enum(base, labels) => base.decode_bytes(reader)
+                .and_then(|value| value.as_i64())
+                .map(|raw| Type::enum_value(labels, raw)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ty::parse_type_name;
+
+    fn decode(ty: Type, bytes: &[u8]) -> Option<Value> {
+        ty.decode_bytes(&mut BinaryReader::new(bytes))
+    }
+
+    fn decode_f64(ty: Type, bytes: &[u8]) -> f64 {
+        decode(ty, bytes).unwrap().into()
+    }
+
+    #[test]
+    fn decodes_integers() {
+        assert_eq!(decode(Type::Uint8, &[0xFF]), Some(Value::Uint8(255)));
+        assert_eq!(decode(Type::Uint16, &[0x34, 0x12]), Some(Value::Uint16(0x1234)));
+        assert_eq!(decode(Type::Uint32, &[0x78, 0x56, 0x34, 0x12]), Some(Value::Uint32(0x12345678)));
+        assert_eq!(decode(Type::Int8, &[0x80]), Some(Value::Int8(-128)));
+        assert_eq!(decode(Type::Int16, &[0xFE, 0xFF]), Some(Value::Int16(-2)));
+        assert_eq!(decode(Type::Int32, &[0x00, 0x00, 0x00, 0x80]), Some(Value::Int32(i32::MIN)));
+    }
+
+    #[test]
+    fn decodes_float32() {
+        assert_eq!(decode(Type::Float32, &1.5f32.to_le_bytes()), Some(Value::Float32(1.5)));
+        assert_eq!(decode_f64(Type::Float32, &(-0.25f32).to_le_bytes()), -0.25);
+    }
+
+    #[test]
+    fn decodes_negative_sfix() {
+        // -1.5 in sfix(16, -8) is -384 = 0xFE80
+        assert_eq!(decode(Type::SFix(16, -8), &[0x80, 0xFE]), Some(Value::SFix { w: 16, e: -8, raw: -384 }));
+        assert_eq!(decode_f64(Type::SFix(16, -8), &[0x80, 0xFE]), -1.5);
+    }
+
+    #[test]
+    fn sign_extends_sfix_from_word_length() {
+        // 12-bit word in a 16-bit container, upper padding bits left zero by the device
+        assert_eq!(decode(Type::SFix(12, 0), &[0xFF, 0x0F]), Some(Value::SFix { w: 12, e: 0, raw: -1 }));
+        assert_eq!(decode(Type::SFix(12, 0), &[0xFF, 0x07]), Some(Value::SFix { w: 12, e: 0, raw: 2047 }));
+        assert_eq!(decode(Type::SFix(3, 0), &[0x04]), Some(Value::SFix { w: 3, e: 0, raw: -4 }));
+    }
+
+    #[test]
+    fn decodes_64_bit_fix() {
+        assert_eq!(decode(Type::SFix(64, 0), &[0xFF; 8]), Some(Value::SFix { w: 64, e: 0, raw: -1 }));
+        assert_eq!(decode(Type::UFix(64, 0), &[0xFF; 8]), Some(Value::UFix { w: 64, e: 0, raw: u64::MAX }));
+        assert_eq!(
+            decode(Type::SFix(64, -32), &i64::MIN.to_le_bytes()),
+            Some(Value::SFix { w: 64, e: -32, raw: i64::MIN }),
+        );
+        assert_eq!(decode_f64(Type::SFix(64, -32), &i64::MIN.to_le_bytes()), -2147483648.0);
+    }
+
+    #[test]
+    fn decodes_positive_exponents() {
+        assert_eq!(decode_f64(Type::UFix(8, 4), &[3]), 48.0);
+        assert_eq!(decode_f64(Type::SFix(8, 2), &[0xFF]), -4.0);
+        assert_eq!(decode_f64(Type::UFix(32, 40), &[1, 0, 0, 0]), 2f64.powi(40));
+    }
+
+    #[test]
+    fn uses_fix_container_sizes() {
+        let mut reader = BinaryReader::new(&[1, 0, 0, 0, 2]);
+        assert_eq!(Type::UFix(17, 0).decode_bytes(&mut reader), Some(Value::UFix { w: 17, e: 0, raw: 1 }));
+        assert_eq!(Type::UFix(1, 0).decode_bytes(&mut reader), Some(Value::UFix { w: 1, e: 0, raw: 0 }));
+        assert_eq!(Type::UFix(65, 0).decode_bytes(&mut BinaryReader::new(&[0; 16])), None);
+    }
+
+    #[test]
+    fn fails_on_short_input() {
+        assert_eq!(decode(Type::Float32, &[0, 0, 0]), None);
+        assert_eq!(decode(Type::SFix(16, -8), &[0]), None);
+    }
+
+    #[test]
+    fn decodes_compound_types() {
+        let ty = parse_type_name("struct{acc:int16[3],status:bits(u16){fault:1,mode:3}}").unwrap();
+        let bytes = [0xFF, 0xFF, 0x02, 0x00, 0x00, 0x80, 0b0000_0111, 0x80];
+
+        assert_eq!(decode(ty.clone(), &bytes), Some(Value::Struct(vec![
+            ("acc".to_string(), Value::Array(vec![Value::Int16(-1), Value::Int16(2), Value::Int16(i16::MIN)])),
+            ("status".to_string(), Value::Bits {
+                raw: 0x8007,
+                fields: vec![
+                    ("fault".to_string(), Value::UFix { w: 1, e: 0, raw: 1 }),
+                    ("mode".to_string(), Value::UFix { w: 3, e: 0, raw: 3 }),
+                ],
+            }),
+        ])));
+        assert_eq!(decode(ty, &bytes[..7]), None);
+    }
+
+    #[test]
+    fn decodes_enums() {
+        let ty = parse_type_name("enum(uint8){0:IDLE,1:RUN,2:FAULT}").unwrap();
+
+        assert_eq!(decode(ty.clone(), &[1]), Some(Value::Enum { raw: 1, label: Some("RUN".to_string()) }));
+        assert_eq!(decode(ty.clone(), &[2]).unwrap().to_string(), "FAULT");
+        assert_eq!(decode(ty.clone(), &[7]).unwrap().to_string(), "7");
+        assert_eq!(decode_f64(ty, &[2]), 2.0);
+
+        let signed = parse_type_name("enum(int16){-1:REVERSE,1:FORWARD}").unwrap();
+        assert_eq!(decode(signed, &[0xFF, 0xFF]).unwrap().to_string(), "REVERSE");
+    }
+
+    #[test]
+    fn value_leaves_match_type_leaves() {
+        let ty = parse_type_name("struct{acc:sfix(16,-8)[2],status:bits(u8){fault:1,mode:3}}[2]").unwrap();
+        let value = decode(ty.clone(), &[0x00, 0x01, 0x80, 0xFF, 0x0F, 0, 0, 0, 0, 0]).unwrap();
+        let value_leaves = value.leaves("imu");
+        let type_leaves = ty.leaves("imu");
+
+        assert_eq!(value_leaves.len(), 8);
+        for ((vpath, value), (tpath, ty)) in value_leaves.iter().zip(&type_leaves) {
+            assert_eq!(vpath, tpath);
+            assert_eq!(std::mem::discriminant(&ty.default_value()), std::mem::discriminant(value));
+        }
+        assert_eq!(f64::from(value_leaves[0].1.clone()), 1.0);
+        assert_eq!(f64::from(value_leaves[1].1.clone()), -0.5);
+        assert_eq!(value_leaves[3].0, "imu[0].status.mode");
+        assert_eq!(value_leaves[3].1.to_string(), "7");
+    }
+
+    #[test]
+    fn builds_values_from_f64() {
+        for (ty, x, expected) in [
+            ("uint8", 300.0, 255.0),
+            ("int16", -12.6, -13.0),
+            ("float32", 0.25, 0.25),
+            ("sfix(16,-8)", -1.5, -1.5),
+            ("sfix(8,-4)", 100.0, 127.0 / 16.0),
+            ("ufix(12,-4)", -3.0, 0.0),
+            ("bits(u8){a:1,b:3}", 1000.0, 255.0),
+            ("enum(uint8){1:RUN}", 1.2, 1.0),
+        ] {
+            let value = parse_type_name(ty).unwrap().value_from_f64(x);
+            assert_eq!(f64::from(value), expected, "{ty}");
         }
+
+        let array = parse_type_name("int8[2]").unwrap().value_from_f64(f64::NAN);
+        assert_eq!(array, Value::Array(vec![Value::Int8(0), Value::Int8(0)]));
+
+        let bits = parse_type_name("bits(u8){a:1,b:3}").unwrap().value_from_f64(11.0);
+        assert_eq!(bits.leaves("s")[1].1, Value::UFix { w: 3, e: 0, raw: 0b101 });
+    }
+
+    #[test]
+    fn displays_all_types() {
+        assert_eq!(Value::Int16(-42).to_string(), "-42");
+        assert_eq!(Value::Float32(0.5).to_string(), "0.5");
+        assert_eq!(Value::SFix { w: 16, e: -8, raw: -384 }.to_string(), "-1.5");
+        assert_eq!(Value::UFix { w: 8, e: 1, raw: 3 }.to_string(), "6");
+        assert_eq!(
+            Value::Struct(vec![
+                ("v".to_string(), Value::Array(vec![Value::Int8(1), Value::Int8(-1)])),
+                ("s".to_string(), Value::Bits { raw: 1, fields: vec![("on".to_string(), Value::UFix { w: 1, e: 0, raw: 1 })] }),
+            ]).to_string(),
+            "{v=[1, -1], s={on=1}}",
+        );
     }
 }
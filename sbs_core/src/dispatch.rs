@@ -0,0 +1,114 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::sbs::{CallbackHandle, FrameId, SignalFrameCallback};
+use crate::subscription::{FrameFilter, Subscription, SubscriptionOptions, SubscriptionSender};
+use crate::value::SignalFrameValue;
+
+struct CallbackEntry {
+    handle: CallbackHandle,
+    filter: FrameFilter,
+    cb: Box<dyn SignalFrameCallback>,
+}
+
+/// Delivers decoded frames to the callbacks and subscriptions registered with a client
+///
+/// Callbacks are invoked on the thread that calls [`Dispatcher::dispatch`], they must not register
+/// or remove callbacks themselves.
+#[derive(Default)]
+pub struct Dispatcher {
+    next_handle: AtomicU64,
+    callbacks: Mutex<Vec<CallbackEntry>>,
+    subscriptions: Mutex<Vec<SubscriptionSender>>,
+}
+
+impl Dispatcher {
+    pub fn new() -> Dispatcher {
+        Default::default()
+    }
+
+    pub fn add_callback(&self, filter: FrameFilter, cb: Box<dyn SignalFrameCallback>) -> CallbackHandle {
+        let handle = CallbackHandle(self.next_handle.fetch_add(1, Ordering::Relaxed));
+        self.callbacks.lock().unwrap().push(CallbackEntry { handle, filter, cb });
+
+        handle
+    }
+
+    pub fn remove_callback(&self, handle: CallbackHandle) -> bool {
+        let mut callbacks = self.callbacks.lock().unwrap();
+        let len = callbacks.len();
+        callbacks.retain(|entry| entry.handle != handle);
+
+        callbacks.len() != len
+    }
+
+    pub fn subscribe(&self, filter: FrameFilter, options: SubscriptionOptions) -> Subscription {
+        let (sender, subscription) = SubscriptionSender::new(filter, options);
+        self.subscriptions.lock().unwrap().push(sender);
+
+        subscription
+    }
+
+    /// Removes every callback and ends every subscription
+    pub fn clear(&self) {
+        self.callbacks.lock().unwrap().clear();
+        self.subscriptions.lock().unwrap().clear();
+    }
+
+    pub fn dispatch(&self, frame_id: FrameId, frame: &SignalFrameValue) {
+        for entry in self.callbacks.lock().unwrap().iter() {
+            if entry.filter.matches(frame_id) {
+                (*entry.cb)(frame_id, frame);
+            }
+        }
+
+        self.subscriptions.lock().unwrap().retain(|sub| sub.send(frame_id, frame));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::sbs::SignalFrameDescriptor;
+
+    fn frame(id: u32) -> SignalFrameValue {
+        SignalFrameValue::new(SignalFrameDescriptor {
+            id: FrameId(id),
+            name: format!("frame{id}"),
+            enabled: true,
+            signals: vec![],
+        })
+    }
+
+    fn recorder(received: &Arc<Mutex<Vec<u32>>>) -> Box<dyn SignalFrameCallback> {
+        let received = received.clone();
+        Box::new(move |frame_id: FrameId, _: &SignalFrameValue| received.lock().unwrap().push(frame_id.0))
+    }
+
+    #[test]
+    fn filters_and_removes_callbacks() {
+        let dispatcher = Dispatcher::new();
+        let all = Arc::new(Mutex::new(Vec::new()));
+        let some = Arc::new(Mutex::new(Vec::new()));
+
+        let all_handle = dispatcher.add_callback(FrameFilter::All, recorder(&all));
+        let some_handle = dispatcher.add_callback([FrameId(2), FrameId(3)].into(), recorder(&some));
+        assert_ne!(all_handle, some_handle);
+
+        for id in 1..=3 {
+            dispatcher.dispatch(FrameId(id), &frame(id));
+        }
+
+        assert!(dispatcher.remove_callback(all_handle));
+        assert!(!dispatcher.remove_callback(all_handle));
+        dispatcher.dispatch(FrameId(2), &frame(2));
+
+        assert_eq!(*all.lock().unwrap(), vec![1, 2, 3]);
+        assert_eq!(*some.lock().unwrap(), vec![2, 3, 2]);
+
+        dispatcher.clear();
+        dispatcher.dispatch(FrameId(2), &frame(2));
+        assert!(!dispatcher.remove_callback(some_handle));
+        assert_eq!(*some.lock().unwrap(), vec![2, 3, 2]);
+    }
+}
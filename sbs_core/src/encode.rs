@@ -0,0 +1,341 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::decode::fix_container_size;
+use crate::ty::Type;
+use crate::value::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EncodeError {
+    TypeMismatch { ty: Type, value: Value },
+    OutOfRange { ty: Type, value: Value },
+    InvalidWordLength(u32),
+    SignalCountMismatch { expected: usize, got: usize },
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::TypeMismatch { ty, value } =>
+                write!(f, "Cannot encode value {value:?} as type {ty:?}"),
+            EncodeError::OutOfRange { ty, value } =>
+                write!(f, "Value {value:?} is out of range for type {ty:?}"),
+            EncodeError::InvalidWordLength(w) =>
+                write!(f, "Invalid fixed-point word length {w}"),
+            EncodeError::SignalCountMismatch { expected, got } =>
+                write!(f, "Expected {expected} signal values, got {got}"),
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+#[derive(Debug, Default)]
+pub struct BinaryWriter {
+    bytes: Vec<u8>,
+}
+
+impl BinaryWriter {
+    pub fn new() -> BinaryWriter {
+        BinaryWriter { bytes: Vec::new() }
+    }
+
+    pub fn write(&mut self, data: &[u8]) {
+        self.bytes.extend_from_slice(data);
+    }
+
+    /// Writes the lowest `n` bytes of `value` in little-endian order
+    pub fn write_uint_le(&mut self, value: u64, n: usize) {
+        self.write(&value.to_le_bytes()[..n.min(8)]);
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl Type {
+    pub fn encode_value(&self, value: &Value, writer: &mut BinaryWriter) -> Result<(), EncodeError> {
+        match (self, value) {
+            (Type::Uint8, Value::Uint8(v)) => writer.write(&v.to_le_bytes()),
+            (Type::Uint16, Value::Uint16(v)) => writer.write(&v.to_le_bytes()),
+            (Type::Uint32, Value::Uint32(v)) => writer.write(&v.to_le_bytes()),
+            (Type::Int8, Value::Int8(v)) => writer.write(&v.to_le_bytes()),
+            (Type::Int16, Value::Int16(v)) => writer.write(&v.to_le_bytes()),
+            (Type::Int32, Value::Int32(v)) => writer.write(&v.to_le_bytes()),
+            (Type::Float32, Value::Float32(v)) => writer.write(&v.to_le_bytes()),
+            (Type::SFix(tw, te), Value::SFix { w, e, raw }) if tw == w && te == e => {
+                let size = fix_container_size(*w).ok_or(EncodeError::InvalidWordLength(*w))?;
+                let min = i64::MIN >> (64 - w);
+                let max = i64::MAX >> (64 - w);
+
+                if *raw < min || *raw > max {
+                    return Err(EncodeError::OutOfRange { ty: self.clone(), value: value.clone() });
+                }
+
+                writer.write_uint_le(*raw as u64, size);
+            }
+            (Type::UFix(tw, te), Value::UFix { w, e, raw }) if tw == w && te == e => {
+                let size = fix_container_size(*w).ok_or(EncodeError::InvalidWordLength(*w))?;
+
+                if *raw > u64::MAX >> (64 - w) {
+                    return Err(EncodeError::OutOfRange { ty: self.clone(), value: value.clone() });
+                }
+
+                writer.write_uint_le(*raw, size);
+            }
+            (Type::Array(inner, len), Value::Array(values)) if values.len() == *len => {
+                for value in values {
+                    inner.encode_value(value, writer)?;
+                }
+            }
+            (Type::Struct(fields), Value::Struct(values))
+            if fields.len() == values.len() && fields.iter().zip(values).all(|(f, (name, _))| &f.name == name) => {
+                for (field, (_, value)) in fields.iter().zip(values) {
+                    field.ty.encode_value(value, writer)?;
+                }
+            }
+            (Type::Bits(width, fields), Value::Bits { raw, fields: values })
+            if fields.len() == values.len() && fields.iter().zip(values).all(|(f, (name, _))| &f.name == name) => {
+                // Bits not covered by any field are taken from the raw word, the fields take precedence
+                let mut word = *raw & (u64::MAX >> (64 - width));
+                let mut offset = 0;
+
+                for (field, (_, value)) in fields.iter().zip(values) {
+                    let field_raw = match value {
+                        Value::UFix { w, e: 0, raw } if *w == field.width => *raw,
+                        _ => return Err(EncodeError::TypeMismatch { ty: self.clone(), value: value.clone() }),
+                    };
+                    let mask = u64::MAX >> (64 - field.width);
+
+                    if field_raw > mask {
+                        return Err(EncodeError::OutOfRange { ty: self.clone(), value: value.clone() });
+                    }
+
+                    word = (word & !(mask << offset)) | (field_raw << offset);
+                    offset += field.width;
+                }
+
+                writer.write_uint_le(word, (*width / 8) as usize);
+            }
+            (Type::Enum(base, _), Value::Enum { raw, .. }) => {
+                let base_value = base.integer_value(*raw)
+                    .ok_or_else(|| EncodeError::OutOfRange { ty: self.clone(), value: value.clone() })?;
+                base.encode_value(&base_value, writer)?;
+            }
+            _ => return Err(EncodeError::TypeMismatch { ty: self.clone(), value: value.clone() }),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::BinaryReader;
+    use crate::ty::parse_type_name;
+    use crate::sbs::{FrameId, SignalDescriptor, SignalFrameDescriptor};
+    use crate::value::SignalFrameValue;
+
+    /// Small xorshift generator so the round-trip tests are deterministic without extra dependencies
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    fn random_value(ty: &Type, rng: &mut Xorshift) -> Value {
+        let bits = rng.next();
+        match ty {
+            Type::Uint8 => Value::Uint8(bits as u8),
+            Type::Uint16 => Value::Uint16(bits as u16),
+            Type::Uint32 => Value::Uint32(bits as u32),
+            Type::Int8 => Value::Int8(bits as i8),
+            Type::Int16 => Value::Int16(bits as i16),
+            Type::Int32 => Value::Int32(bits as i32),
+            Type::Float32 => Value::Float32(f32::from_bits(bits as u32 & 0xBFFF_FFFF)),
+            Type::SFix(w, e) => Value::SFix { w: *w, e: *e, raw: (bits as i64) >> (64 - w) },
+            Type::UFix(w, e) => Value::UFix { w: *w, e: *e, raw: bits >> (64 - w) },
+            Type::Array(inner, len) => Value::Array((0..*len).map(|_| random_value(inner, rng)).collect()),
+            Type::Struct(fields) => Value::Struct(fields.iter()
+                .map(|field| (field.name.clone(), random_value(&field.ty, rng)))
+                .collect()),
+            Type::Bits(width, fields) => {
+                let raw = bits >> (64 - width);
+                let mut offset = 0;
+                let fields = fields.iter()
+                    .map(|field| {
+                        let value = (raw >> offset) & (u64::MAX >> (64 - field.width));
+                        offset += field.width;
+                        (field.name.clone(), Value::UFix { w: field.width, e: 0, raw: value })
+                    })
+                    .collect();
+
+                Value::Bits { raw, fields }
+            }
+            Type::Enum(base, labels) => {
+                let raw = random_value(base, rng).as_i64().unwrap();
+                Type::enum_value(labels, raw)
+            }
+        }
+    }
+
+    fn round_trip(ty: &Type, value: &Value) -> Option<Value> {
+        let mut writer = BinaryWriter::new();
+        ty.encode_value(value, &mut writer).unwrap();
+
+        let bytes = writer.into_bytes();
+        let mut reader = BinaryReader::new(&bytes);
+        let decoded = ty.decode_bytes(&mut reader);
+        assert!(reader.read(1).is_none(), "decoder did not consume all bytes for {ty:?}");
+
+        decoded
+    }
+
+    #[test]
+    fn round_trips_random_values() {
+        let mut rng = Xorshift(0x2545F4914F6CDD1D);
+        let mut types = vec![
+            Type::Uint8, Type::Uint16, Type::Uint32,
+            Type::Int8, Type::Int16, Type::Int32,
+            Type::Float32,
+        ];
+        for w in 1..=64 {
+            types.push(Type::SFix(w, -(w as i32) / 2));
+            types.push(Type::UFix(w, 3));
+        }
+        for name in [
+            "int16[3]", "uint16[16]", "struct{x:float32,y:sfix(12,-4),z:uint8[2]}",
+            "bits(u8){a:1,b:7}", "bits(u16){fault:1,mode:3}", "bits(u32){lo:16,hi:15}",
+            "struct{status:bits(u8){a:1},acc:int16[3]}[2]",
+            "enum(uint8){0:IDLE,1:RUN,2:FAULT}", "enum(int32){-5:NEG,5:POS}",
+        ] {
+            types.push(parse_type_name(name).unwrap());
+        }
+
+        for ty in &types {
+            for _ in 0..256 {
+                let value = random_value(ty, &mut rng);
+                assert_eq!(round_trip(ty, &value), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_fix_edge_values() {
+        for w in [1, 8, 9, 16, 31, 32, 33, 64] {
+            let min = i64::MIN >> (64 - w);
+            let max = i64::MAX >> (64 - w);
+            for raw in [min, -1, 0, max] {
+                let value = Value::SFix { w, e: -8, raw };
+                assert_eq!(round_trip(&Type::SFix(w, -8), &value), Some(value));
+            }
+
+            let value = Value::UFix { w, e: 4, raw: u64::MAX >> (64 - w) };
+            assert_eq!(round_trip(&Type::UFix(w, 4), &value), Some(value));
+        }
+    }
+
+    #[test]
+    fn round_trips_signal_frames() {
+        let descriptor = SignalFrameDescriptor {
+            id: FrameId(3),
+            name: "motor".to_string(),
+            enabled: true,
+            signals: vec![
+                SignalDescriptor::new("current", Type::Int16),
+                SignalDescriptor::new("setpoint", Type::Float32),
+                SignalDescriptor::new("angle", Type::SFix(16, -8)),
+            ],
+        };
+
+        let mut frame = SignalFrameValue::new(descriptor.clone());
+        frame.data = vec![Value::Int16(-1200), Value::Float32(3.25), Value::SFix { w: 16, e: -8, raw: -384 }];
+        let bytes = frame.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 8);
+
+        let mut decoded = SignalFrameValue::new(descriptor);
+        assert!(decoded.update_from_bytes(Default::default(), &bytes));
+        assert_eq!(decoded.data, frame.data);
+
+        frame.data.pop();
+        assert!(matches!(frame.to_bytes(), Err(EncodeError::SignalCountMismatch { expected: 3, got: 2 })));
+    }
+
+    #[test]
+    fn encodes_little_endian() {
+        let mut writer = BinaryWriter::new();
+        Type::SFix(16, -8).encode_value(&Value::SFix { w: 16, e: -8, raw: -384 }, &mut writer).unwrap();
+        Type::Uint32.encode_value(&Value::Uint32(0x12345678), &mut writer).unwrap();
+
+        assert_eq!(writer.bytes(), &[0x80, 0xFE, 0x78, 0x56, 0x34, 0x12]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_fix() {
+        let mut writer = BinaryWriter::new();
+        let too_big = Value::SFix { w: 12, e: 0, raw: 2048 };
+        let too_small = Value::SFix { w: 12, e: 0, raw: -2049 };
+        let too_wide = Value::UFix { w: 12, e: 0, raw: 4096 };
+
+        assert!(matches!(Type::SFix(12, 0).encode_value(&too_big, &mut writer), Err(EncodeError::OutOfRange { .. })));
+        assert!(matches!(Type::SFix(12, 0).encode_value(&too_small, &mut writer), Err(EncodeError::OutOfRange { .. })));
+        assert!(matches!(Type::UFix(12, 0).encode_value(&too_wide, &mut writer), Err(EncodeError::OutOfRange { .. })));
+        assert!(writer.bytes().is_empty());
+    }
+
+    #[test]
+    fn encodes_bit_fields() {
+        let ty = parse_type_name("bits(u16){fault:1,mode:3}").unwrap();
+        let field = |name: &str, w, raw| (name.to_string(), Value::UFix { w, e: 0, raw });
+        let mut writer = BinaryWriter::new();
+
+        ty.encode_value(&Value::Bits { raw: 0x8000, fields: vec![field("fault", 1, 1), field("mode", 3, 5)] }, &mut writer).unwrap();
+        assert_eq!(writer.bytes(), &[0b0000_1011, 0x80]);
+
+        let too_wide = Value::Bits { raw: 0, fields: vec![field("fault", 1, 0), field("mode", 3, 8)] };
+        assert!(matches!(ty.encode_value(&too_wide, &mut writer), Err(EncodeError::OutOfRange { .. })));
+
+        let renamed = Value::Bits { raw: 0, fields: vec![field("fault", 1, 0), field("state", 3, 1)] };
+        assert!(matches!(ty.encode_value(&renamed, &mut writer), Err(EncodeError::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn rejects_out_of_range_enums() {
+        let ty = parse_type_name("enum(uint8){0:IDLE}").unwrap();
+        let mut writer = BinaryWriter::new();
+
+        ty.encode_value(&Value::Enum { raw: 255, label: None }, &mut writer).unwrap();
+        assert_eq!(writer.bytes(), &[255]);
+        assert!(matches!(ty.encode_value(&Value::Enum { raw: 256, label: None }, &mut writer), Err(EncodeError::OutOfRange { .. })));
+        assert!(matches!(ty.encode_value(&Value::Enum { raw: -1, label: None }, &mut writer), Err(EncodeError::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn rejects_mismatched_types() {
+        let mut writer = BinaryWriter::new();
+
+        assert!(matches!(Type::Int16.encode_value(&Value::Uint16(1), &mut writer), Err(EncodeError::TypeMismatch { .. })));
+        assert!(matches!(
+            Type::SFix(16, -8).encode_value(&Value::SFix { w: 16, e: -4, raw: 1 }, &mut writer),
+            Err(EncodeError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            Type::Array(Box::new(Type::Int16), 3).encode_value(&Value::Array(vec![Value::Int16(1)]), &mut writer),
+            Err(EncodeError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            Type::UFix(65, 0).encode_value(&Value::UFix { w: 65, e: 0, raw: 1 }, &mut writer),
+            Err(EncodeError::InvalidWordLength(65))
+        ));
+    }
+}
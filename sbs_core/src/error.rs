@@ -0,0 +1,43 @@
+use std::fmt::{Display, Formatter};
+use crate::sbs::FrameId;
+
+/// Errors returned by a [`Client`](crate::sbs::Client)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The underlying link failed, e.g. the serial port could not be opened or was disconnected
+    Transport(String),
+    /// The device did not respond in time
+    Timeout,
+    /// A frame from the device was received with an invalid checksum
+    Crc,
+    /// The device sent something that doesn't follow the protocol, or not the expected response
+    Protocol(String),
+    /// The frame id is not known to the client or the device
+    UnknownFrame(FrameId),
+    /// The client is not connected to a device
+    NotConnected,
+    Internal(String),
+}
+
+impl Error {
+    /// Whether repeating the request that failed with this error may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::Timeout | Error::Crc | Error::Protocol(_))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Transport(e) => write!(f, "Transport error: {e}"),
+            Error::Timeout => write!(f, "Timeout"),
+            Error::Crc => write!(f, "Invalid frame CRC"),
+            Error::Protocol(e) => write!(f, "Protocol error: {e}"),
+            Error::UnknownFrame(id) => write!(f, "Unknown frame {}", id.0),
+            Error::NotConnected => write!(f, "Not connected"),
+            Error::Internal(e) => write!(f, "Internal error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
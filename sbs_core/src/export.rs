@@ -0,0 +1,330 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::Write;
+use crate::clock::Timestamp;
+use crate::recording::{RecordingError, RecordingReader};
+use crate::sbs::{FrameId, SignalDescriptor, SignalId};
+use crate::value::{SignalFrameValue, Value};
+
+/// Signals to export, identified by the paths of their scalar leaves
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignalSelection {
+    Signal(SignalId),
+    /// Every signal of a frame
+    Frame(FrameId),
+    Signals(Vec<SignalId>),
+}
+
+impl SignalSelection {
+    pub fn contains(&self, signal_id: &SignalId) -> bool {
+        match self {
+            SignalSelection::Signal(id) => id == signal_id,
+            SignalSelection::Frame(frame_id) => *frame_id == signal_id.0,
+            SignalSelection::Signals(ids) => ids.contains(signal_id),
+        }
+    }
+}
+
+/// Samples of a scalar signal leaf, in time order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+    pub id: SignalId,
+    /// Descriptor of the signal the leaf belongs to, for the unit and engineering-unit conversion
+    pub descriptor: Option<SignalDescriptor>,
+    pub samples: Vec<(Timestamp, Value)>,
+}
+
+impl Series {
+    /// Collects the selected leaves of the frames, in the order they first appear
+    pub fn collect(values: impl IntoIterator<Item=SignalFrameValue>, selection: &SignalSelection) -> Vec<Series> {
+        let mut series = Vec::<Series>::new();
+        let mut positions = HashMap::<SignalId, usize>::new();
+
+        for value in values {
+            for (descriptor, data) in value.descriptor.signals.iter().zip(&value.data) {
+                for (path, leaf) in data.leaves(&descriptor.name) {
+                    let id = (value.descriptor.id, path);
+                    if !selection.contains(&id) {
+                        continue;
+                    }
+
+                    let position = *positions.entry(id.clone()).or_insert_with(|| {
+                        series.push(Series { id, descriptor: Some(descriptor.clone()), samples: Vec::new() });
+                        series.len() - 1
+                    });
+                    series[position].samples.push((value.timestamp, leaf));
+                }
+            }
+        }
+
+        series
+    }
+
+    /// Reads the selected leaves from a recording, from its current position on
+    ///
+    /// The frames are streamed from the file, so only the selected samples are held in memory.
+    pub fn from_recording(reader: RecordingReader, selection: &SignalSelection) -> Result<Vec<Series>, RecordingError> {
+        let mut error = None;
+        let values = reader.map_while(|value| value.map_err(|e| error = Some(e)).ok());
+        let series = Series::collect(values, selection);
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(series),
+        }
+    }
+
+    fn format_value(&self, value: &Value, engineering_units: bool) -> String {
+        match (&self.descriptor, engineering_units) {
+            (Some(descriptor), true) => descriptor.to_engineering(value).to_string(),
+            (None, true) => f64::from(value.clone()).to_string(),
+            (_, false) => value.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Tsv,
+}
+
+impl TableFormat {
+    fn delimiter(&self) -> char {
+        match self {
+            TableFormat::Csv => ',',
+            TableFormat::Tsv => '\t',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// Device ticks since the start of the connection
+    Ticks,
+    Seconds,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableLayout {
+    /// A row per timestamp with a column per signal, holding each signal's last sample
+    Aligned,
+    /// A row per sample, with the time, signal name and value
+    Long,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub format: TableFormat,
+    pub time: TimeFormat,
+    pub layout: TableLayout,
+    /// Convert values to engineering units, otherwise raw values and enum labels are written
+    pub engineering_units: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            format: TableFormat::Csv,
+            time: TimeFormat::Seconds,
+            layout: TableLayout::Aligned,
+            engineering_units: true,
+        }
+    }
+}
+
+/// Writes the series as a CSV or TSV table with a header row
+pub fn write_table(out: &mut impl Write, series: &[Series], options: &ExportOptions) -> io::Result<()> {
+    let names = column_names(series);
+    let delimiter = options.format.delimiter();
+
+    let format_time = |timestamp: &Timestamp| match options.time {
+        TimeFormat::Ticks => timestamp.ticks.to_string(),
+        TimeFormat::Seconds => timestamp.seconds.to_string(),
+    };
+    let time_header = match options.time {
+        TimeFormat::Ticks => "ticks",
+        TimeFormat::Seconds => "time [s]",
+    };
+
+    match options.layout {
+        TableLayout::Aligned => {
+            let header = [time_header.to_string()].into_iter().chain(names);
+            write_row(out, delimiter, header)?;
+
+            let mut times = series.iter()
+                .flat_map(|s| s.samples.iter().map(|(t, _)| *t))
+                .collect::<Vec<_>>();
+            times.sort_by(|a, b| a.seconds.total_cmp(&b.seconds).then(a.ticks.cmp(&b.ticks)));
+            times.dedup();
+
+            // Index of the next sample of each series, the one before it is held
+            let mut next = vec![0; series.len()];
+            for time in &times {
+                let values = series.iter().zip(next.iter_mut()).map(|(s, next)| {
+                    while s.samples.get(*next).is_some_and(|(t, _)| t.seconds <= time.seconds) {
+                        *next += 1;
+                    }
+
+                    match next.checked_sub(1) {
+                        Some(i) => s.format_value(&s.samples[i].1, options.engineering_units),
+                        None => String::new(),
+                    }
+                }).collect::<Vec<_>>();
+
+                write_row(out, delimiter, [format_time(time)].into_iter().chain(values))?;
+            }
+        }
+        TableLayout::Long => {
+            write_row(out, delimiter, [time_header, "signal", "value"].map(str::to_string))?;
+
+            let mut rows = series.iter().zip(&names)
+                .flat_map(|(s, name)| s.samples.iter().map(move |(t, v)| (t, name, s, v)))
+                .collect::<Vec<_>>();
+            rows.sort_by(|a, b| a.0.seconds.total_cmp(&b.0.seconds));
+
+            for (time, name, s, value) in rows {
+                write_row(out, delimiter, [format_time(time), name.clone(), s.format_value(value, options.engineering_units)])?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Names the columns after the signal paths and units, prefixed with the frame id where paths are ambiguous
+fn column_names(series: &[Series]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let ambiguous = series.iter()
+        .filter(|s| !seen.insert(&s.id.1))
+        .map(|s| &s.id.1)
+        .collect::<HashSet<_>>();
+
+    series.iter()
+        .map(|s| {
+            let path = if ambiguous.contains(&s.id.1) {
+                format!("{}:{}", s.id.0.0, s.id.1)
+            } else {
+                s.id.1.clone()
+            };
+
+            match &s.descriptor {
+                Some(descriptor) => descriptor.display_name(&path),
+                None => path,
+            }
+        })
+        .collect()
+}
+
+fn write_row(out: &mut impl Write, delimiter: char, fields: impl IntoIterator<Item=String>) -> io::Result<()> {
+    let row = fields.into_iter()
+        .map(|field| {
+            if field.contains([delimiter, '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&delimiter.to_string());
+
+    writeln!(out, "{row}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbs::SignalFrameDescriptor;
+    use crate::ty::{EnumLabel, Type};
+
+    fn frames() -> Vec<SignalFrameValue> {
+        let mut current = SignalDescriptor::new("current", Type::Int16);
+        current.unit = Some("A".to_string());
+        current.scale = Some(0.5);
+
+        let labels = vec![
+            EnumLabel { value: 0, label: "IDLE".to_string() },
+            EnumLabel { value: 1, label: "RUN".to_string() },
+        ];
+        let descriptor = SignalFrameDescriptor {
+            id: FrameId(1),
+            name: "motor".to_string(),
+            enabled: true,
+            signals: vec![current, SignalDescriptor::new("state", Type::Enum(Box::new(Type::Uint8), labels.clone()))],
+        };
+
+        [(0, 10, 0), (20, 12, 1), (30, 14, 1)].into_iter()
+            .map(|(ticks, current, state)| {
+                let mut value = SignalFrameValue::new(descriptor.clone());
+                value.timestamp = Timestamp { ticks, seconds: ticks as f64 / 100.0 };
+                value.data = vec![Value::Int16(current), Type::enum_value(&labels, state)];
+                value
+            })
+            .collect()
+    }
+
+    fn export(series: &[Series], options: ExportOptions) -> String {
+        let mut out = Vec::new();
+        write_table(&mut out, series, &options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn reads_series_from_recordings() {
+        let path = std::env::temp_dir().join(format!("sbs_export_{}.sbsrec", std::process::id()));
+        let mut writer = crate::recording::RecordingWriter::create(&path, &[frames()[0].descriptor.clone()]).unwrap();
+        for value in frames() {
+            writer.write(&value).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let selection = SignalSelection::Signal((FrameId(1), "current".to_string()));
+        let series = Series::from_recording(RecordingReader::open(&path).unwrap(), &selection).unwrap();
+        assert_eq!(series, Series::collect(frames(), &selection));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn selects_signals() {
+        let all = Series::collect(frames(), &SignalSelection::Frame(FrameId(1)));
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].samples.len(), 3);
+
+        let state = (FrameId(1), "state".to_string());
+        let one = Series::collect(frames(), &SignalSelection::Signal(state.clone()));
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].id, state);
+
+        assert!(Series::collect(frames(), &SignalSelection::Frame(FrameId(2))).is_empty());
+    }
+
+    #[test]
+    fn writes_aligned_and_long_tables() {
+        let mut series = Series::collect(frames(), &SignalSelection::Frame(FrameId(1)));
+        // Every other sample of the state is missing, the last one is held
+        series[1].samples.remove(1);
+
+        assert_eq!(export(&series, ExportOptions::default()),
+                   "time [s],current [A],state\n0,5,0\n0.2,6,0\n0.3,7,1\n");
+
+        assert_eq!(export(&series, ExportOptions {
+            format: TableFormat::Tsv,
+            time: TimeFormat::Ticks,
+            layout: TableLayout::Long,
+            engineering_units: false,
+        }), "ticks\tsignal\tvalue\n0\tcurrent [A]\t10\n0\tstate\tIDLE\n20\tcurrent [A]\t12\n30\tcurrent [A]\t14\n30\tstate\tRUN\n");
+    }
+
+    #[test]
+    fn disambiguates_and_quotes_columns() {
+        let mut series = Series::collect(frames(), &SignalSelection::Signal((FrameId(1), "state".to_string())));
+        let mut other = series[0].clone();
+        other.id.0 = FrameId(2);
+        other.descriptor.as_mut().unwrap().unit = Some("a,b".to_string());
+        series.push(other);
+
+        let table = export(&series, ExportOptions::default());
+        assert_eq!(table.lines().next(), Some("time [s],1:state,\"2:state [a,b]\""));
+    }
+}
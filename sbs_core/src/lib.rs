@@ -2,3 +2,14 @@ pub mod sbs;
 pub mod ty;
 pub mod value;
 pub mod decode;
+pub mod encode;
+pub mod overrides;
+pub mod error;
+pub mod subscription;
+pub mod dispatch;
+pub mod clock;
+pub mod schema;
+pub mod recording;
+pub mod export;
+#[cfg(feature = "columnar")]
+pub mod columnar;
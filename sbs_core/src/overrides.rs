@@ -0,0 +1,173 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use serde::Deserialize;
+use crate::sbs::{FrameId, SignalFrameDescriptor};
+
+/// Host-side signal metadata, used to add or correct units and scaling the device doesn't announce
+///
+/// ```toml
+/// [[signal]]
+/// frame = "motor"    # frame name or numeric id
+/// name = "current"
+/// unit = "A"
+/// scale = 0.001
+/// min = -10.0
+/// max = 10.0
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SignalOverrides {
+    #[serde(default, rename = "signal")]
+    pub signals: Vec<SignalOverride>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SignalOverride {
+    pub frame: FrameRef,
+    pub name: String,
+    pub unit: Option<String>,
+    pub scale: Option<f64>,
+    pub offset: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FrameRef {
+    Id(u32),
+    Name(String),
+}
+
+impl FrameRef {
+    pub fn matches(&self, frame: &SignalFrameDescriptor) -> bool {
+        match self {
+            FrameRef::Id(id) => frame.id == FrameId(*id),
+            FrameRef::Name(name) => &frame.name == name,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum OverridesError {
+    Io(String),
+    Parse(String),
+}
+
+impl Display for OverridesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverridesError::Io(e) => write!(f, "Failed to read signal overrides: {e}"),
+            OverridesError::Parse(e) => write!(f, "Invalid signal overrides: {e}"),
+        }
+    }
+}
+
+impl Error for OverridesError {}
+
+impl SignalOverrides {
+    pub fn load(path: impl AsRef<Path>) -> Result<SignalOverrides, OverridesError> {
+        let content = std::fs::read_to_string(path).map_err(|e| OverridesError::Io(e.to_string()))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<SignalOverrides, OverridesError> {
+        toml::from_str(content).map_err(|e| OverridesError::Parse(e.to_string()))
+    }
+
+    /// Applies the overrides to the given frames, only replacing the fields that are set in the override
+    ///
+    /// Returns the overrides that didn't match any signal.
+    pub fn apply(&self, frames: &mut [SignalFrameDescriptor]) -> Vec<&SignalOverride> {
+        let mut unmatched = Vec::new();
+
+        for ovr in &self.signals {
+            let signal = frames.iter_mut()
+                .filter(|frame| ovr.frame.matches(frame))
+                .flat_map(|frame| frame.signals.iter_mut())
+                .find(|signal| signal.name == ovr.name);
+
+            match signal {
+                Some(signal) => {
+                    signal.unit = ovr.unit.clone().or(signal.unit.take());
+                    signal.scale = ovr.scale.or(signal.scale);
+                    signal.offset = ovr.offset.or(signal.offset);
+                    signal.min = ovr.min.or(signal.min);
+                    signal.max = ovr.max.or(signal.max);
+                }
+                None => unmatched.push(ovr),
+            }
+        }
+
+        unmatched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbs::SignalDescriptor;
+    use crate::ty::Type;
+    use crate::value::Value;
+
+    #[test]
+    fn applies_overrides() {
+        let overrides = SignalOverrides::parse(r#"
+            [[signal]]
+            frame = "motor"
+            name = "current"
+            unit = "A"
+            scale = 0.001
+
+            [[signal]]
+            frame = 2
+            name = "speed"
+            unit = "rpm"
+            offset = -100.0
+            max = 6000.0
+
+            [[signal]]
+            frame = "motor"
+            name = "missing"
+            unit = "V"
+        "#).unwrap();
+
+        let mut speed = SignalDescriptor::new("speed", Type::Uint16);
+        speed.scale = Some(2.0);
+        let mut frames = vec![
+            SignalFrameDescriptor {
+                id: FrameId(1),
+                name: "motor".to_string(),
+                enabled: false,
+                signals: vec![SignalDescriptor::new("current", Type::Int16)],
+            },
+            SignalFrameDescriptor {
+                id: FrameId(2),
+                name: "drive".to_string(),
+                enabled: false,
+                signals: vec![speed],
+            },
+        ];
+
+        let unmatched = overrides.apply(&mut frames);
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0].name, "missing");
+
+        let current = &frames[0].signals[0];
+        assert_eq!(current.unit.as_deref(), Some("A"));
+        assert_eq!(current.to_engineering(&Value::Int16(-1500)), -1.5);
+        assert_eq!(current.display_name("current"), "current [A]");
+        assert_eq!(current.from_engineering(-1.5), Value::Int16(-1500));
+
+        let speed = &frames[1].signals[0];
+        assert_eq!(speed.to_engineering(&Value::Uint16(100)), 100.0);
+        assert_eq!(speed.max, Some(6000.0));
+        assert_eq!(speed.min, None);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(matches!(SignalOverrides::parse("[[signal]]\nname = 3"), Err(OverridesError::Parse(_))));
+        assert!(SignalOverrides::parse("").unwrap().signals.is_empty());
+    }
+}
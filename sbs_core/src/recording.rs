@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use crate::clock::Timestamp;
+use crate::encode::EncodeError;
+use crate::sbs::{FrameId, SignalFrameDescriptor};
+use crate::schema::Schema;
+use crate::value::SignalFrameValue;
+
+/// Start of every recording, the last byte is the format version
+const MAGIC: &[u8; 8] = b"SBSREC\x00\x01";
+
+/// End of a recording that was finished, preceded by the index offset, entry count and duration
+const TRAILER_MAGIC: &[u8; 8] = b"SBSIDX\x00\x01";
+
+const TRAILER_LEN: u64 = 8 + 4 + 8 + 8;
+
+/// Size of the record header: frame id, ticks, seconds and payload length
+const RECORD_HEADER_LEN: u64 = 24;
+
+const INDEX_ENTRY_LEN: u64 = 16;
+
+/// Number of records between two index entries
+const INDEX_INTERVAL: u64 = 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordingError {
+    Io(String),
+    /// The file is not a recording, or is damaged
+    Format(String),
+    Encode(EncodeError),
+    /// The frame is not part of the frames the recording was created with
+    UnknownFrame(FrameId),
+}
+
+impl Display for RecordingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::Io(e) => write!(f, "Recording I/O error: {e}"),
+            RecordingError::Format(e) => write!(f, "Invalid recording: {e}"),
+            RecordingError::Encode(e) => write!(f, "Failed to encode frame: {e}"),
+            RecordingError::UnknownFrame(id) => write!(f, "Frame {} is not part of the recording", id.0),
+        }
+    }
+}
+
+impl Error for RecordingError {}
+
+impl From<io::Error> for RecordingError {
+    fn from(e: io::Error) -> Self {
+        RecordingError::Io(e.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct IndexEntry {
+    seconds: f64,
+    offset: u64,
+}
+
+struct RecordHeader {
+    frame_id: FrameId,
+    timestamp: Timestamp,
+    len: u64,
+}
+
+impl RecordHeader {
+    fn parse(bytes: &[u8; RECORD_HEADER_LEN as usize]) -> RecordHeader {
+        RecordHeader {
+            frame_id: FrameId(u32::from_le_bytes(bytes[0..4].try_into().unwrap())),
+            timestamp: Timestamp {
+                ticks: u64::from_le_bytes(bytes[4..12].try_into().unwrap()),
+                seconds: f64::from_le_bytes(bytes[12..20].try_into().unwrap()),
+            },
+            len: u32::from_le_bytes(bytes[20..24].try_into().unwrap()) as u64,
+        }
+    }
+}
+
+/// Writes a decoded session: the frame descriptors once, followed by every frame with its timestamp
+///
+/// The file starts with `SBSREC\0\x01`, a u32 LE length and the frames as a JSON [`Schema`]. Each
+/// record is a u32 LE frame id, u64 LE ticks, f64 LE seconds, u32 LE length and the frame encoded
+/// as on the wire. [`RecordingWriter::finish`] appends an index of the time of every 1024th record,
+/// a recording that wasn't finished is still readable, it is indexed when opened.
+pub struct RecordingWriter {
+    file: BufWriter<File>,
+    frames: HashMap<FrameId, SignalFrameDescriptor>,
+    offset: u64,
+    records: u64,
+    index: Vec<IndexEntry>,
+    duration: f64,
+}
+
+impl RecordingWriter {
+    pub fn create(path: impl AsRef<Path>, frames: &[SignalFrameDescriptor]) -> Result<RecordingWriter, RecordingError> {
+        let schema = Schema::from(frames.to_vec()).to_json();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&(schema.len() as u32).to_le_bytes())?;
+        file.write_all(schema.as_bytes())?;
+        file.flush()?;
+
+        Ok(RecordingWriter {
+            file,
+            frames: frames.iter().map(|f| (f.id, f.clone())).collect(),
+            offset: (MAGIC.len() + 4 + schema.len()) as u64,
+            records: 0,
+            index: Vec::new(),
+            duration: 0.0,
+        })
+    }
+
+    pub fn write(&mut self, value: &SignalFrameValue) -> Result<(), RecordingError> {
+        let descriptor = self.frames.get(&value.descriptor.id)
+            .ok_or(RecordingError::UnknownFrame(value.descriptor.id))?;
+        if descriptor.signals.len() != value.data.len() {
+            return Err(RecordingError::Encode(EncodeError::SignalCountMismatch {
+                expected: descriptor.signals.len(),
+                got: value.data.len(),
+            }));
+        }
+        let payload = value.to_bytes().map_err(RecordingError::Encode)?;
+
+        if self.records.is_multiple_of(INDEX_INTERVAL) {
+            self.index.push(IndexEntry { seconds: value.timestamp.seconds, offset: self.offset });
+            // Flushed along with the index, so little is lost if the recording is interrupted
+            self.file.flush()?;
+        }
+
+        self.file.write_all(&value.descriptor.id.0.to_le_bytes())?;
+        self.file.write_all(&value.timestamp.ticks.to_le_bytes())?;
+        self.file.write_all(&value.timestamp.seconds.to_le_bytes())?;
+        self.file.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.file.write_all(&payload)?;
+
+        self.offset += RECORD_HEADER_LEN + payload.len() as u64;
+        self.records += 1;
+        self.duration = self.duration.max(value.timestamp.seconds);
+        Ok(())
+    }
+
+    /// Writes the index and closes the recording
+    pub fn finish(mut self) -> Result<(), RecordingError> {
+        for entry in &self.index {
+            self.file.write_all(&entry.seconds.to_le_bytes())?;
+            self.file.write_all(&entry.offset.to_le_bytes())?;
+        }
+
+        self.file.write_all(&self.offset.to_le_bytes())?;
+        self.file.write_all(&(self.index.len() as u32).to_le_bytes())?;
+        self.file.write_all(&self.duration.to_le_bytes())?;
+        self.file.write_all(TRAILER_MAGIC)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a recording written by [`RecordingWriter`], as an iterator over its frames from the current position
+pub struct RecordingReader {
+    file: BufReader<File>,
+    frames: Vec<SignalFrameDescriptor>,
+    index: Vec<IndexEntry>,
+    records_end: u64,
+    position: u64,
+    duration: f64,
+}
+
+impl RecordingReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<RecordingReader, RecordingError> {
+        let mut file = BufReader::new(File::open(path)?);
+        let file_len = file.get_ref().metadata()?.len();
+
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic).map_err(|_| RecordingError::Format("File is too short".to_string()))?;
+        if &magic != MAGIC {
+            return Err(RecordingError::Format("Not an SBS recording".to_string()));
+        }
+
+        let mut len = [0u8; 4];
+        file.read_exact(&mut len)?;
+        let records_start = (MAGIC.len() + 4) as u64 + u32::from_le_bytes(len) as u64;
+        // Checked before allocating, a damaged length would otherwise allocate up to 4 GiB
+        if records_start > file_len {
+            return Err(RecordingError::Format("Schema is longer than the file".to_string()));
+        }
+
+        let mut schema = vec![0u8; u32::from_le_bytes(len) as usize];
+        file.read_exact(&mut schema)?;
+        let schema = String::from_utf8(schema)
+            .map_err(|e| RecordingError::Format(e.to_string()))
+            .and_then(|s| Schema::parse_json(&s).map_err(|e| RecordingError::Format(e.to_string())))?;
+
+        let mut reader = RecordingReader {
+            file,
+            frames: schema.frames,
+            index: Vec::new(),
+            records_end: file_len,
+            position: records_start,
+            duration: 0.0,
+        };
+
+        if !reader.read_index(records_start, file_len)? {
+            reader.build_index(records_start)?;
+        }
+        reader.file.seek(SeekFrom::Start(records_start))?;
+
+        Ok(reader)
+    }
+
+    pub fn frames(&self) -> &[SignalFrameDescriptor] {
+        &self.frames
+    }
+
+    /// Time in seconds of the last frame
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Moves to the first frame at or after `seconds`
+    pub fn seek(&mut self, seconds: f64) -> Result<(), RecordingError> {
+        let entry = self.index.partition_point(|e| e.seconds <= seconds).saturating_sub(1);
+        self.position = self.index.get(entry).map_or(self.records_end, |e| e.offset);
+        self.file.seek(SeekFrom::Start(self.position))?;
+
+        while let Some(header) = self.read_header()? {
+            if header.timestamp.seconds >= seconds {
+                break;
+            }
+            self.position += RECORD_HEADER_LEN + header.len;
+            self.file.seek_relative(header.len as i64)?;
+        }
+
+        self.file.seek(SeekFrom::Start(self.position))?;
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<Option<SignalFrameValue>, RecordingError> {
+        let Some(header) = self.read_header()? else {
+            return Ok(None);
+        };
+
+        let mut payload = vec![0u8; header.len as usize];
+        self.file.read_exact(&mut payload)?;
+        self.position += RECORD_HEADER_LEN + header.len;
+
+        let descriptor = self.frames.iter()
+            .find(|f| f.id == header.frame_id)
+            .ok_or(RecordingError::UnknownFrame(header.frame_id))?;
+
+        let mut value = SignalFrameValue::new(descriptor.clone());
+        if !value.update_from_bytes(header.timestamp, &payload) {
+            return Err(RecordingError::Format(format!("Frame {} at {} s is too short", header.frame_id.0, header.timestamp.seconds)));
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Reads the header of the record at the current position, `None` at the end of the records
+    fn read_header(&mut self) -> Result<Option<RecordHeader>, RecordingError> {
+        if self.position + RECORD_HEADER_LEN > self.records_end {
+            return Ok(None);
+        }
+
+        let mut bytes = [0u8; RECORD_HEADER_LEN as usize];
+        self.file.read_exact(&mut bytes)?;
+        let header = RecordHeader::parse(&bytes);
+
+        if self.position + RECORD_HEADER_LEN + header.len > self.records_end {
+            return Ok(None);
+        }
+
+        Ok(Some(header))
+    }
+
+    /// Reads the index written by [`RecordingWriter::finish`], returns `false` if there is none
+    fn read_index(&mut self, records_start: u64, file_len: u64) -> Result<bool, RecordingError> {
+        if file_len < records_start + TRAILER_LEN {
+            return Ok(false);
+        }
+
+        let mut trailer = [0u8; TRAILER_LEN as usize];
+        self.file.seek(SeekFrom::Start(file_len - TRAILER_LEN))?;
+        self.file.read_exact(&mut trailer)?;
+        if &trailer[20..28] != TRAILER_MAGIC {
+            return Ok(false);
+        }
+
+        let index_offset = u64::from_le_bytes(trailer[0..8].try_into().unwrap());
+        let entries = u32::from_le_bytes(trailer[8..12].try_into().unwrap()) as u64;
+        let index_end = index_offset.checked_add(entries * INDEX_ENTRY_LEN + TRAILER_LEN);
+        if index_offset < records_start || index_end != Some(file_len) {
+            return Err(RecordingError::Format("Index does not match the file size".to_string()));
+        }
+
+        let mut index = vec![0u8; (entries * INDEX_ENTRY_LEN) as usize];
+        self.file.seek(SeekFrom::Start(index_offset))?;
+        self.file.read_exact(&mut index)?;
+
+        self.index = index.chunks_exact(INDEX_ENTRY_LEN as usize)
+            .map(|entry| IndexEntry {
+                seconds: f64::from_le_bytes(entry[0..8].try_into().unwrap()),
+                offset: u64::from_le_bytes(entry[8..16].try_into().unwrap()),
+            })
+            .collect();
+        self.records_end = index_offset;
+        self.duration = f64::from_le_bytes(trailer[12..20].try_into().unwrap());
+        Ok(true)
+    }
+
+    /// Indexes a recording that wasn't finished, a record cut short by the interruption is left out
+    fn build_index(&mut self, records_start: u64) -> Result<(), RecordingError> {
+        self.position = records_start;
+        self.file.seek(SeekFrom::Start(records_start))?;
+
+        let mut records: u64 = 0;
+        while let Some(header) = self.read_header()? {
+            if records.is_multiple_of(INDEX_INTERVAL) {
+                self.index.push(IndexEntry { seconds: header.timestamp.seconds, offset: self.position });
+            }
+            self.duration = self.duration.max(header.timestamp.seconds);
+
+            self.position += RECORD_HEADER_LEN + header.len;
+            self.file.seek_relative(header.len as i64)?;
+            records += 1;
+        }
+
+        self.records_end = self.position;
+        self.position = records_start;
+        Ok(())
+    }
+}
+
+impl Iterator for RecordingReader {
+    type Item = Result<SignalFrameValue, RecordingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbs::SignalDescriptor;
+    use crate::ty::Type;
+    use crate::value::Value;
+
+    fn frame(id: u32) -> SignalFrameDescriptor {
+        SignalFrameDescriptor {
+            id: FrameId(id),
+            name: format!("frame{id}"),
+            enabled: false,
+            signals: vec![SignalDescriptor::new("x", Type::Int16), SignalDescriptor::new("y", Type::Float32)],
+        }
+    }
+
+    fn value(id: u32, ticks: u64) -> SignalFrameValue {
+        let mut value = SignalFrameValue::new(frame(id));
+        value.timestamp = Timestamp { ticks, seconds: ticks as f64 / 1000.0 };
+        value.data = vec![Value::Int16(ticks as i16), Value::Float32(id as f32)];
+        value
+    }
+
+    fn write_session(path: &Path, finish: bool) {
+        let mut writer = RecordingWriter::create(path, &[frame(1), frame(2)]).unwrap();
+        for ticks in 0..3000 {
+            writer.write(&value(1 + (ticks % 2) as u32, ticks)).unwrap();
+        }
+        assert_eq!(writer.write(&value(3, 0)), Err(RecordingError::UnknownFrame(FrameId(3))));
+
+        if finish {
+            writer.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_and_seeks_recordings() {
+        for finish in [true, false] {
+            let path = std::env::temp_dir().join(format!("sbs_recording_{}_{finish}.sbsrec", std::process::id()));
+            write_session(&path, finish);
+
+            let mut reader = RecordingReader::open(&path).unwrap();
+            assert_eq!(reader.frames(), &[frame(1), frame(2)]);
+            assert_eq!(reader.duration(), 2.999);
+
+            let values = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+            assert_eq!(values.len(), 3000);
+            assert_eq!(values[1001].descriptor.id, FrameId(2));
+            assert_eq!(values[1001].timestamp, Timestamp { ticks: 1001, seconds: 1.001 });
+            assert_eq!(values[1001].data, vec![Value::Int16(1001), Value::Float32(2.0)]);
+
+            reader.seek(2.5).unwrap();
+            assert_eq!(reader.next().unwrap().unwrap().timestamp.ticks, 2500);
+            reader.seek(0.0).unwrap();
+            assert_eq!(reader.next().unwrap().unwrap().timestamp.ticks, 0);
+            reader.seek(10.0).unwrap();
+            assert!(reader.next().is_none());
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_interrupted_recordings() {
+        let path = std::env::temp_dir().join(format!("sbs_recording_{}_cut.sbsrec", std::process::id()));
+        write_session(&path, false);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+
+        let reader = RecordingReader::open(&path).unwrap();
+        assert_eq!(reader.count(), 2999);
+
+        std::fs::write(&path, b"SBSCAP\x00\x01").unwrap();
+        assert!(matches!(RecordingReader::open(&path), Err(RecordingError::Format(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_damaged_lengths() {
+        let path = std::env::temp_dir().join(format!("sbs_recording_{}_damaged.sbsrec", std::process::id()));
+        write_session(&path, true);
+        let bytes = std::fs::read(&path).unwrap();
+
+        let mut damaged = bytes.clone();
+        let trailer = bytes.len() - TRAILER_LEN as usize;
+        damaged[trailer..trailer + 8].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+        std::fs::write(&path, &damaged).unwrap();
+        assert!(matches!(RecordingReader::open(&path), Err(RecordingError::Format(_))));
+
+        let mut damaged = bytes;
+        damaged[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &damaged).unwrap();
+        assert!(matches!(RecordingReader::open(&path), Err(RecordingError::Format(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,8 +1,9 @@
+use crate::error::Error;
+use crate::subscription::{FrameFilter, Subscription, SubscriptionOptions};
 use crate::ty::Type;
-use crate::value::SignalFrameValue;
+use crate::value::{SignalFrameValue, Value};
 use async_trait::async_trait;
-use std::fmt::Debug;
-use std::future::Future;
+use std::fmt::{Debug, Display, Formatter};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct FrameId(pub u32);
@@ -10,7 +11,7 @@ pub struct FrameId(pub u32);
 pub type SignalId = (FrameId, String);
 
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SignalFrameDescriptor {
     pub id: FrameId,
     pub name: String,
@@ -18,10 +19,54 @@ pub struct SignalFrameDescriptor {
     pub signals: Vec<SignalDescriptor>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SignalDescriptor {
     pub name: String,
     pub ty: Type,
+    /// Engineering unit, e.g. `A` or `rpm`
+    pub unit: Option<String>,
+    /// Factor applied to the raw value to get the value in engineering units
+    pub scale: Option<f64>,
+    /// Offset added to the scaled value to get the value in engineering units
+    pub offset: Option<f64>,
+    /// Expected minimum in engineering units
+    pub min: Option<f64>,
+    /// Expected maximum in engineering units
+    pub max: Option<f64>,
+}
+
+impl SignalDescriptor {
+    pub fn new(name: &str, ty: Type) -> SignalDescriptor {
+        SignalDescriptor {
+            name: name.to_string(),
+            ty,
+            unit: None,
+            scale: None,
+            offset: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Converts a raw value of this signal, or one of its leaves, to engineering units
+    pub fn to_engineering(&self, value: &Value) -> f64 {
+        let raw: f64 = value.clone().into();
+        raw * self.scale.unwrap_or(1.0) + self.offset.unwrap_or(0.0)
+    }
+
+    /// Converts a number in engineering units back to a value of this signal, saturating to its type
+    pub fn from_engineering(&self, x: f64) -> Value {
+        let raw = (x - self.offset.unwrap_or(0.0)) / self.scale.unwrap_or(1.0);
+        self.ty.value_from_f64(raw)
+    }
+
+    /// Returns the name with the unit appended, e.g. `current [A]`
+    pub fn display_name(&self, path: &str) -> String {
+        match &self.unit {
+            Some(unit) => format!("{path} [{unit}]"),
+            None => path.to_string(),
+        }
+    }
 }
 
 pub trait SignalFrameCallback: Fn(FrameId, &SignalFrameValue) + Send + Sync {}
@@ -31,13 +76,51 @@ where
     T: Fn(FrameId, &SignalFrameValue) + Send + Sync,
 {}
 
+/// Identifies a callback registered with [`Client::add_callback`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallbackHandle(pub(crate) u64);
+
+/// State of the link between a client and its device
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConnectionState {
+    #[default]
+    Disconnected,
+    Connected,
+    /// The link was lost and is being reopened
+    Reconnecting,
+    /// The link was reopened or the device was reset, and the frames are being discovered and enabled again
+    Recovering,
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionState::Disconnected => write!(f, "Disconnected"),
+            ConnectionState::Connected => write!(f, "Connected"),
+            ConnectionState::Reconnecting => write!(f, "Reconnecting"),
+            ConnectionState::Recovering => write!(f, "Recovering"),
+        }
+    }
+}
+
 #[async_trait]
 pub trait Client {
-    async fn get_frames(&mut self) -> Result<Vec<SignalFrameDescriptor>, String>;
+    async fn get_frames(&mut self) -> Result<Vec<SignalFrameDescriptor>, Error>;
+
+    async fn enable_frame(&mut self, frame_id: FrameId) -> Result<(), Error>;
+    async fn disable_frame(&mut self, frame_id: FrameId) -> Result<(), Error>;
+
+    /// Registers a callback for the frames passing `filter`, the returned handle can be used to remove it again
+    async fn add_callback(&mut self, filter: FrameFilter, cb: Box<dyn SignalFrameCallback>) -> CallbackHandle;
+    /// Removes a callback registered with [`Client::add_callback`], returns `false` if it was already removed
+    async fn remove_callback(&mut self, handle: CallbackHandle) -> bool;
 
-    async fn enable_frame(&mut self, frame_id: FrameId) -> Result<(), String>;
-    async fn disable_frame(&mut self, frame_id: FrameId) -> Result<(), String>;
+    /// Returns a stream of the frames passing `filter`, buffered according to `options`
+    async fn subscribe(&mut self, filter: FrameFilter, options: SubscriptionOptions) -> Subscription;
 
-    async fn add_callback(&mut self, cb: Box<dyn SignalFrameCallback>);
+    /// Disables the frames enabled through this client, removes the callbacks, ends the subscriptions and closes the link
+    async fn disconnect(&mut self) -> Result<(), Error>;
+    /// Current state of the link to the device
+    fn connection_state(&self) -> ConnectionState;
 }
 
@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::sbs::{FrameId, SignalDescriptor, SignalFrameDescriptor};
+use crate::ty::{parse_type_name, Type};
+
+/// Offline description of the frames a device sends, for devices that can't announce their own layout
+///
+/// Schemas are stored as TOML, or as JSON with the same structure:
+///
+/// ```toml
+/// tick_frequency = 10000.0
+///
+/// [[frame]]
+/// id = 1
+/// name = "motor"
+///
+/// [[frame.signal]]
+/// name = "current"
+/// type = "sfix(16,-8)"
+/// unit = "A"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub frames: Vec<SignalFrameDescriptor>,
+    /// Frequency of the device timestamp counter in Hz, if the schema sets it
+    pub tick_frequency: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SchemaFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tick_frequency: Option<f64>,
+    #[serde(default, rename = "frame")]
+    frames: Vec<FrameEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FrameEntry {
+    id: u32,
+    name: String,
+    #[serde(default, rename = "signal")]
+    signals: Vec<SignalEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignalEntry {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    offset: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SchemaError {
+    Io(String),
+    Parse(String),
+    InvalidType { frame: FrameId, signal: String, ty: String },
+    DuplicateFrame(FrameId),
+    InvalidTickFrequency(f64),
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::Io(e) => write!(f, "Failed to read schema: {e}"),
+            SchemaError::Parse(e) => write!(f, "Invalid schema: {e}"),
+            SchemaError::InvalidType { frame, signal, ty } =>
+                write!(f, "Invalid type {ty} for signal {signal} in frame {}", frame.0),
+            SchemaError::DuplicateFrame(id) => write!(f, "Frame {} is defined more than once", id.0),
+            SchemaError::InvalidTickFrequency(tick_frequency) => write!(f, "Invalid tick frequency {tick_frequency}"),
+        }
+    }
+}
+
+impl Error for SchemaError {}
+
+/// Difference between the frames in a schema and the frames a device announces
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaMismatch {
+    /// The frame is in the schema, but the device doesn't announce it
+    MissingFrame(FrameId),
+    /// The device announces a frame that isn't in the schema
+    UnexpectedFrame(FrameId),
+    FrameName { frame: FrameId, schema: String, device: String },
+    /// The signal at `index` differs in name or type, `None` if one side has fewer signals
+    Signal { frame: FrameId, index: usize, schema: Option<String>, device: Option<String> },
+}
+
+impl Display for SchemaMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaMismatch::MissingFrame(id) => write!(f, "Frame {} is in the schema but not on the device", id.0),
+            SchemaMismatch::UnexpectedFrame(id) => write!(f, "Frame {} is on the device but not in the schema", id.0),
+            SchemaMismatch::FrameName { frame, schema, device } =>
+                write!(f, "Frame {} is named {schema} in the schema, but {device} on the device", frame.0),
+            SchemaMismatch::Signal { frame, index, schema, device } =>
+                write!(f, "Signal {index} of frame {} is {} in the schema, but {} on the device",
+                       frame.0,
+                       schema.as_deref().unwrap_or("missing"),
+                       device.as_deref().unwrap_or("missing")),
+        }
+    }
+}
+
+impl Schema {
+    /// Loads a schema, as JSON if the file has a `.json` extension and as TOML otherwise
+    pub fn load(path: impl AsRef<Path>) -> Result<Schema, SchemaError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| SchemaError::Io(e.to_string()))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::parse_json(&content),
+            _ => Self::parse_toml(&content),
+        }
+    }
+
+    pub fn parse_toml(content: &str) -> Result<Schema, SchemaError> {
+        Self::from_file(toml::from_str(content).map_err(|e| SchemaError::Parse(e.to_string()))?)
+    }
+
+    pub fn parse_json(content: &str) -> Result<Schema, SchemaError> {
+        Self::from_file(serde_json::from_str(content).map_err(|e| SchemaError::Parse(e.to_string()))?)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(&self.to_file()).expect("schema is always representable as TOML")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_file()).expect("schema is always representable as JSON")
+    }
+
+    /// Returns how the frames announced by a device differ from the schema
+    ///
+    /// Only the frame names and the signal names and types are compared, metadata such as units is not.
+    pub fn compare(&self, announced: &[SignalFrameDescriptor]) -> Vec<SchemaMismatch> {
+        let mut mismatches = Vec::new();
+
+        for frame in &self.frames {
+            let Some(device_frame) = announced.iter().find(|f| f.id == frame.id) else {
+                mismatches.push(SchemaMismatch::MissingFrame(frame.id));
+                continue;
+            };
+
+            if frame.name != device_frame.name {
+                mismatches.push(SchemaMismatch::FrameName {
+                    frame: frame.id,
+                    schema: frame.name.clone(),
+                    device: device_frame.name.clone(),
+                });
+            }
+
+            let num_signals = frame.signals.len().max(device_frame.signals.len());
+            for index in 0..num_signals {
+                let schema = frame.signals.get(index).map(|s| (&s.name, &s.ty));
+                let device = device_frame.signals.get(index).map(|s| (&s.name, &s.ty));
+
+                if schema != device {
+                    mismatches.push(SchemaMismatch::Signal {
+                        frame: frame.id,
+                        index,
+                        schema: schema.map(|(name, ty)| format!("{name}:{ty}")),
+                        device: device.map(|(name, ty)| format!("{name}:{ty}")),
+                    });
+                }
+            }
+        }
+
+        for frame in announced {
+            if !self.frames.iter().any(|f| f.id == frame.id) {
+                mismatches.push(SchemaMismatch::UnexpectedFrame(frame.id));
+            }
+        }
+
+        mismatches
+    }
+
+    fn from_file(file: SchemaFile) -> Result<Schema, SchemaError> {
+        if let Some(tick_frequency) = file.tick_frequency.filter(|f| !(f.is_finite() && *f > 0.0)) {
+            return Err(SchemaError::InvalidTickFrequency(tick_frequency));
+        }
+
+        let mut ids = HashSet::new();
+        let mut frames = Vec::new();
+
+        for frame in file.frames {
+            let id = FrameId(frame.id);
+            if !ids.insert(id) {
+                return Err(SchemaError::DuplicateFrame(id));
+            }
+
+            let signals = frame.signals.into_iter()
+                .map(|s| {
+                    let ty: Type = parse_type_name(&s.ty).ok_or_else(|| SchemaError::InvalidType {
+                        frame: id,
+                        signal: s.name.clone(),
+                        ty: s.ty.clone(),
+                    })?;
+
+                    Ok(SignalDescriptor {
+                        name: s.name,
+                        ty,
+                        unit: s.unit,
+                        scale: s.scale,
+                        offset: s.offset,
+                        min: s.min,
+                        max: s.max,
+                    })
+                })
+                .collect::<Result<Vec<_>, SchemaError>>()?;
+
+            frames.push(SignalFrameDescriptor {
+                id,
+                name: frame.name,
+                enabled: false,
+                signals,
+            });
+        }
+
+        Ok(Schema { frames, tick_frequency: file.tick_frequency })
+    }
+
+    fn to_file(&self) -> SchemaFile {
+        SchemaFile {
+            tick_frequency: self.tick_frequency,
+            frames: self.frames.iter().map(|frame| FrameEntry {
+                id: frame.id.0,
+                name: frame.name.clone(),
+                signals: frame.signals.iter().map(|s| SignalEntry {
+                    name: s.name.clone(),
+                    ty: s.ty.to_string(),
+                    unit: s.unit.clone(),
+                    scale: s.scale,
+                    offset: s.offset,
+                    min: s.min,
+                    max: s.max,
+                }).collect(),
+            }).collect(),
+        }
+    }
+}
+
+impl From<Vec<SignalFrameDescriptor>> for Schema {
+    fn from(frames: Vec<SignalFrameDescriptor>) -> Self {
+        Schema { frames, tick_frequency: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOTOR_SCHEMA: &str = r#"
+        tick_frequency = 10000.0
+
+        [[frame]]
+        id = 1
+        name = "motor"
+
+        [[frame.signal]]
+        name = "current"
+        type = "sfix(16,-8)"
+        unit = "A"
+
+        [[frame.signal]]
+        name = "state"
+        type = "enum(uint8){0:IDLE,1:RUN}"
+
+        [[frame]]
+        id = 2
+        name = "pos"
+
+        [[frame.signal]]
+        name = "xyz"
+        type = "int32[3]"
+    "#;
+
+    #[test]
+    fn parses_toml() {
+        let schema = Schema::parse_toml(MOTOR_SCHEMA).unwrap();
+
+        assert_eq!(schema.tick_frequency, Some(10000.0));
+        assert_eq!(schema.frames.len(), 2);
+        let motor = &schema.frames[0];
+        assert_eq!(motor.id, FrameId(1));
+        assert_eq!(motor.signals[0].ty, Type::SFix(16, -8));
+        assert_eq!(motor.signals[0].unit.as_deref(), Some("A"));
+        assert_eq!(schema.frames[1].signals[0].ty, Type::Array(Box::new(Type::Int32), 3));
+    }
+
+    #[test]
+    fn round_trips_toml_and_json() {
+        let schema = Schema::parse_toml(MOTOR_SCHEMA).unwrap();
+
+        assert_eq!(Schema::parse_toml(&schema.to_toml()).unwrap(), schema);
+        assert_eq!(Schema::parse_json(&schema.to_json()).unwrap(), schema);
+    }
+
+    #[test]
+    fn rejects_invalid_schemas() {
+        let invalid_type = "[[frame]]\nid = 1\nname = \"a\"\n[[frame.signal]]\nname = \"x\"\ntype = \"int12\"";
+        assert!(matches!(Schema::parse_toml(invalid_type), Err(SchemaError::InvalidType { .. })));
+
+        let duplicate = "[[frame]]\nid = 1\nname = \"a\"\n[[frame]]\nid = 1\nname = \"b\"";
+        assert!(matches!(Schema::parse_toml(duplicate), Err(SchemaError::DuplicateFrame(FrameId(1)))));
+
+        assert!(matches!(Schema::parse_json("{\"frame\": 3}"), Err(SchemaError::Parse(_))));
+        assert!(matches!(Schema::parse_toml("tick_frequency = 0.0"), Err(SchemaError::InvalidTickFrequency(_))));
+    }
+
+    #[test]
+    fn compares_with_announced_frames() {
+        let schema = Schema::parse_toml(MOTOR_SCHEMA).unwrap();
+        assert!(schema.compare(&schema.frames).is_empty());
+
+        let mut announced = schema.frames.clone();
+        announced[0].enabled = true;
+        announced[0].signals[0].unit = None;
+        assert!(schema.compare(&announced).is_empty());
+
+        announced[0].name = "drive".to_string();
+        announced[0].signals[1].ty = Type::Uint8;
+        announced.remove(1);
+        announced.push(SignalFrameDescriptor {
+            id: FrameId(3),
+            name: "extra".to_string(),
+            enabled: false,
+            signals: vec![],
+        });
+
+        assert_eq!(schema.compare(&announced), vec![
+            SchemaMismatch::FrameName { frame: FrameId(1), schema: "motor".to_string(), device: "drive".to_string() },
+            SchemaMismatch::Signal {
+                frame: FrameId(1),
+                index: 1,
+                schema: Some("state:enum(uint8){0:IDLE,1:RUN}".to_string()),
+                device: Some("state:uint8".to_string()),
+            },
+            SchemaMismatch::MissingFrame(FrameId(2)),
+            SchemaMismatch::UnexpectedFrame(FrameId(3)),
+        ]);
+    }
+}
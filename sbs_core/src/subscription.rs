@@ -0,0 +1,228 @@
+use std::collections::{HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+use futures_core::Stream;
+use crate::sbs::FrameId;
+use crate::value::SignalFrameValue;
+
+/// Selects the frames a subscription receives
+#[derive(Clone, Debug, Default)]
+pub enum FrameFilter {
+    #[default]
+    All,
+    Frames(HashSet<FrameId>),
+}
+
+impl FrameFilter {
+    pub fn matches(&self, frame_id: FrameId) -> bool {
+        match self {
+            FrameFilter::All => true,
+            FrameFilter::Frames(ids) => ids.contains(&frame_id),
+        }
+    }
+}
+
+impl<I: IntoIterator<Item=FrameId>> From<I> for FrameFilter {
+    fn from(ids: I) -> Self {
+        FrameFilter::Frames(ids.into_iter().collect())
+    }
+}
+
+/// What to do with a new frame when the subscription buffer is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the oldest buffered frame to make room, so a slow consumer always sees the latest data
+    #[default]
+    DropOldest,
+    /// Discard the new frame, so a slow consumer sees a gap-free prefix of the data
+    DropNewest,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SubscriptionOptions {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for SubscriptionOptions {
+    fn default() -> Self {
+        SubscriptionOptions {
+            capacity: 256,
+            overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    frames: VecDeque<SignalFrameValue>,
+    waker: Option<Waker>,
+    closed: bool,
+    dropped: u64,
+}
+
+/// Stream of decoded frames, created by [`Client::subscribe`](crate::sbs::Client::subscribe)
+///
+/// Dropping the subscription unsubscribes it. The stream ends when the client is disconnected or dropped.
+pub struct Subscription {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl Subscription {
+    /// Number of frames discarded so far because the buffer was full
+    pub fn dropped_count(&self) -> u64 {
+        self.queue.lock().unwrap().dropped
+    }
+}
+
+impl Stream for Subscription {
+    type Item = SignalFrameValue;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut queue = self.queue.lock().unwrap();
+
+        if let Some(frame) = queue.frames.pop_front() {
+            Poll::Ready(Some(frame))
+        } else if queue.closed {
+            Poll::Ready(None)
+        } else {
+            queue.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Producer side of a [`Subscription`]
+pub struct SubscriptionSender {
+    filter: FrameFilter,
+    options: SubscriptionOptions,
+    queue: Weak<Mutex<Queue>>,
+}
+
+impl SubscriptionSender {
+    pub fn new(filter: FrameFilter, options: SubscriptionOptions) -> (SubscriptionSender, Subscription) {
+        let queue = Arc::new(Mutex::new(Queue::default()));
+
+        let sender = SubscriptionSender {
+            filter,
+            options,
+            queue: Arc::downgrade(&queue),
+        };
+
+        (sender, Subscription { queue })
+    }
+
+    /// Queues the frame if it passes the filter, returns `false` once the subscription was dropped
+    pub fn send(&self, frame_id: FrameId, frame: &SignalFrameValue) -> bool {
+        let Some(queue) = self.queue.upgrade() else {
+            return false;
+        };
+
+        if !self.filter.matches(frame_id) {
+            return true;
+        }
+
+        let mut queue = queue.lock().unwrap();
+        if queue.frames.len() >= self.options.capacity {
+            queue.dropped += 1;
+
+            match self.options.overflow {
+                OverflowPolicy::DropOldest => { queue.frames.pop_front(); }
+                OverflowPolicy::DropNewest => return true,
+            }
+        }
+
+        queue.frames.push_back(frame.clone());
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+
+        true
+    }
+}
+
+impl Drop for SubscriptionSender {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.upgrade() {
+            let mut queue = queue.lock().unwrap();
+            queue.closed = true;
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+    use crate::sbs::SignalFrameDescriptor;
+
+    fn frame(id: u32, ticks: u64) -> SignalFrameValue {
+        let mut frame = SignalFrameValue::new(SignalFrameDescriptor {
+            id: FrameId(id),
+            name: format!("frame{id}"),
+            enabled: true,
+            signals: vec![],
+        });
+        frame.timestamp.ticks = ticks;
+        frame
+    }
+
+    fn timestamps(subscription: Subscription) -> Vec<u64> {
+        block_on(subscription.map(|f| f.timestamp.ticks).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn filters_frames() {
+        let (sender, subscription) = SubscriptionSender::new([FrameId(2)].into(), SubscriptionOptions::default());
+
+        for (id, ts) in [(1, 10), (2, 20), (3, 30), (2, 40)] {
+            assert!(sender.send(FrameId(id), &frame(id, ts)));
+        }
+        drop(sender);
+
+        assert_eq!(timestamps(subscription), vec![20, 40]);
+    }
+
+    #[test]
+    fn applies_overflow_policy() {
+        for (overflow, expected) in [(OverflowPolicy::DropOldest, vec![3, 4]), (OverflowPolicy::DropNewest, vec![1, 2])] {
+            let (sender, subscription) = SubscriptionSender::new(FrameFilter::All, SubscriptionOptions { capacity: 2, overflow });
+
+            for ts in 1..=4 {
+                sender.send(FrameId(1), &frame(1, ts));
+            }
+            drop(sender);
+
+            assert_eq!(subscription.dropped_count(), 2);
+            assert_eq!(timestamps(subscription), expected);
+        }
+    }
+
+    #[test]
+    fn unsubscribes_on_drop() {
+        let (sender, subscription) = SubscriptionSender::new(FrameFilter::All, SubscriptionOptions::default());
+        assert!(sender.send(FrameId(1), &frame(1, 1)));
+
+        drop(subscription);
+        assert!(!sender.send(FrameId(1), &frame(1, 2)));
+    }
+
+    #[test]
+    fn wakes_pending_consumer() {
+        let (sender, mut subscription) = SubscriptionSender::new(FrameFilter::All, SubscriptionOptions::default());
+
+        let producer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            sender.send(FrameId(1), &frame(1, 7));
+        });
+
+        assert_eq!(block_on(subscription.next()).map(|f| f.timestamp.ticks), Some(7));
+        producer.join().unwrap();
+        assert!(block_on(subscription.next()).is_none());
+    }
+}
@@ -1,7 +1,9 @@
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use std::sync::OnceLock;
 use regex::Regex;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Type {
     Uint8,
     Uint16,
@@ -12,37 +14,499 @@ pub enum Type {
     Float32,
     SFix(u32, i32),
     UFix(u32, i32),
+    Array(Box<Type>, usize),
+    Struct(Vec<StructField>),
+    /// Packed bit fields in an unsigned container of the given bit width, allocated LSB first
+    Bits(u32, Vec<BitField>),
+    /// Integer with a table of labels for known values, e.g. the state of a state machine
+    Enum(Box<Type>, Vec<EnumLabel>),
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructField {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitField {
+    pub name: String,
+    pub width: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnumLabel {
+    pub value: i64,
+    pub label: String,
+}
+
+impl Type {
+    /// Returns the plottable scalar elements of a value of this type, with their paths relative to `name`
+    ///
+    /// Array elements are addressed as `name[i]`, struct and bit fields as `name.field`. Bit fields
+    /// are reported as `ufix(width, 0)`, which is exactly how they are decoded.
+    pub fn leaves(&self, name: &str) -> Vec<(String, Type)> {
+        let mut result = Vec::new();
+        self.collect_leaves(name.to_string(), &mut result);
+        result
+    }
+
+    fn collect_leaves(&self, path: String, out: &mut Vec<(String, Type)>) {
+        match self {
+            Type::Array(inner, len) =>
+                for i in 0..*len {
+                    inner.collect_leaves(format!("{path}[{i}]"), out);
+                },
+            Type::Struct(fields) =>
+                for field in fields {
+                    field.ty.collect_leaves(format!("{path}.{}", field.name), out);
+                },
+            Type::Bits(_, fields) =>
+                for field in fields {
+                    out.push((format!("{path}.{}", field.name), Type::UFix(field.width, 0)));
+                },
+            _ => out.push((path, self.clone())),
+        }
+    }
+
+    pub fn is_scalar(&self) -> bool {
+        !matches!(self, Type::Array(..) | Type::Struct(_) | Type::Bits(..))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Uint8 | Type::Uint16 | Type::Uint32 | Type::Int8 | Type::Int16 | Type::Int32)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Uint8 => write!(f, "uint8"),
+            Type::Uint16 => write!(f, "uint16"),
+            Type::Uint32 => write!(f, "uint32"),
+            Type::Int8 => write!(f, "int8"),
+            Type::Int16 => write!(f, "int16"),
+            Type::Int32 => write!(f, "int32"),
+            Type::Float32 => write!(f, "float32"),
+            Type::SFix(w, e) => write!(f, "sfix({w},{e})"),
+            Type::UFix(w, e) => write!(f, "ufix({w},{e})"),
+            Type::Array(inner, len) => write!(f, "{inner}[{len}]"),
+            Type::Struct(fields) => {
+                let fields = fields.iter()
+                    .map(|field| format!("{}:{}", field.name, field.ty))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, "struct{{{fields}}}")
+            }
+            Type::Bits(width, fields) => {
+                let fields = fields.iter()
+                    .map(|field| format!("{}:{}", field.name, field.width))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, "bits(u{width}){{{fields}}}")
+            }
+            Type::Enum(base, labels) => {
+                let labels = labels.iter()
+                    .map(|label| format!("{}:{}", label.value, label.label))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, "enum({base}){{{labels}}}")
+            }
+        }
+    }
+}
+
+/// Parses a type name as announced by a device, e.g. `int16`, `sfix(16,-8)`, `int16[3]`,
+/// `struct{x:int16,y:int16}`, `bits(u16){fault:1,mode:3}` or `enum(uint8){0:IDLE,1:RUN}`
+///
+/// Type names come from the device, so types with more than [`MAX_ELEMENTS`] scalar elements or
+/// nested deeper than [`MAX_NESTING_DEPTH`] are rejected rather than allocated.
 pub fn parse_type_name(ty_name: &str) -> Option<Type> {
-    let re = Regex::new(r"^(?<named>(uint8)|(uint16)|(uint32)|(int8)|(int16)|(int32)|(float32))|((?<fix_base>(ufix)|(sfix))\((?<fix_wlen>[0-9]+),( )*(?<fix_exp>-?[0-9]+)\))$").unwrap();
-
-    match re.captures(ty_name) {
-        Some(caps) => {
-            if caps.name("named").is_some() {
-                match caps.name("named").unwrap().as_str() {
-                    "uint8" => Some(Type::Uint8),
-                    "uint16" => Some(Type::Uint16),
-                    "uint32" => Some(Type::Uint32),
-                    "int8" => Some(Type::Int8),
-                    "int16" => Some(Type::Int16),
-                    "int32" => Some(Type::Int32),
-                    "float32" => Some(Type::Float32),
-                    _ => None
-                }
-            } else if caps.name("fix_base").is_some() {
-                let wlen = u32::from_str(caps.name("fix_wlen").unwrap().as_str()).ok()?;
-                let exp = i32::from_str(caps.name("fix_exp").unwrap().as_str()).ok()?;
-
-                match caps.name("fix_base").unwrap().as_str() {
-                    "sfix" => Some(Type::SFix(wlen, exp)),
-                    "ufix" => Some(Type::UFix(wlen, exp)),
-                    _ => None
-                }
-            } else {
-                None
-            }
-        }
-        None => None
+    let mut parser = TypeParser { rest: ty_name, depth: 0 };
+    let ty = parser.parse_type()?;
+
+    parser.skip_whitespace();
+    if parser.rest.is_empty() {
+        Some(ty)
+    } else {
+        None
+    }
+}
+
+fn scalar_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(?:(?<named>uint8|uint16|uint32|int8|int16|int32|float32)|(?<fix_base>ufix|sfix)\(\s*(?<fix_wlen>[0-9]+)\s*,\s*(?<fix_exp>-?[0-9]+)\s*\))").unwrap())
+}
+
+/// Most scalar elements in a value of one type, arrays included
+pub const MAX_ELEMENTS: usize = 65_536;
+
+/// Most levels of arrays and structs nested in each other
+pub const MAX_NESTING_DEPTH: usize = 32;
+
+/// Number of scalar elements in a value of `ty`, saturating on overflow
+fn element_count(ty: &Type) -> usize {
+    match ty {
+        Type::Array(inner, len) => element_count(inner).saturating_mul(*len),
+        Type::Struct(fields) => fields.iter().fold(0, |count, field| count.saturating_add(element_count(&field.ty))),
+        _ => 1,
+    }
+}
+
+struct TypeParser<'s> {
+    rest: &'s str,
+    /// Types being parsed that contain the current one
+    depth: usize,
+}
+
+impl<'s> TypeParser<'s> {
+    fn parse_type(&mut self) -> Option<Type> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return None;
+        }
+
+        self.depth += 1;
+        let ty = self.parse_nested_type();
+        self.depth -= 1;
+
+        ty
+    }
+
+    fn parse_nested_type(&mut self) -> Option<Type> {
+        self.skip_whitespace();
+
+        let mut ty = if self.rest.starts_with("struct") {
+            self.parse_struct()?
+        } else if self.rest.starts_with("bits") {
+            self.parse_bits()?
+        } else if self.rest.starts_with("enum") {
+            self.parse_enum()?
+        } else {
+            self.parse_scalar()?
+        };
+
+        // Array suffixes, applied left to right so that `int8[2][3]` is three arrays of two elements
+        let mut arrays = 0;
+        while self.eat('[') {
+            let len = self.parse_uint()?;
+            if len == 0 || !self.eat(']') {
+                return None;
+            }
+
+            arrays += 1;
+            let len = usize::try_from(len).ok()?;
+            if self.depth + arrays > MAX_NESTING_DEPTH || element_count(&ty).checked_mul(len)? > MAX_ELEMENTS {
+                return None;
+            }
+
+            ty = Type::Array(Box::new(ty), len);
+        }
+
+        if element_count(&ty) > MAX_ELEMENTS {
+            return None;
+        }
+
+        Some(ty)
+    }
+
+    fn parse_scalar(&mut self) -> Option<Type> {
+        let caps = scalar_regex().captures(self.rest)?;
+        self.rest = &self.rest[caps.get(0).unwrap().end()..];
+
+        if let Some(named) = caps.name("named") {
+            match named.as_str() {
+                "uint8" => Some(Type::Uint8),
+                "uint16" => Some(Type::Uint16),
+                "uint32" => Some(Type::Uint32),
+                "int8" => Some(Type::Int8),
+                "int16" => Some(Type::Int16),
+                "int32" => Some(Type::Int32),
+                "float32" => Some(Type::Float32),
+                _ => None
+            }
+        } else {
+            let wlen = u32::from_str(caps.name("fix_wlen")?.as_str()).ok()?;
+            let exp = i32::from_str(caps.name("fix_exp")?.as_str()).ok()?;
+            // Fixed-point values are decoded from containers of at most 64 bits
+            if !(1..=64).contains(&wlen) {
+                return None;
+            }
+
+            match caps.name("fix_base")?.as_str() {
+                "sfix" => Some(Type::SFix(wlen, exp)),
+                "ufix" => Some(Type::UFix(wlen, exp)),
+                _ => None
+            }
+        }
+    }
+
+    fn parse_struct(&mut self) -> Option<Type> {
+        self.rest = self.rest.strip_prefix("struct")?;
+        if !self.eat('{') {
+            return None;
+        }
+
+        let mut fields = Vec::new();
+        loop {
+            let name = self.parse_ident()?;
+            if !self.eat(':') {
+                return None;
+            }
+            let ty = self.parse_type()?;
+            fields.push(StructField { name, ty });
+
+            if !self.eat(',') {
+                break;
+            }
+        }
+
+        if self.eat('}') {
+            Some(Type::Struct(fields))
+        } else {
+            None
+        }
+    }
+
+    fn parse_bits(&mut self) -> Option<Type> {
+        self.rest = self.rest.strip_prefix("bits")?;
+        if !self.eat('(') {
+            return None;
+        }
+
+        self.skip_whitespace();
+        let width = match self.parse_ident()?.as_str() {
+            "u8" | "uint8" => 8,
+            "u16" | "uint16" => 16,
+            "u32" | "uint32" => 32,
+            _ => return None,
+        };
+        if !self.eat(')') || !self.eat('{') {
+            return None;
+        }
+
+        let mut fields = Vec::new();
+        loop {
+            let name = self.parse_ident()?;
+            if !self.eat(':') {
+                return None;
+            }
+            let field_width = u32::try_from(self.parse_uint()?).ok()?;
+            if field_width == 0 || field_width > width {
+                return None;
+            }
+            fields.push(BitField { name, width: field_width });
+
+            if !self.eat(',') {
+                break;
+            }
+        }
+
+        if !self.eat('}') || fields.iter().map(|f| f.width).sum::<u32>() > width {
+            return None;
+        }
+
+        Some(Type::Bits(width, fields))
+    }
+
+    fn parse_enum(&mut self) -> Option<Type> {
+        self.rest = self.rest.strip_prefix("enum")?;
+        if !self.eat('(') {
+            return None;
+        }
+
+        self.skip_whitespace();
+        let base = self.parse_scalar()?;
+        if !base.is_integer() || !self.eat(')') || !self.eat('{') {
+            return None;
+        }
+
+        let mut labels = Vec::new();
+        loop {
+            let value = self.parse_int()?;
+            if !self.eat(':') {
+                return None;
+            }
+            let label = self.parse_ident()?;
+            labels.push(EnumLabel { value, label });
+
+            if !self.eat(',') {
+                break;
+            }
+        }
+
+        if self.eat('}') {
+            Some(Type::Enum(Box::new(base), labels))
+        } else {
+            None
+        }
+    }
+
+    fn parse_ident(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let len = self.rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest.len());
+
+        if len == 0 || self.rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let (ident, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(ident.to_string())
+    }
+
+    fn parse_uint(&mut self) -> Option<u64> {
+        self.skip_whitespace();
+        let len = self.rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+
+        let (digits, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        u64::from_str(digits).ok()
+    }
+
+    fn parse_int(&mut self) -> Option<i64> {
+        let negative = self.eat('-');
+        let magnitude = i64::try_from(self.parse_uint()?).ok()?;
+
+        Some(if negative { -magnitude } else { magnitude })
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scalars() {
+        assert_eq!(parse_type_name("uint8"), Some(Type::Uint8));
+        assert_eq!(parse_type_name("float32"), Some(Type::Float32));
+        assert_eq!(parse_type_name("sfix(16, -8)"), Some(Type::SFix(16, -8)));
+        assert_eq!(parse_type_name("ufix(32,4)"), Some(Type::UFix(32, 4)));
+        assert_eq!(parse_type_name("ufix(64,0)"), Some(Type::UFix(64, 0)));
+        assert_eq!(parse_type_name("sfix(0,-8)"), None);
+        assert_eq!(parse_type_name("ufix(65,0)"), None);
+        assert_eq!(parse_type_name("uint8x"), None);
+        assert_eq!(parse_type_name("int64"), None);
+    }
+
+    #[test]
+    fn parses_arrays() {
+        assert_eq!(parse_type_name("int16[3]"), Some(Type::Array(Box::new(Type::Int16), 3)));
+        assert_eq!(
+            parse_type_name("sfix(16,-8)[2][4]"),
+            Some(Type::Array(Box::new(Type::Array(Box::new(Type::SFix(16, -8)), 2)), 4)),
+        );
+        assert_eq!(parse_type_name("int16[0]"), None);
+        assert_eq!(parse_type_name("int16[3"), None);
+
+        // Sizes a device can't have are rejected instead of allocated
+        assert_eq!(parse_type_name("uint8[99999999999]"), None);
+        assert_eq!(parse_type_name("uint8[1000][1000]"), None);
+        assert_eq!(parse_type_name(&format!("uint8{}", "[1]".repeat(MAX_NESTING_DEPTH))), None);
+        assert!(parse_type_name(&format!("uint8{}", "[1]".repeat(MAX_NESTING_DEPTH - 1))).is_some());
+    }
+
+    #[test]
+    fn parses_structs() {
+        assert_eq!(
+            parse_type_name("struct{x:int16, y:struct{a:uint8,b:float32[2]}}"),
+            Some(Type::Struct(vec![
+                StructField { name: "x".to_string(), ty: Type::Int16 },
+                StructField {
+                    name: "y".to_string(),
+                    ty: Type::Struct(vec![
+                        StructField { name: "a".to_string(), ty: Type::Uint8 },
+                        StructField { name: "b".to_string(), ty: Type::Array(Box::new(Type::Float32), 2) },
+                    ]),
+                },
+            ])),
+        );
+        assert_eq!(parse_type_name("struct{}"), None);
+        assert_eq!(parse_type_name("struct{x int16}"), None);
+
+        let nested = |depth| format!("{}uint8{}", "struct{a:".repeat(depth), "}".repeat(depth));
+        assert!(parse_type_name(&nested(MAX_NESTING_DEPTH - 1)).is_some());
+        assert_eq!(parse_type_name(&nested(MAX_NESTING_DEPTH)), None);
+        assert_eq!(parse_type_name(&nested(100_000)), None);
+        assert_eq!(parse_type_name("struct{a:uint8[60000],b:uint8[60000]}"), None);
+    }
+
+    #[test]
+    fn parses_bits() {
+        assert_eq!(
+            parse_type_name("bits(u16){fault:1,mode:3}"),
+            Some(Type::Bits(16, vec![
+                BitField { name: "fault".to_string(), width: 1 },
+                BitField { name: "mode".to_string(), width: 3 },
+            ])),
+        );
+        assert_eq!(parse_type_name("bits(u8){a:4,b:5}"), None);
+        assert_eq!(parse_type_name("bits(u12){a:4}"), None);
+        assert_eq!(parse_type_name("bits(u8){a:0}"), None);
+        assert_eq!(parse_type_name("bits(u8){a:4294967295,b:4294967295}"), None);
+    }
+
+    #[test]
+    fn parses_enums() {
+        assert_eq!(
+            parse_type_name("enum(uint8){0:IDLE, 1:RUN, 2:FAULT}"),
+            Some(Type::Enum(Box::new(Type::Uint8), vec![
+                EnumLabel { value: 0, label: "IDLE".to_string() },
+                EnumLabel { value: 1, label: "RUN".to_string() },
+                EnumLabel { value: 2, label: "FAULT".to_string() },
+            ])),
+        );
+        assert_eq!(
+            parse_type_name("enum(int16){-1:REVERSE,1:FORWARD}"),
+            Some(Type::Enum(Box::new(Type::Int16), vec![
+                EnumLabel { value: -1, label: "REVERSE".to_string() },
+                EnumLabel { value: 1, label: "FORWARD".to_string() },
+            ])),
+        );
+        assert_eq!(parse_type_name("enum(float32){0:A}"), None);
+        assert_eq!(parse_type_name("enum(uint8){A:0}"), None);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for name in [
+            "uint16", "sfix(16,-8)", "int16[3]", "struct{x:int16,y:ufix(12,0)[2]}",
+            "bits(u32){fault:1,mode:3}", "struct{status:bits(u8){a:1},v:float32}[4]",
+            "enum(uint8){0:IDLE,1:RUN,2:FAULT}", "enum(int8){-2:LOW,2:HIGH}[2]",
+        ] {
+            let ty = parse_type_name(name).unwrap();
+            assert_eq!(ty.to_string(), name);
+            assert_eq!(parse_type_name(&ty.to_string()), Some(ty));
+        }
+    }
+
+    #[test]
+    fn lists_leaves() {
+        let ty = parse_type_name("struct{acc:int16[2],status:bits(u8){fault:1,mode:3}}").unwrap();
+        assert_eq!(ty.leaves("imu"), vec![
+            ("imu.acc[0]".to_string(), Type::Int16),
+            ("imu.acc[1]".to_string(), Type::Int16),
+            ("imu.status.fault".to_string(), Type::UFix(1, 0)),
+            ("imu.status.mode".to_string(), Type::UFix(3, 0)),
+        ]);
+        assert_eq!(Type::Float32.leaves("x"), vec![("x".to_string(), Type::Float32)]);
     }
 }
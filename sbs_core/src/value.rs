@@ -1,9 +1,11 @@
 use std::fmt::{Display, Formatter};
+use crate::clock::Timestamp;
 use crate::decode::BinaryReader;
+use crate::encode::{BinaryWriter, EncodeError};
 use crate::sbs::SignalFrameDescriptor;
-use crate::ty::Type;
+use crate::ty::{BitField, EnumLabel, Type};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Uint8(u8),
     Uint16(u16),
@@ -14,6 +16,12 @@ pub enum Value {
     Float32(f32),
     SFix { w: u32, e: i32, raw: i64 },
     UFix { w: u32, e: i32, raw: u64 },
+    Array(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+    /// Bit field values, each as a `UFix` of the field width, along with the raw container word
+    Bits { raw: u64, fields: Vec<(String, Value)> },
+    /// Raw integer of an enumerated type, with its label if the value is known
+    Enum { raw: i64, label: Option<String> },
 }
 
 impl Type {
@@ -28,31 +36,166 @@ impl Type {
             Type::Float32 => Value::Float32(0.0),
             Type::SFix(w, e) => Value::SFix { w: *w, e: *e, raw: 0 },
             Type::UFix(w, e) => Value::UFix { w: *w, e: *e, raw: 0 },
+            Type::Array(inner, len) => Value::Array(vec![inner.default_value(); *len]),
+            Type::Struct(fields) => Value::Struct(fields.iter()
+                .map(|field| (field.name.clone(), field.ty.default_value()))
+                .collect()),
+            Type::Bits(_, fields) => Value::Bits {
+                raw: 0,
+                fields: fields.iter()
+                    .map(|field| (field.name.clone(), Value::UFix { w: field.width, e: 0, raw: 0 }))
+                    .collect(),
+            },
+            Type::Enum(_, labels) => Type::enum_value(labels, 0),
+        }
+    }
+
+    /// Builds a value of an integer type from `raw`, or `None` if it doesn't fit
+    pub fn integer_value(&self, raw: i64) -> Option<Value> {
+        match self {
+            Type::Uint8 => u8::try_from(raw).ok().map(Value::Uint8),
+            Type::Uint16 => u16::try_from(raw).ok().map(Value::Uint16),
+            Type::Uint32 => u32::try_from(raw).ok().map(Value::Uint32),
+            Type::Int8 => i8::try_from(raw).ok().map(Value::Int8),
+            Type::Int16 => i16::try_from(raw).ok().map(Value::Int16),
+            Type::Int32 => i32::try_from(raw).ok().map(Value::Int32),
+            _ => None
+        }
+    }
+
+    /// Builds a bit field value from the raw container word, fields are taken from the least significant bit up
+    pub fn bits_value(fields: &[BitField], raw: u64) -> Value {
+        let mut offset = 0;
+        let fields = fields.iter()
+            .map(|field| {
+                let value = (raw >> offset) & (u64::MAX >> (64 - field.width));
+                offset += field.width;
+                (field.name.clone(), Value::UFix { w: field.width, e: 0, raw: value })
+            })
+            .collect();
+
+        Value::Bits { raw, fields }
+    }
+
+    /// Builds a value of this type from a number, rounding and saturating to the range of the type
+    ///
+    /// Every element of an array or struct gets the same value. NaN becomes zero.
+    pub fn value_from_f64(&self, x: f64) -> Value {
+        let x = if x.is_nan() { 0.0 } else { x };
+        let saturate = |min: f64, max: f64| x.round().clamp(min, max);
+
+        match self {
+            Type::Uint8 => Value::Uint8(saturate(0.0, u8::MAX as f64) as u8),
+            Type::Uint16 => Value::Uint16(saturate(0.0, u16::MAX as f64) as u16),
+            Type::Uint32 => Value::Uint32(saturate(0.0, u32::MAX as f64) as u32),
+            Type::Int8 => Value::Int8(saturate(i8::MIN as f64, i8::MAX as f64) as i8),
+            Type::Int16 => Value::Int16(saturate(i16::MIN as f64, i16::MAX as f64) as i16),
+            Type::Int32 => Value::Int32(saturate(i32::MIN as f64, i32::MAX as f64) as i32),
+            Type::Float32 => Value::Float32(x as f32),
+            Type::SFix(w, e) => {
+                let limit = 2f64.powi(*w as i32 - 1);
+                let raw = (x / 2f64.powi(*e)).round().clamp(-limit, limit - 1.0);
+                Value::SFix { w: *w, e: *e, raw: raw as i64 }
+            }
+            Type::UFix(w, e) => {
+                let limit = 2f64.powi(*w as i32);
+                let raw = (x / 2f64.powi(*e)).round().clamp(0.0, limit - 1.0);
+                Value::UFix { w: *w, e: *e, raw: raw as u64 }
+            }
+            Type::Array(inner, len) => Value::Array(vec![inner.value_from_f64(x); *len]),
+            Type::Struct(fields) => Value::Struct(fields.iter()
+                .map(|field| (field.name.clone(), field.ty.value_from_f64(x)))
+                .collect()),
+            Type::Bits(width, fields) =>
+                Type::bits_value(fields, saturate(0.0, 2f64.powi(*width as i32) - 1.0) as u64),
+            Type::Enum(base, labels) => {
+                let raw = base.value_from_f64(x).as_i64().unwrap_or_default();
+                Type::enum_value(labels, raw)
+            }
+        }
+    }
+
+    /// Builds an enum value for `raw`, looking up its label in `labels`
+    pub fn enum_value(labels: &[EnumLabel], raw: i64) -> Value {
+        Value::Enum {
+            raw,
+            label: labels.iter().find(|l| l.value == raw).map(|l| l.label.clone()),
         }
     }
 }
 
+impl Value {
+    /// Returns the value of an integer or enum as an `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Uint8(v) => Some(*v as i64),
+            Value::Uint16(v) => Some(*v as i64),
+            Value::Uint32(v) => Some(*v as i64),
+            Value::Int8(v) => Some(*v as i64),
+            Value::Int16(v) => Some(*v as i64),
+            Value::Int32(v) => Some(*v as i64),
+            Value::Enum { raw, .. } => Some(*raw),
+            _ => None
+        }
+    }
+
+    /// Returns the scalar elements of this value with their paths relative to `name`, in the same
+    /// order and with the same paths as [`Type::leaves`]
+    pub fn leaves(&self, name: &str) -> Vec<(String, Value)> {
+        let mut result = Vec::new();
+        self.collect_leaves(name.to_string(), &mut result);
+        result
+    }
+
+    fn collect_leaves(&self, path: String, out: &mut Vec<(String, Value)>) {
+        match self {
+            Value::Array(values) =>
+                for (i, value) in values.iter().enumerate() {
+                    value.collect_leaves(format!("{path}[{i}]"), out);
+                },
+            Value::Struct(fields) | Value::Bits { fields, .. } =>
+                for (name, value) in fields {
+                    value.collect_leaves(format!("{path}.{name}"), out);
+                },
+            _ => out.push((path, self.clone())),
+        }
+    }
+}
+
+/// Scales a fixed-point raw value by 2^e
+fn fix_to_f64(raw: f64, e: i32) -> f64 {
+    raw * 2f64.powi(e)
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Value::UFix { e, raw, .. } => {
-                let mut approx = *raw as f64;
-                if *e < 0 {
-                    approx /= (2 << (-*e - 1)) as f64;
-                } else if *e > 0 {
-                    approx *= (2 << (e - 1)) as f64;
-                }
-
-                write!(f, "{}", approx)
+            Value::Uint8(v) => write!(f, "{v}"),
+            Value::Uint16(v) => write!(f, "{v}"),
+            Value::Uint32(v) => write!(f, "{v}"),
+            Value::Int8(v) => write!(f, "{v}"),
+            Value::Int16(v) => write!(f, "{v}"),
+            Value::Int32(v) => write!(f, "{v}"),
+            Value::Float32(v) => write!(f, "{v}"),
+            Value::SFix { e, raw, .. } => write!(f, "{}", fix_to_f64(*raw as f64, *e)),
+            Value::UFix { e, raw, .. } => write!(f, "{}", fix_to_f64(*raw as f64, *e)),
+            Value::Array(values) => {
+                let values = values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+                write!(f, "[{values}]")
+            }
+            Value::Enum { label: Some(label), .. } => write!(f, "{label}"),
+            Value::Enum { raw, label: None } => write!(f, "{raw}"),
+            Value::Struct(fields) | Value::Bits { fields, .. } => {
+                let fields = fields.iter().map(|(name, v)| format!("{name}={v}")).collect::<Vec<_>>().join(", ");
+                write!(f, "{{{fields}}}")
             }
-            _ => todo!()
         }
     }
 }
 
-impl Into<f64> for Value {
-    fn into(self) -> f64 {
-        match self {
+impl From<Value> for f64 {
+    fn from(value: Value) -> f64 {
+        match value {
             Value::Uint8(v) => v as f64,
             Value::Uint16(v) => v as f64,
             Value::Uint32(v) => v as f64,
@@ -60,17 +203,12 @@ impl Into<f64> for Value {
             Value::Int16(v) => v as f64,
             Value::Int32(v) => v as f64,
             Value::Float32(v) => v as f64,
-            Value::SFix { .. } => todo!(),
-            Value::UFix { e, raw, .. } => {
-                let mut approx = raw as f64;
-                if e < 0 {
-                    approx /= (2 << (-e - 1)) as f64
-                } else if e > 0 {
-                    approx *= (2 << (e - 1)) as f64
-                }
-
-                approx
-            }
+            Value::SFix { e, raw, .. } => fix_to_f64(raw as f64, e),
+            Value::UFix { e, raw, .. } => fix_to_f64(raw as f64, e),
+            Value::Bits { raw, .. } => raw as f64,
+            Value::Enum { raw, .. } => raw as f64,
+            // Compound values have no single numeric value, their leaves should be plotted instead
+            Value::Array(_) | Value::Struct(_) => f64::NAN,
         }
     }
 }
@@ -79,7 +217,7 @@ impl Into<f64> for Value {
 #[derive(Clone, Debug)]
 pub struct SignalFrameValue {
     pub descriptor: SignalFrameDescriptor,
-    pub timestamp: u32,
+    pub timestamp: Timestamp,
     pub data: Vec<Value>,
 }
 
@@ -89,12 +227,12 @@ impl SignalFrameValue {
 
         SignalFrameValue {
             descriptor,
-            timestamp: 0,
+            timestamp: Timestamp::default(),
             data,
         }
     }
 
-    pub fn update_from_bytes(&mut self, timestamp: u32, bytes: &[u8]) -> bool {
+    pub fn update_from_bytes(&mut self, timestamp: Timestamp, bytes: &[u8]) -> bool {
         self.timestamp = timestamp;
 
         let mut reader = BinaryReader::new(bytes);
@@ -108,6 +246,23 @@ impl SignalFrameValue {
 
         true
     }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        if self.data.len() != self.descriptor.signals.len() {
+            return Err(EncodeError::SignalCountMismatch {
+                expected: self.descriptor.signals.len(),
+                got: self.data.len(),
+            });
+        }
+
+        let mut writer = BinaryWriter::new();
+
+        for (signal, value) in self.descriptor.signals.iter().zip(&self.data) {
+            signal.ty.encode_value(value, &mut writer)?;
+        }
+
+        Ok(writer.into_bytes())
+    }
 }
 
 impl Display for SignalFrameValue {
@@ -125,9 +280,9 @@ impl Display for SignalFrameValue {
 
 
         if signal_values.is_empty() {
-            write!(f, "{}(t={})", self.descriptor.name, self.timestamp)
+            write!(f, "{}(t={})", self.descriptor.name, self.timestamp.ticks)
         } else {
-            write!(f, "{}(t={}, {})", self.descriptor.name, self.timestamp, signal_values)
+            write!(f, "{}(t={}, {})", self.descriptor.name, self.timestamp.ticks, signal_values)
         }
     }
 }
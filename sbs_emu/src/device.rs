@@ -0,0 +1,310 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use sbs_core::sbs::FrameId;
+use sbs_sim::simulation::Simulation;
+use crate::encoder::{data_frame_payload, encode_frame, frame_info_payload, list_frames_payload, nack_payload,
+                     FRAME_END, FRAME_START, NACK_MALFORMED, NACK_UNKNOWN_COMMAND, NACK_UNKNOWN_FRAME, SEQUENCE_PREFIX};
+
+/// Longest time between checks for due frames when no host data arrives
+const MAX_WAIT: Duration = Duration::from_millis(10);
+
+/// Longest payload of a command frame, a sequence number and a command with a frame ID
+const MAX_COMMAND_LEN: usize = 8;
+
+/// Device side of the SBS protocol, answering host commands and sending the enabled frames of a simulation
+pub struct Device {
+    sim: Simulation,
+    rx_buf: Vec<u8>,
+}
+
+impl Device {
+    pub fn new(sim: Simulation) -> Device {
+        Device {
+            sim,
+            rx_buf: Vec::new(),
+        }
+    }
+
+    /// Processes bytes received from the host, returns the encoded response frames
+    ///
+    /// Unknown bytes are skipped, and commands for unknown frames are ignored like the
+    /// firmware does. A command prefixed with `#` and a sequence number gets a response
+    /// with the same prefix. Commands may also come wrapped in a frame like the responses,
+    /// a command frame that can't be taken is answered with a NACK.
+    pub fn handle_input(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.rx_buf.extend_from_slice(data);
+        let mut tx = Vec::new();
+        let start_word = FRAME_START.to_le_bytes();
+
+        loop {
+            let consumed = if self.rx_buf.starts_with(&start_word) {
+                match self.handle_command_frame(&mut tx) {
+                    Some(consumed) => consumed,
+                    None => break,
+                }
+            } else if !self.rx_buf.is_empty() && start_word.starts_with(&self.rx_buf) {
+                // Wait for the rest of the start word
+                break;
+            } else {
+                match parse_command(&self.rx_buf) {
+                    Parsed::Incomplete => break,
+                    Parsed::Invalid => 1,
+                    Parsed::Command { len, seq, cmd, frame_id } => {
+                        if let Some(response) = self.respond(cmd, frame_id) {
+                            tx.push(encode_response(seq, response));
+                        }
+                        len
+                    }
+                }
+            };
+
+            self.rx_buf.drain(..consumed);
+        }
+
+        tx
+    }
+
+    /// Answers the command frame at the start of the input, returns its length or None if it's incomplete
+    fn handle_command_frame(&mut self, tx: &mut Vec<Vec<u8>>) -> Option<usize> {
+        let len = u32::from_le_bytes(self.rx_buf.get(4..8)?.try_into().unwrap()) as usize;
+        if len > MAX_COMMAND_LEN {
+            // The length is broken, so there is no telling where the frame ends, skip the start word
+            tx.push(encode_frame(&nack_payload(NACK_MALFORMED)));
+            return Some(4);
+        }
+
+        let frame = self.rx_buf.get(..len + 11)?;
+        let crc = u16::from_le_bytes([frame[len + 8], frame[len + 9]]);
+        if frame[len + 10] != FRAME_END || crc != crc::Crc::<u16>::new(&crc::CRC_16_ARC).checksum(&frame[5..len + 8]) {
+            tx.push(encode_frame(&nack_payload(NACK_MALFORMED)));
+            return Some(len + 11);
+        }
+
+        let payload = frame[8..len + 8].to_vec();
+        let response = match parse_command(&payload) {
+            Parsed::Command { len, seq, cmd, frame_id } if len == payload.len() => {
+                let response = self.respond(cmd, frame_id).unwrap_or_else(|| nack_payload(NACK_UNKNOWN_FRAME));
+                encode_response(seq, response)
+            }
+            _ => {
+                let seq = match payload.as_slice() {
+                    [SEQUENCE_PREFIX, seq, ..] => Some(*seq),
+                    _ => None,
+                };
+                encode_response(seq, nack_payload(NACK_UNKNOWN_COMMAND))
+            }
+        };
+        tx.push(response);
+
+        Some(len + 11)
+    }
+
+    /// Carries out a command, returns the response payload or None if the frame is unknown
+    fn respond(&mut self, cmd: u8, frame_id: FrameId) -> Option<Vec<u8>> {
+        match cmd {
+            b'l' => Some(list_frames_payload(self.sim.frames())),
+            b'i' => self.sim.frame(frame_id).map(frame_info_payload),
+            b'e' => self.sim.set_enabled(frame_id, true).then(|| b"eE".to_vec()),
+            b'd' => self.sim.set_enabled(frame_id, false).then(|| b"dD".to_vec()),
+            _ => None,
+        }
+    }
+
+    /// Returns the encoded data frames that are due at `now`
+    pub fn poll(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        let mut tx = Vec::new();
+
+        for (timestamp, value) in self.sim.poll(now) {
+            match value.to_bytes() {
+                Ok(data) => tx.push(encode_frame(&data_frame_payload(value.descriptor.id.0, timestamp, &data))),
+                Err(err) => println!("Failed to encode frame {}: {err}", value.descriptor.name),
+            }
+        }
+
+        tx
+    }
+
+    /// Runs the device on a byte stream until reading from it fails
+    ///
+    /// Reading happens on a separate thread, so read timeouts are retried rather than ending the session.
+    pub fn serve<R, W>(&mut self, mut reader: R, mut writer: W) -> std::io::Result<()>
+    where
+        R: Read + Send + 'static,
+        W: Write,
+    {
+        let (rx_tx, rx_rx) = mpsc::channel::<std::io::Result<Vec<u8>>>();
+
+        thread::spawn(move || {
+            let mut buf = [0u8; 256];
+            loop {
+                let result = match reader.read(&mut buf) {
+                    Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+                    Ok(n) => Ok(buf[..n].to_vec()),
+                    Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => Err(err),
+                };
+
+                let failed = result.is_err();
+                if rx_tx.send(result).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        loop {
+            let now = Instant::now();
+            let wakeup = self.sim.next_due().map_or(now + MAX_WAIT, |due| due.min(now + MAX_WAIT));
+
+            match rx_rx.recv_timeout(wakeup.saturating_duration_since(now)) {
+                Ok(Ok(data)) => write_output(&mut writer, &self.handle_input(&data).concat())?,
+                Ok(Err(err)) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Ok(Err(err)) => return Err(err),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+
+            write_output(&mut writer, &self.poll(Instant::now()).concat())?;
+        }
+    }
+
+    /// Runs the device on a UDP socket, sending every frame in a datagram of its own
+    ///
+    /// Data frames go to whoever sent the last command.
+    pub fn serve_udp(&mut self, socket: &UdpSocket) -> std::io::Result<()> {
+        let mut host = None;
+        let mut buf = [0u8; 2048];
+
+        loop {
+            let now = Instant::now();
+            let wakeup = self.sim.next_due().map_or(now + MAX_WAIT, |due| due.min(now + MAX_WAIT));
+            socket.set_read_timeout(Some(wakeup.saturating_duration_since(now).max(Duration::from_millis(1))))?;
+
+            match socket.recv_from(&mut buf) {
+                Ok((n, from)) => {
+                    host = Some(from);
+                    for frame in self.handle_input(&buf[..n]) {
+                        socket.send_to(&frame, from)?;
+                    }
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::ConnectionReset) => {}
+                Err(err) => return Err(err),
+            }
+
+            let frames = self.poll(Instant::now());
+            if let Some(host) = host {
+                for frame in frames {
+                    // The host may be gone, data frames are sent regardless like a real device would
+                    let _ = socket.send_to(&frame, host);
+                }
+            }
+        }
+    }
+}
+
+/// Start of the host input, as far as it's understood
+enum Parsed {
+    /// The input may be the start of a command
+    Incomplete,
+    /// The input doesn't start with a command
+    Invalid,
+    /// The input starts with a command of `len` bytes, `frame_id` is 0 for commands that don't take one
+    Command { len: usize, seq: Option<u8>, cmd: u8, frame_id: FrameId },
+}
+
+fn parse_command(data: &[u8]) -> Parsed {
+    let (seq, command) = match data {
+        [SEQUENCE_PREFIX, seq, command @ ..] => (Some(*seq), command),
+        command => (None, command),
+    };
+    let prefix_len = if seq.is_some() { 2 } else { 0 };
+
+    match command {
+        [] | [SEQUENCE_PREFIX] => Parsed::Incomplete,
+        [b'l', b'L', ..] =>
+            Parsed::Command { len: prefix_len + 2, seq, cmd: b'l', frame_id: FrameId(0) },
+        [cmd @ (b'i' | b'e' | b'd'), a, b, c, d, end, ..] if *end == cmd.to_ascii_uppercase() => {
+            let frame_id = FrameId(u32::from_le_bytes([*a, *b, *c, *d]));
+            Parsed::Command { len: prefix_len + 6, seq, cmd: *cmd, frame_id }
+        }
+        // Wait for the rest of a command that may still be valid
+        [b'l'] => Parsed::Incomplete,
+        [b'i' | b'e' | b'd', rest @ ..] if rest.len() < 5 => Parsed::Incomplete,
+        _ => Parsed::Invalid,
+    }
+}
+
+/// Encodes the response to a command, echoing the sequence number of the command if it had one
+fn encode_response(seq: Option<u8>, payload: Vec<u8>) -> Vec<u8> {
+    match seq {
+        Some(seq) => encode_frame(&[&[SEQUENCE_PREFIX, seq], payload.as_slice()].concat()),
+        None => encode_frame(&payload),
+    }
+}
+
+/// Writes output to the host, dropping it if the host isn't reading
+///
+/// A partially written frame is fine, the host decoder skips to the next start word.
+fn write_output(writer: &mut impl Write, data: &[u8]) -> std::io::Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    match writer.write_all(data).and_then(|_| writer.flush()) {
+        Err(err) if err.kind() == ErrorKind::TimedOut => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sbs_sim::config::SimConfig;
+
+    #[test]
+    fn answers_split_and_unknown_commands() {
+        let mut device = Device::new(Simulation::new(SimConfig::demo()));
+
+        assert!(device.handle_input(b"x").is_empty());
+        assert!(device.handle_input(b"e\x01\x00").is_empty());
+        assert_eq!(device.handle_input(b"\x00\x00E"), [encode_frame(b"eE")]);
+
+        // Unknown frames get no response
+        assert!(device.handle_input(b"d\x63\x00\x00\x00D").is_empty());
+
+        assert_eq!(device.handle_input(b"lLd\x01\x00\x00\x00D"), [encode_frame(&list_frames_payload(device.sim.frames())), encode_frame(b"dD")]);
+    }
+
+    #[test]
+    fn echoes_sequence_numbers() {
+        let mut device = Device::new(Simulation::new(SimConfig::demo()));
+
+        assert!(device.handle_input(b"#").is_empty());
+        assert!(device.handle_input(b"\x07e\x01").is_empty());
+        assert_eq!(device.handle_input(b"\x00\x00\x00E#\x08d\x01\x00\x00\x00D"), [encode_frame(b"#\x07eE"), encode_frame(b"#\x08dD")]);
+
+        // A sequence number can be `#` itself
+        assert_eq!(device.handle_input(b"##d\x01\x00\x00\x00D"), [encode_frame(b"##dD")]);
+    }
+
+    #[test]
+    fn answers_and_rejects_command_frames() {
+        let mut device = Device::new(Simulation::new(SimConfig::demo()));
+
+        let enable = encode_frame(b"#\x01e\x01\x00\x00\x00E");
+        assert!(device.handle_input(&enable[..2]).is_empty());
+        assert!(device.handle_input(&enable[2..9]).is_empty());
+        assert_eq!(device.handle_input(&enable[9..]), [encode_frame(b"#\x01eE")]);
+
+        assert_eq!(device.handle_input(&encode_frame(b"#\x02d\x63\x00\x00\x00D")), [encode_frame(b"#\x02n\x03N")]);
+        assert_eq!(device.handle_input(&encode_frame(b"#\x03xX")), [encode_frame(b"#\x03n\x02N")]);
+        assert_eq!(device.handle_input(&encode_frame(b"lLlL")), [encode_frame(b"n\x02N")]);
+
+        let mut damaged = encode_frame(b"lL");
+        damaged[8] = b'x';
+        assert_eq!(device.handle_input(&[damaged, encode_frame(b"lL")].concat()),
+                   [encode_frame(b"n\x01N"), encode_frame(&list_frames_payload(device.sim.frames()))]);
+    }
+}
@@ -0,0 +1,134 @@
+use sbs_core::sbs::SignalFrameDescriptor;
+
+pub const FRAME_START: u32 = 0xBBBBBBBB;
+pub const FRAME_END: u8 = 0xEE;
+
+/// Prefix of a command or response payload that carries a sequence number
+pub const SEQUENCE_PREFIX: u8 = b'#';
+
+/// Reasons in a NACK response
+pub const NACK_MALFORMED: u8 = 0x01;
+pub const NACK_UNKNOWN_COMMAND: u8 = 0x02;
+pub const NACK_UNKNOWN_FRAME: u8 = 0x03;
+
+/// Flags in the first byte of a GetFrameInfo response
+const FRAME_INFO_ENABLED: u8 = 0x01;
+const FRAME_INFO_METADATA: u8 = 0x02;
+
+/// Flags in a signal metadata block
+const META_SCALE: u8 = 0x01;
+const META_OFFSET: u8 = 0x02;
+const META_MIN: u8 = 0x04;
+const META_MAX: u8 = 0x08;
+
+/// Wraps a payload in a frame: start word, payload length, payload, CRC-16/ARC and end byte
+///
+/// Like the decoder on the host, the CRC covers the upper three bytes of the length and the payload.
+pub fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 11);
+    frame.extend_from_slice(&FRAME_START.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload);
+
+    let crc = crc::Crc::<u16>::new(&crc::CRC_16_ARC).checksum(&frame[5..]);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame.push(FRAME_END);
+
+    frame
+}
+
+pub fn list_frames_payload<'a>(frames: impl IntoIterator<Item=&'a SignalFrameDescriptor>) -> Vec<u8> {
+    let frames = frames.into_iter().collect::<Vec<_>>();
+
+    let mut payload = vec![b'l'];
+    payload.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+    for frame in frames {
+        payload.extend_from_slice(&frame.id.0.to_le_bytes());
+        push_str8(&mut payload, &frame.name);
+    }
+    payload.push(b'L');
+
+    payload
+}
+
+/// Builds the GetFrameInfo response, with signal metadata only if any signal has some
+pub fn frame_info_payload(frame: &SignalFrameDescriptor) -> Vec<u8> {
+    let has_metadata = frame.signals.iter().any(|s| {
+        s.unit.is_some() || s.scale.is_some() || s.offset.is_some() || s.min.is_some() || s.max.is_some()
+    });
+
+    let mut flags = 0;
+    if frame.enabled {
+        flags |= FRAME_INFO_ENABLED;
+    }
+    if has_metadata {
+        flags |= FRAME_INFO_METADATA;
+    }
+
+    let mut payload = vec![b'i', flags];
+    payload.extend_from_slice(&(frame.signals.len() as u32).to_le_bytes());
+    for signal in &frame.signals {
+        push_str8(&mut payload, &signal.name);
+        push_str8(&mut payload, &signal.ty.to_string());
+
+        if has_metadata {
+            push_str8(&mut payload, signal.unit.as_deref().unwrap_or(""));
+
+            let values = [(META_SCALE, signal.scale), (META_OFFSET, signal.offset), (META_MIN, signal.min), (META_MAX, signal.max)];
+            payload.push(values.iter()
+                .filter(|(_, value)| value.is_some())
+                .fold(0, |flags, (flag, _)| flags | flag));
+            for value in values.iter().filter_map(|(_, value)| *value) {
+                payload.extend_from_slice(&(value as f32).to_le_bytes());
+            }
+        }
+    }
+    payload.push(b'I');
+
+    payload
+}
+
+pub fn data_frame_payload(frame_id: u32, timestamp: u32, data: &[u8]) -> Vec<u8> {
+    let mut payload = vec![b's'];
+    payload.extend_from_slice(&frame_id.to_le_bytes());
+    payload.extend_from_slice(&timestamp.to_le_bytes());
+    payload.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    payload.extend_from_slice(data);
+    payload.push(b'S');
+
+    payload
+}
+
+/// Builds the response that rejects a command
+pub fn nack_payload(reason: u8) -> Vec<u8> {
+    vec![b'n', reason, b'N']
+}
+
+/// Appends a string with a length byte, truncated to 255 bytes
+fn push_str8(payload: &mut Vec<u8>, s: &str) {
+    let bytes = &s.as_bytes()[..s.len().min(u8::MAX as usize)];
+    payload.push(bytes.len() as u8);
+    payload.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_frame() {
+        let frame = encode_frame(b"eE");
+
+        assert_eq!(frame[..8], [0xBB, 0xBB, 0xBB, 0xBB, 2, 0, 0, 0]);
+        assert_eq!(frame[8..10], *b"eE");
+        let crc = crc::Crc::<u16>::new(&crc::CRC_16_ARC).checksum(&[0, 0, 0, b'e', b'E']);
+        assert_eq!(frame[10..12], crc.to_le_bytes());
+        assert_eq!(frame[12], FRAME_END);
+    }
+
+    #[test]
+    fn encodes_data_frame() {
+        let payload = data_frame_payload(3, 0x01020304, &[0xAA, 0xBB]);
+        assert_eq!(payload, [b's', 3, 0, 0, 0, 4, 3, 2, 1, 2, 0, 0, 0, 0xAA, 0xBB, b'S']);
+    }
+}
@@ -0,0 +1,98 @@
+use std::net::{TcpListener, UdpSocket};
+use std::path::PathBuf;
+use clap::Parser;
+use sbs_emu::device::Device;
+use sbs_sim::config::SimConfig;
+use sbs_sim::simulation::Simulation;
+
+/// Emulates an SBS device on a pseudo-terminal, TCP or UDP socket
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Simulator config with the frames of the device, the built-in demo device if not given
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Serve on a new pseudo-terminal, printing the path to connect to
+    #[arg(long, conflicts_with_all = ["tcp", "udp"])]
+    pty: bool,
+
+    /// Serve on a TCP address, one connection at a time
+    #[arg(long, value_name = "ADDR", conflicts_with = "udp")]
+    tcp: Option<String>,
+
+    /// Serve on a UDP address, with a frame per datagram
+    #[arg(long, value_name = "ADDR")]
+    udp: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let config = match &args.config {
+        Some(path) => SimConfig::load(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load {}: {err}", path.display());
+            std::process::exit(1);
+        }),
+        None => SimConfig::demo(),
+    };
+    let mut device = Device::new(Simulation::new(config));
+
+    let result = match (args.tcp, args.udp) {
+        (Some(addr), _) => serve_tcp(&mut device, &addr),
+        (_, Some(addr)) => serve_udp(&mut device, &addr),
+        _ => serve_pty(&mut device),
+    };
+
+    if let Err(err) = result {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(unix)]
+fn serve_pty(device: &mut Device) -> std::io::Result<()> {
+    use serialport::SerialPort;
+
+    let (master, slave) = serialport::TTYPort::pair()
+        .map_err(std::io::Error::from)?;
+    println!("Serving on {}", slave.name().unwrap_or_default());
+
+    // The slave stays open, so the master doesn't see a hangup when a client disconnects
+    let reader = master.try_clone_native().map_err(std::io::Error::from)?;
+    let result = device.serve(reader, master);
+    drop(slave);
+
+    result
+}
+
+#[cfg(not(unix))]
+fn serve_pty(_device: &mut Device) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Pseudo-terminals are only available on Unix"))
+}
+
+fn serve_tcp(device: &mut Device, addr: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("Serving on {}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        println!("Client connected from {}", stream.peer_addr()?);
+
+        stream.set_nodelay(true)?;
+        if let Err(err) = device.serve(stream.try_clone()?, stream) {
+            println!("Client disconnected: {err}");
+        } else {
+            println!("Client disconnected");
+        }
+    }
+
+    Ok(())
+}
+
+fn serve_udp(device: &mut Device, addr: &str) -> std::io::Result<()> {
+    let socket = UdpSocket::bind(addr)?;
+    println!("Serving on {}", socket.local_addr()?);
+
+    device.serve_udp(&socket)
+}
@@ -0,0 +1,91 @@
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+use futures::StreamExt;
+use sbs_core::sbs::{Client, FrameId};
+use sbs_core::subscription::{FrameFilter, SubscriptionOptions};
+use sbs_emu::device::Device;
+use sbs_emu::encoder::{data_frame_payload, encode_frame};
+use sbs_sim::config::SimConfig;
+use sbs_sim::simulation::Simulation;
+use sbs_uart::capture::{Capture, CaptureConfig, CaptureTransport};
+use sbs_uart::replay::SbsReplay;
+use sbs_uart::sbs_uart::SbsUart;
+use sbs_uart::transport::{TcpConfig, TcpTransport};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replays_recorded_session() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut config = SimConfig::demo();
+        config.seed = Some(1);
+        let (stream, _) = listener.accept().unwrap();
+        let _ = Device::new(Simulation::new(config)).serve(stream.try_clone().unwrap(), stream);
+    });
+
+    let path = std::env::temp_dir().join(format!("sbs_replay_{}.sbscap", std::process::id()));
+    let recorded = {
+        let mut client = SbsUart::<CaptureTransport<TcpTransport>>::new();
+        client.connect(CaptureConfig::new(TcpConfig::new(&addr.to_string()), &path).unwrap()).await.unwrap();
+        client.get_frames().await.unwrap();
+
+        let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+        client.enable_frame(FrameId(1)).await.unwrap();
+        let recorded = tokio::time::timeout(Duration::from_secs(5), (&mut subscription).take(20).collect::<Vec<_>>())
+            .await
+            .unwrap();
+        client.disable_frame(FrameId(1)).await.unwrap();
+        recorded
+    };
+
+    let capture = Capture::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut replay = SbsReplay::new(capture);
+    replay.control().set_speed(10.0);
+    let mut subscription = replay.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    replay.start().await.unwrap();
+
+    let frames = replay.get_frames().await.unwrap();
+    assert_eq!(frames.iter().map(|f| (f.id, f.enabled)).collect::<Vec<_>>(), [(FrameId(1), true), (FrameId(2), false)]);
+
+    let replayed = tokio::time::timeout(Duration::from_secs(5), (&mut subscription).take(20).collect::<Vec<_>>())
+        .await
+        .unwrap();
+    for (recorded, replayed) in recorded.iter().zip(&replayed) {
+        assert_eq!(recorded.timestamp, replayed.timestamp);
+        assert_eq!(recorded.data, replayed.data);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn steps_through_paused_replay() {
+    let frame = encode_frame(&data_frame_payload(1, 0, &[7]));
+
+    // Only a schema describes the frames, since the capture has no discovery
+    let mut bytes = b"SBSCAP\x00\x01".to_vec();
+    for time in [1_000_000u64, 2_000_000] {
+        bytes.extend_from_slice(&time.to_le_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&frame);
+    }
+    let schema = sbs_core::schema::Schema::parse_toml("[[frame]]\nid = 1\nname = \"counter\"\n[[frame.signal]]\nname = \"count\"\ntype = \"uint8\"\n").unwrap();
+
+    let mut replay = SbsReplay::new(Capture::parse(&bytes).unwrap());
+    assert!(replay.start().await.is_err());
+    replay.set_schema(schema);
+
+    let control = replay.control();
+    control.set_paused(true);
+    let mut subscription = replay.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    replay.start().await.unwrap();
+
+    assert!(tokio::time::timeout(Duration::from_millis(300), subscription.next()).await.is_err());
+
+    control.step();
+    assert!(tokio::time::timeout(Duration::from_secs(1), subscription.next()).await.unwrap().is_some());
+    assert_eq!(control.position(), Duration::from_secs(1));
+    assert!(!control.is_finished());
+}
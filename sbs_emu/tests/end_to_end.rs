@@ -0,0 +1,377 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use futures::StreamExt;
+use sbs_core::error::Error as ClientError;
+use sbs_core::sbs::{Client, ConnectionState, FrameId};
+use sbs_core::subscription::{FrameFilter, SubscriptionOptions};
+use sbs_emu::device::Device;
+use sbs_emu::encoder::{encode_frame, list_frames_payload};
+use sbs_sim::config::SimConfig;
+use sbs_sim::simulation::Simulation;
+use sbs_uart::connection::ConnectionConfig;
+use sbs_uart::protocol::ProtocolOptions;
+use sbs_uart::sbs_uart::{SbsTcp, SbsUart};
+use sbs_uart::transport::{SerialConfig, TcpConfig};
+use sbs_uart::udp_transport::{UdpConfig, UdpTransport};
+
+fn demo_config() -> SimConfig {
+    let mut config = SimConfig::demo();
+    config.seed = Some(1);
+    config
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_uart_talks_to_emulated_device() {
+    use serialport::SerialPort;
+
+    let (master, slave) = serialport::TTYPort::pair().unwrap();
+    let port_name = slave.name().unwrap();
+    let reader = master.try_clone_native().unwrap();
+    thread::spawn(move || {
+        let _slave = slave;
+        let _ = Device::new(Simulation::new(demo_config())).serve(reader, master);
+    });
+
+    let mut client: SbsUart = SbsUart::new();
+    client.connect(SerialConfig::new(&port_name, 115200)).await.unwrap();
+
+    let frames = client.get_frames().await.unwrap();
+    let expected = demo_config().frames.into_iter().map(|f| f.descriptor).collect::<Vec<_>>();
+    assert_eq!(frames.len(), expected.len());
+    for (frame, expected) in frames.iter().zip(&expected) {
+        assert_eq!(frame.id, expected.id);
+        assert_eq!(frame.name, expected.name);
+        // Metadata goes over the wire as f32
+        let mut expected_signals = expected.signals.clone();
+        for signal in &mut expected_signals {
+            for value in [&mut signal.scale, &mut signal.offset, &mut signal.min, &mut signal.max].into_iter().flatten() {
+                *value = *value as f32 as f64;
+            }
+        }
+        assert_eq!(frame.signals, expected_signals);
+        assert!(!frame.enabled);
+    }
+
+    let mut subscription = client.subscribe(FrameFilter::Frames([FrameId(2)].into()), SubscriptionOptions::default()).await;
+    client.enable_frame(FrameId(2)).await.unwrap();
+
+    let received = tokio::time::timeout(Duration::from_secs(5), (&mut subscription).take(3).collect::<Vec<_>>())
+        .await
+        .unwrap();
+    assert_eq!(received.len(), 3);
+    for value in &received {
+        assert_eq!(value.descriptor.id, FrameId(2));
+        let temperature = value.descriptor.signals[0].to_engineering(&value.data[0]);
+        assert!((15.0..=30.0).contains(&temperature));
+    }
+    assert!(received.windows(2).all(|w| w[0].timestamp.seconds < w[1].timestamp.seconds));
+
+    client.disable_frame(FrameId(2)).await.unwrap();
+}
+
+#[test]
+fn serves_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let _ = Device::new(Simulation::new(demo_config())).serve(stream.try_clone().unwrap(), stream);
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(b"lL").unwrap();
+
+    let config = demo_config();
+    let expected = encode_frame(&list_frames_payload(config.frames.iter().map(|f| &f.descriptor)));
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).unwrap();
+    assert_eq!(response, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_tcp_reconnects_after_connection_loss() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (conn_tx, conn_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut device = Device::new(Simulation::new(demo_config()));
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let _ = conn_tx.send(stream.try_clone().unwrap());
+            let _ = device.serve(stream.try_clone().unwrap(), stream);
+        }
+    });
+
+    let mut client = SbsTcp::new();
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+    assert_eq!(client.get_frames().await.unwrap().len(), 2);
+
+    conn_rx.recv().unwrap().shutdown(Shutdown::Both).unwrap();
+
+    // Commands fail while the link is down, and work again once the client reconnected
+    let mut enabled = false;
+    for _ in 0..50 {
+        if client.enable_frame(FrameId(1)).await.is_ok() {
+            enabled = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(enabled);
+    assert!(conn_rx.try_recv().is_ok());
+
+    let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    let value = tokio::time::timeout(Duration::from_secs(5), subscription.next()).await.unwrap().unwrap();
+    assert_eq!(value.descriptor.id, FrameId(1));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_tcp_restores_enabled_frames_after_device_reboot() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (conn_tx, conn_rx) = mpsc::channel();
+    thread::spawn(move || {
+        // Every connection gets a fresh device, as if it rebooted
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let _ = conn_tx.send(stream.try_clone().unwrap());
+            let _ = Device::new(Simulation::new(demo_config())).serve(stream.try_clone().unwrap(), stream);
+        }
+    });
+
+    let mut client = SbsTcp::new();
+    let mut states = client.connection_events();
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+    assert_eq!(*states.borrow_and_update(), ConnectionState::Connected);
+    client.get_frames().await.unwrap();
+    client.enable_frame(FrameId(1)).await.unwrap();
+
+    // Run long enough that the timestamps after the reboot are behind the last ones before it
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    conn_rx.recv().unwrap().shutdown(Shutdown::Both).unwrap();
+
+    let mut seen = Vec::new();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while states.changed().await.is_ok() {
+            let state = *states.borrow_and_update();
+            seen.push(state);
+            if state == ConnectionState::Connected {
+                break;
+            }
+        }
+    }).await.unwrap();
+    assert_eq!(seen.first(), Some(&ConnectionState::Reconnecting));
+    assert_eq!(seen.last(), Some(&ConnectionState::Connected));
+
+    // Frame 1 is streamed again without enabling it again
+    let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    let value = tokio::time::timeout(Duration::from_secs(5), subscription.next()).await.unwrap().unwrap();
+    assert_eq!(value.descriptor.id, FrameId(1));
+    assert!(client.get_frames().await.unwrap().iter().any(|frame| frame.id == FrameId(1) && frame.enabled));
+
+    // The timestamps starting over after the reconnect aren't taken for another reset
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!states.has_changed().unwrap());
+}
+
+/// Serves the demo device on a TCP listener, resetting it whenever `reset` is set
+fn serve_resettable_device(listener: TcpListener, device_reset: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let mut device = Device::new(Simulation::new(demo_config()));
+        let mut buf = [0u8; 256];
+        loop {
+            // The device resets without dropping the link, and streams nothing until frames are enabled again
+            if device_reset.swap(false, Ordering::Relaxed) {
+                device = Device::new(Simulation::new(demo_config()));
+            }
+
+            let mut output = match stream.read(&mut buf) {
+                Ok(0) => return,
+                Ok(n) => device.handle_input(&buf[..n]),
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Vec::new(),
+                Err(_) => return,
+            };
+            output.extend(device.poll(Instant::now()));
+            if stream.write_all(&output.concat()).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_tcp_restores_enabled_frames_after_silent_device_reset() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let reset = Arc::new(AtomicBool::new(false));
+    serve_resettable_device(listener, reset.clone());
+
+    let mut client = SbsTcp::new();
+    client.set_connection_config(ConnectionConfig::default().with_silence_probe(Some(Duration::from_millis(500))));
+    let mut states = client.connection_events();
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+    client.get_frames().await.unwrap();
+    client.enable_frame(FrameId(1)).await.unwrap();
+    states.borrow_and_update();
+
+    // Run long enough that the timestamps after the reset are behind the last ones before it
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    reset.store(true, Ordering::Relaxed);
+
+    let mut seen = Vec::new();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while states.changed().await.is_ok() {
+            let state = *states.borrow_and_update();
+            seen.push(state);
+            if state == ConnectionState::Connected {
+                break;
+            }
+        }
+    }).await.unwrap();
+    assert_eq!(seen, [ConnectionState::Recovering, ConnectionState::Connected]);
+
+    let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    let value = tokio::time::timeout(Duration::from_secs(5), subscription.next()).await.unwrap().unwrap();
+    assert_eq!(value.descriptor.id, FrameId(1));
+
+    // The restarted timestamps aren't taken for another reset
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!states.has_changed().unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_tcp_does_not_probe_silent_devices_when_turned_off() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let reset = Arc::new(AtomicBool::new(false));
+    serve_resettable_device(listener, reset.clone());
+
+    let mut client = SbsTcp::new();
+    client.set_connection_config(ConnectionConfig::default().with_silence_probe(None));
+    let mut states = client.connection_events();
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+    client.get_frames().await.unwrap();
+    client.enable_frame(FrameId(1)).await.unwrap();
+    states.borrow_and_update();
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    reset.store(true, Ordering::Relaxed);
+
+    // A device with slow frames is silent for longer than the default probe interval
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    assert!(!states.has_changed().unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_tcp_numbers_commands() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let stream = listener.incoming().next().unwrap().unwrap();
+        let _ = Device::new(Simulation::new(demo_config())).serve(stream.try_clone().unwrap(), stream);
+    });
+
+    let mut client = SbsTcp::new();
+    client.set_protocol_options(ProtocolOptions { sequence_numbers: true, ..Default::default() });
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+
+    let frames = client.get_frames().await.unwrap();
+    let expected = demo_config().frames.into_iter().map(|f| f.descriptor.name).collect::<Vec<_>>();
+    assert_eq!(frames.iter().map(|f| f.name.clone()).collect::<Vec<_>>(), expected);
+    assert!(frames.iter().all(|f| !f.signals.is_empty()));
+
+    let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    client.enable_frame(FrameId(2)).await.unwrap();
+    let value = tokio::time::timeout(Duration::from_secs(5), subscription.next()).await.unwrap().unwrap();
+    assert_eq!(value.descriptor.id, FrameId(2));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_tcp_frames_commands() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let stream = listener.incoming().next().unwrap().unwrap();
+        let _ = Device::new(Simulation::new(demo_config())).serve(stream.try_clone().unwrap(), stream);
+    });
+
+    let mut client = SbsTcp::new();
+    client.set_protocol_options(ProtocolOptions { framed_commands: true, ..Default::default() });
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+
+    // Before the frames are known, the command goes to the device, which rejects it
+    assert!(matches!(client.enable_frame(FrameId(99)).await, Err(ClientError::UnknownFrame(FrameId(99)))));
+
+    let frames = client.get_frames().await.unwrap();
+    assert_eq!(frames.len(), demo_config().frames.len());
+
+    let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    client.enable_frame(FrameId(1)).await.unwrap();
+    let value = tokio::time::timeout(Duration::from_secs(5), subscription.next()).await.unwrap().unwrap();
+    assert_eq!(value.descriptor.id, FrameId(1));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_tcp_disconnects_and_connects_again() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut device = Device::new(Simulation::new(demo_config()));
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let _ = device.serve(stream.try_clone().unwrap(), stream);
+        }
+    });
+
+    let mut client = SbsTcp::new();
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+    client.get_frames().await.unwrap();
+    client.enable_frame(FrameId(1)).await.unwrap();
+    let subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+
+    client.disconnect().await.unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Disconnected);
+    tokio::time::timeout(Duration::from_secs(5), subscription.count()).await.unwrap();
+
+    // The device kept its state, and frame 1 was disabled on it before disconnecting
+    client.connect(TcpConfig::new(&addr.to_string())).await.unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Connected);
+    assert!(client.get_frames().await.unwrap().iter().all(|frame| !frame.enabled));
+    let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    assert!(tokio::time::timeout(Duration::from_millis(500), subscription.next()).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sbs_uart_talks_to_emulated_device_over_udp() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let _ = Device::new(Simulation::new(demo_config())).serve_udp(&socket);
+    });
+
+    let config = UdpConfig::new(&addr.to_string());
+    let stats = config.stats.clone();
+    let mut client = SbsUart::<UdpTransport>::new();
+    client.connect(config).await.unwrap();
+    assert_eq!(client.get_frames().await.unwrap().len(), 2);
+
+    let mut subscription = client.subscribe(FrameFilter::All, SubscriptionOptions::default()).await;
+    client.enable_frame(FrameId(1)).await.unwrap();
+    let received = tokio::time::timeout(Duration::from_secs(5), (&mut subscription).take(10).collect::<Vec<_>>())
+        .await
+        .unwrap();
+    assert!(received.iter().all(|f| f.descriptor.id == FrameId(1)));
+    client.disable_frame(FrameId(1)).await.unwrap();
+
+    let snapshot = stats.snapshot();
+    assert!(snapshot.received >= 10);
+    assert_eq!(snapshot.malformed, 0);
+}
@@ -0,0 +1,59 @@
+use sbs_core::sbs::{FrameId, SignalFrameDescriptor};
+
+/// Finds the frames named by `selectors`, each either a frame id or a frame name
+pub fn select_frames(frames: &[SignalFrameDescriptor], selectors: &[String]) -> Result<Vec<FrameId>, String> {
+    let mut result = Vec::new();
+
+    for selector in selectors {
+        let frame = frames.iter()
+            .find(|frame| selector.parse::<u32>().is_ok_and(|id| frame.id.0 == id))
+            .or_else(|| frames.iter().find(|frame| frame.name == *selector))
+            .ok_or_else(|| format!("Device has no frame {selector}"))?;
+
+        if !result.contains(&frame.id) {
+            result.push(frame.id);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Describes a frame and its signals over multiple lines, for listing the frames of a device
+pub fn describe_frame(frame: &SignalFrameDescriptor) -> String {
+    let mut result = format!("{} {}{}", frame.id.0, frame.name, if frame.enabled { " (enabled)" } else { "" });
+
+    for signal in &frame.signals {
+        result.push_str(&format!("\n    {}: {}", signal.display_name(&signal.name), signal.ty));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sbs_core::sbs::SignalDescriptor;
+    use sbs_core::ty::Type;
+
+    fn frames() -> Vec<SignalFrameDescriptor> {
+        let mut current = SignalDescriptor::new("current", Type::Int16);
+        current.unit = Some("A".to_string());
+
+        vec![
+            SignalFrameDescriptor { id: FrameId(1), name: "motor".to_string(), enabled: true, signals: vec![current] },
+            SignalFrameDescriptor { id: FrameId(7), name: "2".to_string(), enabled: false, signals: vec![] },
+        ]
+    }
+
+    #[test]
+    fn selects_frames_by_id_or_name() {
+        let selectors = ["motor", "7", "2", "1"].map(str::to_string);
+        assert_eq!(select_frames(&frames(), &selectors), Ok(vec![FrameId(1), FrameId(7)]));
+        assert!(select_frames(&frames(), &["pump".to_string()]).is_err());
+    }
+
+    #[test]
+    fn describes_frames() {
+        assert_eq!(describe_frame(&frames()[0]), "1 motor (enabled)\n    current [A]: int16");
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use clap::Parser;
+use futures::StreamExt;
+use sbs_core::columnar::ColumnarWriter;
+use sbs_core::overrides::SignalOverrides;
+use sbs_core::recording::RecordingWriter;
+use sbs_core::sbs::{Client, FrameId, SignalFrameDescriptor};
+use sbs_core::schema::Schema;
+use sbs_core::subscription::{OverflowPolicy, Subscription, SubscriptionOptions};
+use sbs_core::value::SignalFrameValue;
+use sbs_rec::frames::{describe_frame, select_frames};
+use sbs_rec::output::{OutputFormat, TableFormat};
+use sbs_uart::connection::ConnectionConfig;
+use sbs_uart::protocol::ProtocolOptions;
+use sbs_uart::sbs_uart::{FrameDiscovery, SbsUart};
+use sbs_uart::transport::SerialConfig;
+
+/// Number of decoded frames buffered while writing the output, before frames are dropped
+const BUFFERED_FRAMES: usize = 16_384;
+
+/// Records the frames of an SBS device on a serial port, until interrupted
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Serial port of the device, e.g. /dev/ttyUSB0
+    port: String,
+
+    #[arg(short, long, default_value_t = 115_200)]
+    baud: u32,
+
+    /// List the frames of the device and their signals, and exit
+    #[arg(short, long)]
+    list: bool,
+
+    /// Frame to enable, by id or name, can be given multiple times
+    #[arg(short, long = "frame", value_name = "FRAME")]
+    frames: Vec<String>,
+
+    /// Enable every frame of the device
+    #[arg(short, long, conflicts_with = "frames")]
+    all: bool,
+
+    /// Format of the frames written to stdout
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Write the frames to a recording file instead of stdout
+    #[arg(short, long, value_name = "PATH", conflicts_with = "format")]
+    record: Option<PathBuf>,
+
+    /// Write the frames to a directory of tables instead of stdout, a table per enabled frame
+    #[arg(short, long, value_name = "DIR", conflicts_with_all = ["format", "record"])]
+    tables: Option<PathBuf>,
+
+    /// Format of the tables written with --tables
+    #[arg(long, value_enum, default_value_t = TableFormat::Parquet)]
+    table_format: TableFormat,
+
+    /// Stop recording after this many seconds
+    #[arg(short, long, value_name = "SECONDS", value_parser = parse_duration)]
+    duration: Option<Duration>,
+
+    /// Frame schema to use instead of querying the frames from the device
+    #[arg(long, value_name = "PATH")]
+    schema: Option<PathBuf>,
+
+    /// Host-side signal metadata applied on top of what the device announces
+    #[arg(long, value_name = "PATH")]
+    overrides: Option<PathBuf>,
+
+    /// Frequency of the device timestamp counter in Hz, taken from the schema or 1000 Hz if not given
+    #[arg(long, value_name = "HZ")]
+    tick_frequency: Option<f64>,
+
+    /// Number the commands, for devices that echo the number in their responses
+    #[arg(long)]
+    sequence_numbers: bool,
+
+    /// Send the commands in CRC-checked frames, for devices that reject broken commands
+    #[arg(long)]
+    framed_commands: bool,
+
+    /// Time the device gets to respond to a command, for slow bootloaders or busy targets
+    #[arg(long, value_name = "MS", default_value_t = 2000, value_parser = clap::value_parser!(u64).range(1..))]
+    response_timeout: u64,
+
+    /// Longest time a read from the port waits for data, before pending commands are sent
+    #[arg(long, value_name = "MS", default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    read_timeout: u64,
+
+    /// Time without frames after which the device is asked whether it was reset, 0 for devices
+    /// with frames slower than that
+    #[arg(long, value_name = "MS", default_value_t = 2000)]
+    silence_probe: u64,
+}
+
+/// Parses a duration in seconds, which must be finite and above 0
+fn parse_duration(arg: &str) -> Result<Duration, String> {
+    let seconds = arg.parse::<f64>().map_err(|e| e.to_string())?;
+    if !(seconds.is_finite() && seconds > 0.0) {
+        return Err(format!("{seconds} is not a positive number of seconds"));
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
+
+/// Where the decoded frames go
+enum Sink {
+    Stdout(OutputFormat, BufWriter<std::io::Stdout>),
+    Recording(RecordingWriter),
+    Tables(ColumnarWriter),
+}
+
+impl Sink {
+    fn write(&mut self, value: &SignalFrameValue) -> Result<(), String> {
+        match self {
+            Sink::Stdout(format, out) => writeln!(out, "{}", format.format(value)).map_err(|e| e.to_string()),
+            Sink::Recording(writer) => writer.write(value).map_err(|e| e.to_string()),
+            Sink::Tables(writer) => writer.write(value).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Sink::Stdout(_, mut out) => out.flush().map_err(|e| e.to_string()),
+            Sink::Recording(writer) => writer.finish().map_err(|e| e.to_string()),
+            Sink::Tables(writer) => writer.finish().map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    if let Err(err) = run(args).await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let mut client: SbsUart = SbsUart::new();
+    if let Some(path) = &args.overrides {
+        client.set_signal_overrides(SignalOverrides::load(path).map_err(|e| format!("Failed to load {}: {e}", path.display()))?);
+    }
+    if let Some(path) = &args.schema {
+        client.set_frame_discovery(FrameDiscovery::Schema(Schema::load(path).map_err(|e| format!("Failed to load {}: {e}", path.display()))?));
+    }
+    client.set_tick_frequency(args.tick_frequency);
+    client.set_protocol_options(ProtocolOptions {
+        sequence_numbers: args.sequence_numbers,
+        framed_commands: args.framed_commands,
+    });
+    client.set_connection_config(ConnectionConfig::default()
+        .with_response_timeout(Duration::from_millis(args.response_timeout))
+        .with_read_timeout(Duration::from_millis(args.read_timeout))
+        .with_silence_probe((args.silence_probe > 0).then(|| Duration::from_millis(args.silence_probe))));
+
+    client.connect(SerialConfig::new(&args.port, args.baud)).await
+        .map_err(|e| format!("Failed to connect to {}: {e}", args.port))?;
+    let frames = client.get_frames().await.map_err(|e| format!("Failed to get frames: {e}"))?;
+
+    if args.list {
+        for frame in &frames {
+            println!("{}", describe_frame(frame));
+        }
+        return Ok(());
+    }
+
+    let enabled: Vec<FrameId> = if args.all {
+        frames.iter().map(|frame| frame.id).collect()
+    } else {
+        select_frames(&frames, &args.frames)?
+    };
+    if enabled.is_empty() {
+        return Err("No frames to record, select them with --frame or --all".to_string());
+    }
+
+    let sink = match (&args.record, &args.tables) {
+        (Some(path), _) => Sink::Recording(RecordingWriter::create(path, &frames)
+            .map_err(|e| format!("Failed to create {}: {e}", path.display()))?),
+        (None, Some(dir)) => {
+            let tables = frames.iter().filter(|frame| enabled.contains(&frame.id)).cloned().collect::<Vec<_>>();
+            Sink::Tables(ColumnarWriter::create(dir, &tables, args.table_format.into())
+                .map_err(|e| format!("Failed to create tables in {}: {e}", dir.display()))?)
+        }
+        (None, None) => Sink::Stdout(args.format, BufWriter::new(std::io::stdout())),
+    };
+
+    let options = SubscriptionOptions { capacity: BUFFERED_FRAMES, overflow: OverflowPolicy::DropNewest };
+    let mut subscription = client.subscribe(enabled.clone().into(), options).await;
+    for frame_id in &enabled {
+        client.enable_frame(*frame_id).await.map_err(|e| format!("Failed to enable frame {}: {e}", frame_id.0))?;
+    }
+
+    // Recording carries on through reconnects, so only report them
+    let mut states = client.connection_events();
+    tokio::spawn(async move {
+        while states.changed().await.is_ok() {
+            eprintln!("Connection state: {}", *states.borrow_and_update());
+        }
+    });
+
+    let started = Instant::now();
+    let (counts, result) = record(&mut subscription, sink, args.duration).await;
+
+    if let Err(e) = client.disconnect().await {
+        eprintln!("Failed to disconnect: {e}");
+    }
+
+    print_summary(&frames, &enabled, &counts, started.elapsed(), subscription.dropped_count(), &client);
+    result
+}
+
+/// Writes frames to the sink until interrupted, the duration passed or writing fails, returns the frame counts
+async fn record(
+    subscription: &mut Subscription,
+    mut sink: Sink,
+    duration: Option<Duration>,
+) -> (HashMap<FrameId, u64>, Result<(), String>) {
+    let mut counts = HashMap::<FrameId, u64>::new();
+    let interrupted = stop_requested();
+    let timed_out = sleep_until(duration.map(|duration| tokio::time::Instant::now() + duration));
+    tokio::pin!(interrupted, timed_out);
+
+    let result = loop {
+        let value = tokio::select! {
+            value = subscription.next() => value,
+            _ = &mut interrupted => break Ok(()),
+            _ = &mut timed_out => break Ok(()),
+        };
+
+        let Some(value) = value else {
+            break Err("Client stopped".to_string());
+        };
+
+        *counts.entry(value.descriptor.id).or_default() += 1;
+        if let Err(e) = sink.write(&value) {
+            break Err(format!("Failed to write frame: {e}"));
+        }
+    };
+
+    let result = result.and(sink.finish());
+    (counts, result)
+}
+
+/// Waits for Ctrl-C, or on Unix also for SIGTERM or SIGHUP, so the output is finished when the
+/// recorder is stopped by a service manager or its terminal closes
+async fn stop_requested() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let (Ok(mut terminate), Ok(mut hangup)) = (signal(SignalKind::terminate()), signal(SignalKind::hangup())) else {
+            eprintln!("Failed to listen for SIGTERM and SIGHUP, only Ctrl-C stops the recording cleanly");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+            _ = hangup.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn print_summary(
+    frames: &[SignalFrameDescriptor],
+    enabled: &[FrameId],
+    counts: &HashMap<FrameId, u64>,
+    elapsed: Duration,
+    dropped: u64,
+    client: &SbsUart,
+) {
+    eprintln!("Recorded for {:.1} s", elapsed.as_secs_f64());
+    for frame_id in enabled {
+        let name = frames.iter().find(|frame| frame.id == *frame_id).map_or("", |frame| frame.name.as_str());
+        eprintln!("    {} {name}: {} frames", frame_id.0, counts.get(frame_id).copied().unwrap_or(0));
+    }
+
+    eprintln!("{}", client.decode_stats().snapshot());
+    if dropped > 0 {
+        eprintln!("{dropped} frames dropped because the output could not keep up");
+    }
+}
@@ -0,0 +1,132 @@
+use clap::ValueEnum;
+use serde_json::{json, Map, Number};
+use sbs_core::columnar::ColumnarFormat;
+use sbs_core::value::{SignalFrameValue, Value};
+
+/// How decoded frames are written to stdout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A line per frame with its time in seconds, name and signal values
+    Text,
+    /// A JSON object per line, with the time in ticks and seconds, and the signal values
+    Json,
+}
+
+impl OutputFormat {
+    pub fn format(&self, value: &SignalFrameValue) -> String {
+        match self {
+            OutputFormat::Text => text_line(value),
+            OutputFormat::Json => json_line(value),
+        }
+    }
+}
+
+/// Format of the tables the frames are written to, a table per frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TableFormat {
+    Parquet,
+    /// Arrow IPC file, also known as Feather v2
+    Arrow,
+}
+
+impl From<TableFormat> for ColumnarFormat {
+    fn from(format: TableFormat) -> Self {
+        match format {
+            TableFormat::Parquet => ColumnarFormat::Parquet,
+            TableFormat::Arrow => ColumnarFormat::ArrowIpc,
+        }
+    }
+}
+
+/// Formats a frame as e.g. `1.250000 motor current=1.5 state=RUN`
+pub fn text_line(value: &SignalFrameValue) -> String {
+    let signals = value.descriptor.signals.iter().zip(&value.data)
+        .map(|(signal, data)| format!(" {}={data}", signal.name))
+        .collect::<String>();
+
+    format!("{:.6} {}{signals}", value.timestamp.seconds, value.descriptor.name)
+}
+
+/// Formats a frame as a single-line JSON object
+pub fn json_line(value: &SignalFrameValue) -> String {
+    let signals = value.descriptor.signals.iter().zip(&value.data)
+        .map(|(signal, data)| (signal.name.clone(), to_json(data)))
+        .collect::<Map<_, _>>();
+
+    json!({
+        "ticks": value.timestamp.ticks,
+        "time": value.timestamp.seconds,
+        "id": value.descriptor.id.0,
+        "frame": value.descriptor.name,
+        "signals": signals,
+    }).to_string()
+}
+
+/// Converts a value to JSON, with enums as their label if known and compound values nested
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Uint8(v) => json!(v),
+        Value::Uint16(v) => json!(v),
+        Value::Uint32(v) => json!(v),
+        Value::Int8(v) => json!(v),
+        Value::Int16(v) => json!(v),
+        Value::Int32(v) => json!(v),
+        Value::Float32(_) | Value::SFix { .. } | Value::UFix { .. } =>
+            Number::from_f64(value.clone().into()).map_or(serde_json::Value::Null, serde_json::Value::Number),
+        Value::Array(values) => values.iter().map(to_json).collect(),
+        Value::Struct(fields) | Value::Bits { fields, .. } => fields.iter()
+            .map(|(name, value)| (name.clone(), to_json(value)))
+            .collect::<Map<_, _>>()
+            .into(),
+        Value::Enum { label: Some(label), .. } => json!(label),
+        Value::Enum { raw, label: None } => json!(raw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sbs_core::clock::Timestamp;
+    use sbs_core::sbs::{FrameId, SignalDescriptor, SignalFrameDescriptor};
+    use sbs_core::ty::{EnumLabel, Type};
+
+    fn value() -> SignalFrameValue {
+        let labels = vec![EnumLabel { value: 1, label: "RUN".to_string() }];
+        let descriptor = SignalFrameDescriptor {
+            id: FrameId(1),
+            name: "motor".to_string(),
+            enabled: true,
+            signals: vec![
+                SignalDescriptor::new("current", Type::SFix(16, -1)),
+                SignalDescriptor::new("state", Type::Enum(Box::new(Type::Uint8), labels.clone())),
+                SignalDescriptor::new("phases", Type::Array(Box::new(Type::Int8), 2)),
+            ],
+        };
+
+        let mut value = SignalFrameValue::new(descriptor);
+        value.timestamp = Timestamp { ticks: 1250, seconds: 1.25 };
+        value.data = vec![
+            Value::SFix { w: 16, e: -1, raw: 3 },
+            Type::enum_value(&labels, 1),
+            Value::Array(vec![Value::Int8(-1), Value::Int8(2)]),
+        ];
+        value
+    }
+
+    #[test]
+    fn formats_text_lines() {
+        assert_eq!(text_line(&value()), "1.250000 motor current=1.5 state=RUN phases=[-1, 2]");
+    }
+
+    #[test]
+    fn formats_json_lines() {
+        let line: serde_json::Value = serde_json::from_str(&json_line(&value())).unwrap();
+        assert_eq!(line, json!({
+            "ticks": 1250,
+            "time": 1.25,
+            "id": 1,
+            "frame": "motor",
+            "signals": { "current": 1.5, "state": "RUN", "phases": [-1, 2] },
+        }));
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use serde::Deserialize;
+use sbs_core::clock::DEFAULT_TICK_FREQUENCY;
+use sbs_core::sbs::{FrameId, SignalDescriptor, SignalFrameDescriptor};
+use sbs_core::ty::parse_type_name;
+use crate::source::Source;
+
+/// Configuration of the frames of a simulated device
+///
+/// ```toml
+/// tick_frequency = 1000.0
+///
+/// [[frame]]
+/// id = 1
+/// name = "waves"
+/// rate = 100.0        # frames per second
+///
+/// [[frame.signal]]
+/// name = "sine"
+/// type = "sfix(16,-8)"
+/// unit = "V"
+/// source = { kind = "sine", amplitude = 5.0, frequency = 0.5 }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SimConfig {
+    pub tick_frequency: f64,
+    /// Seed for the noise and random walk sources, random if not set
+    pub seed: Option<u64>,
+    pub frames: Vec<SimFrame>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SimFrame {
+    pub descriptor: SignalFrameDescriptor,
+    /// Frames per second while the frame is enabled
+    pub rate: f64,
+    /// Source of every signal in the descriptor
+    pub sources: Vec<Source>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    tick_frequency: Option<f64>,
+    seed: Option<u64>,
+    #[serde(default, rename = "frame")]
+    frames: Vec<FrameEntry>,
+}
+
+#[derive(Deserialize)]
+struct FrameEntry {
+    id: u32,
+    name: String,
+    rate: f64,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default, rename = "signal")]
+    signals: Vec<SignalEntry>,
+}
+
+#[derive(Deserialize)]
+struct SignalEntry {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    source: Source,
+    unit: Option<String>,
+    scale: Option<f64>,
+    offset: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SimConfigError {
+    Io(String),
+    Parse(String),
+    InvalidType { frame: FrameId, signal: String, ty: String },
+    InvalidRate(FrameId),
+    DuplicateFrame(FrameId),
+}
+
+impl Display for SimConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimConfigError::Io(e) => write!(f, "Failed to read simulator config: {e}"),
+            SimConfigError::Parse(e) => write!(f, "Invalid simulator config: {e}"),
+            SimConfigError::InvalidType { frame, signal, ty } =>
+                write!(f, "Invalid type {ty} for signal {signal} in frame {}", frame.0),
+            SimConfigError::InvalidRate(id) => write!(f, "Rate of frame {} must be above 0 and at most {MAX_RATE}", id.0),
+            SimConfigError::DuplicateFrame(id) => write!(f, "Frame {} is defined more than once", id.0),
+        }
+    }
+}
+
+impl Error for SimConfigError {}
+
+const DEMO_CONFIG: &str = include_str!("../demo.toml");
+
+/// Highest frame rate in frames per second, faster frames would be due all the time
+pub const MAX_RATE: f64 = 1_000_000.0;
+
+impl SimConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<SimConfig, SimConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|e| SimConfigError::Io(e.to_string()))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<SimConfig, SimConfigError> {
+        let file: ConfigFile = toml::from_str(content).map_err(|e| SimConfigError::Parse(e.to_string()))?;
+
+        let mut ids = HashSet::new();
+        let mut frames = Vec::new();
+
+        for frame in file.frames {
+            let id = FrameId(frame.id);
+            if !ids.insert(id) {
+                return Err(SimConfigError::DuplicateFrame(id));
+            }
+            if !(frame.rate > 0.0 && frame.rate <= MAX_RATE) {
+                return Err(SimConfigError::InvalidRate(id));
+            }
+
+            let mut signals = Vec::new();
+            let mut sources = Vec::new();
+            for s in frame.signals {
+                let ty = parse_type_name(&s.ty).ok_or_else(|| SimConfigError::InvalidType {
+                    frame: id,
+                    signal: s.name.clone(),
+                    ty: s.ty.clone(),
+                })?;
+
+                signals.push(SignalDescriptor {
+                    name: s.name,
+                    ty,
+                    unit: s.unit,
+                    scale: s.scale,
+                    offset: s.offset,
+                    min: s.min,
+                    max: s.max,
+                });
+                sources.push(s.source);
+            }
+
+            frames.push(SimFrame {
+                descriptor: SignalFrameDescriptor {
+                    id,
+                    name: frame.name,
+                    enabled: frame.enabled,
+                    signals,
+                },
+                rate: frame.rate,
+                sources,
+            });
+        }
+
+        Ok(SimConfig {
+            tick_frequency: file.tick_frequency.unwrap_or(DEFAULT_TICK_FREQUENCY),
+            seed: file.seed,
+            frames,
+        })
+    }
+
+    /// A set of frames showing every source, used when no configuration is given
+    pub fn demo() -> SimConfig {
+        Self::parse(DEMO_CONFIG).expect("demo config is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sbs_core::ty::Type;
+
+    #[test]
+    fn parses_config() {
+        let config = SimConfig::parse(r#"
+            seed = 3
+
+            [[frame]]
+            id = 7
+            name = "motor"
+            rate = 50.0
+            enabled = true
+
+            [[frame.signal]]
+            name = "current"
+            type = "int16"
+            unit = "A"
+            scale = 0.01
+            source = { kind = "square", amplitude = 2.0, frequency = 1.0 }
+        "#).unwrap();
+
+        assert_eq!(config.tick_frequency, DEFAULT_TICK_FREQUENCY);
+        assert_eq!(config.seed, Some(3));
+
+        let frame = &config.frames[0];
+        assert!(frame.descriptor.enabled);
+        assert_eq!(frame.descriptor.signals[0].ty, Type::Int16);
+        assert_eq!(frame.sources[0], Source::Square { amplitude: 2.0, frequency: 1.0, offset: 0.0, duty: 0.5 });
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        let zero_rate = "[[frame]]\nid = 1\nname = \"a\"\nrate = 0.0";
+        assert!(matches!(SimConfig::parse(zero_rate), Err(SimConfigError::InvalidRate(FrameId(1)))));
+        for rate in ["-1.0", "nan", "inf"] {
+            let invalid_rate = format!("[[frame]]\nid = 2\nname = \"a\"\nrate = {rate}");
+            assert!(matches!(SimConfig::parse(&invalid_rate), Err(SimConfigError::InvalidRate(FrameId(2)))));
+        }
+
+        let unknown_source = "[[frame]]\nid = 1\nname = \"a\"\nrate = 1.0\n[[frame.signal]]\nname = \"x\"\ntype = \"int8\"\nsource = { kind = \"chirp\" }";
+        assert!(matches!(SimConfig::parse(unknown_source), Err(SimConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn demo_config_is_valid() {
+        let config = SimConfig::demo();
+        assert!(!config.frames.is_empty());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod source;
+pub mod config;
+pub mod simulation;
+pub mod sim_client;
@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use sbs_core::clock::Clock;
+use sbs_core::dispatch::Dispatcher;
+use sbs_core::error::Error;
+use sbs_core::sbs::{CallbackHandle, Client, ConnectionState, FrameId, SignalFrameCallback, SignalFrameDescriptor};
+use sbs_core::subscription::{FrameFilter, Subscription, SubscriptionOptions};
+use crate::config::SimConfig;
+use crate::simulation::Simulation;
+
+/// Longest time the generator thread sleeps, so it notices being stopped or frames being enabled
+const MAX_SLEEP: Duration = Duration::from_millis(10);
+
+/// [`Client`] for a simulated device, generating the signals of its frames in-process
+pub struct SimClient {
+    sim: Arc<Mutex<Simulation>>,
+    dispatcher: Arc<Dispatcher>,
+    running: Arc<AtomicBool>,
+    generator_thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for SimClient {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[async_trait]
+impl Client for SimClient {
+    async fn get_frames(&mut self) -> Result<Vec<SignalFrameDescriptor>, Error> {
+        Ok(self.sim.lock().unwrap().frames().cloned().collect())
+    }
+
+    async fn enable_frame(&mut self, frame_id: FrameId) -> Result<(), Error> {
+        self.set_enabled(frame_id, true)
+    }
+
+    async fn disable_frame(&mut self, frame_id: FrameId) -> Result<(), Error> {
+        self.set_enabled(frame_id, false)
+    }
+
+    async fn add_callback(&mut self, filter: FrameFilter, cb: Box<dyn SignalFrameCallback>) -> CallbackHandle {
+        self.dispatcher.add_callback(filter, cb)
+    }
+
+    async fn remove_callback(&mut self, handle: CallbackHandle) -> bool {
+        self.dispatcher.remove_callback(handle)
+    }
+
+    async fn subscribe(&mut self, filter: FrameFilter, options: SubscriptionOptions) -> Subscription {
+        self.dispatcher.subscribe(filter, options)
+    }
+
+    async fn disconnect(&mut self) -> Result<(), Error> {
+        self.stop();
+
+        let mut sim = self.sim.lock().unwrap();
+        let frame_ids = sim.frames().map(|frame| frame.id).collect::<Vec<_>>();
+        for frame_id in frame_ids {
+            sim.set_enabled(frame_id, false);
+        }
+        self.dispatcher.clear();
+
+        Ok(())
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        if self.generator_thread.is_some() {
+            ConnectionState::Connected
+        } else {
+            ConnectionState::Disconnected
+        }
+    }
+}
+
+impl SimClient {
+    pub fn new(config: SimConfig) -> SimClient {
+        let mut clock = Clock::new(config.tick_frequency);
+        let sim = Arc::new(Mutex::new(Simulation::new(config)));
+        let dispatcher = Arc::new(Dispatcher::new());
+        let running = Arc::new(AtomicBool::new(true));
+
+        SimClient {
+            sim: sim.clone(),
+            dispatcher: dispatcher.clone(),
+            running: running.clone(),
+            generator_thread: Some(thread::spawn(move || {
+                while running.load(Ordering::SeqCst) {
+                    let now = Instant::now();
+                    let (due_frames, next_due) = {
+                        let mut sim = sim.lock().unwrap();
+                        (sim.poll(now), sim.next_due())
+                    };
+
+                    for (raw_ticks, mut value) in due_frames {
+                        value.timestamp = clock.update(raw_ticks).0;
+                        dispatcher.dispatch(value.descriptor.id, &value);
+                    }
+
+                    let wakeup = next_due.map_or(now + MAX_SLEEP, |due| due.min(now + MAX_SLEEP));
+                    thread::sleep(wakeup.saturating_duration_since(Instant::now()));
+                }
+            })),
+        }
+    }
+
+    /// Stops the generator thread, and waits for it to finish
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.generator_thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn set_enabled(&mut self, frame_id: FrameId, enabled: bool) -> Result<(), Error> {
+        if self.sim.lock().unwrap().set_enabled(frame_id, enabled) {
+            Ok(())
+        } else {
+            Err(Error::UnknownFrame(frame_id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    #[test]
+    fn generates_enabled_frames() {
+        let mut config = SimConfig::demo();
+        config.seed = Some(1);
+        let mut client = SimClient::new(config);
+
+        let frames = block_on(client.get_frames()).unwrap();
+        assert!(frames.iter().all(|f| !f.enabled));
+
+        let mut subscription = block_on(client.subscribe(FrameFilter::All, SubscriptionOptions::default()));
+        block_on(client.enable_frame(FrameId(1))).unwrap();
+        assert_eq!(block_on(client.enable_frame(FrameId(99))), Err(Error::UnknownFrame(FrameId(99))));
+
+        let received = block_on((&mut subscription).take(5).collect::<Vec<_>>());
+        assert!(received.iter().all(|f| f.descriptor.id == FrameId(1)));
+        assert!(received.windows(2).all(|w| w[0].timestamp.ticks <= w[1].timestamp.ticks));
+
+        let sine = received[4].descriptor.signals[0].to_engineering(&received[4].data[0]);
+        assert!((-5.0..=5.0).contains(&sine));
+
+        assert_eq!(client.connection_state(), ConnectionState::Connected);
+        block_on(client.disconnect()).unwrap();
+        assert_eq!(client.connection_state(), ConnectionState::Disconnected);
+        assert!(block_on(client.get_frames()).unwrap().iter().all(|f| !f.enabled));
+        // The subscription ends instead of waiting for more frames
+        block_on(subscription.count());
+    }
+}
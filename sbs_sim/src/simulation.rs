@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use sbs_core::sbs::{FrameId, SignalFrameDescriptor};
+use sbs_core::value::SignalFrameValue;
+use crate::config::{SimConfig, SimFrame};
+use crate::source::Generator;
+
+struct FrameState {
+    descriptor: SignalFrameDescriptor,
+    period: Duration,
+    next_due: Instant,
+    generators: Vec<Generator>,
+}
+
+/// Frame generation of a simulated device, independent of how the frames are delivered
+pub struct Simulation {
+    start: Instant,
+    tick_frequency: f64,
+    frames: Vec<FrameState>,
+    rng: StdRng,
+}
+
+impl Simulation {
+    pub fn new(config: SimConfig) -> Simulation {
+        let start = Instant::now();
+
+        Simulation {
+            start,
+            tick_frequency: config.tick_frequency,
+            frames: config.frames.into_iter()
+                .map(|SimFrame { descriptor, rate, sources }| FrameState {
+                    descriptor,
+                    period: Duration::from_secs_f64(1.0 / rate),
+                    next_due: start,
+                    generators: sources.into_iter().map(Generator::new).collect(),
+                })
+                .collect(),
+            rng: match config.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+        }
+    }
+
+    pub fn tick_frequency(&self) -> f64 {
+        self.tick_frequency
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item=&SignalFrameDescriptor> {
+        self.frames.iter().map(|fs| &fs.descriptor)
+    }
+
+    pub fn frame(&self, frame_id: FrameId) -> Option<&SignalFrameDescriptor> {
+        self.frames().find(|f| f.id == frame_id)
+    }
+
+    /// Enables or disables generating a frame, returns `false` if the frame doesn't exist
+    pub fn set_enabled(&mut self, frame_id: FrameId, enabled: bool) -> bool {
+        let Some(frame) = self.frames.iter_mut().find(|fs| fs.descriptor.id == frame_id) else {
+            return false;
+        };
+
+        if enabled && !frame.descriptor.enabled {
+            frame.next_due = Instant::now();
+        }
+        frame.descriptor.enabled = enabled;
+
+        true
+    }
+
+    /// Value of the 32-bit device tick counter at `now`, which wraps like the counter of a real device would
+    pub fn raw_ticks(&self, now: Instant) -> u32 {
+        (now.duration_since(self.start).as_secs_f64() * self.tick_frequency) as u64 as u32
+    }
+
+    /// Generates the enabled frames that are due at `now`, along with the raw device timestamp
+    pub fn poll(&mut self, now: Instant) -> Vec<(u32, SignalFrameValue)> {
+        let t = now.duration_since(self.start).as_secs_f64();
+        let raw_ticks = self.raw_ticks(now);
+        let mut due_frames = Vec::new();
+
+        for frame in self.frames.iter_mut().filter(|fs| fs.descriptor.enabled && fs.next_due <= now) {
+            let mut value = SignalFrameValue::new(frame.descriptor.clone());
+            for ((data, signal), generator) in value.data.iter_mut()
+                .zip(&frame.descriptor.signals)
+                .zip(&mut frame.generators) {
+                *data = signal.from_engineering(generator.sample(t, &mut self.rng));
+            }
+            due_frames.push((raw_ticks, value));
+
+            // Skip frames rather than bursting when polling was delayed
+            frame.next_due = (frame.next_due + frame.period).max(now);
+        }
+
+        due_frames
+    }
+
+    /// Time at which the next enabled frame is due, if any frame is enabled
+    pub fn next_due(&self) -> Option<Instant> {
+        self.frames.iter()
+            .filter(|fs| fs.descriptor.enabled)
+            .map(|fs| fs.next_due)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_frames_at_their_rate() {
+        let mut config = SimConfig::demo();
+        config.seed = Some(1);
+        let mut sim = Simulation::new(config);
+        let start = Instant::now();
+
+        assert!(sim.poll(start).is_empty());
+        assert!(sim.set_enabled(FrameId(2), true));
+        assert!(!sim.set_enabled(FrameId(99), true));
+
+        // The sensors frame runs at 20 Hz, so one second yields 20 frames
+        let count = (0..100)
+            .map(|i| sim.poll(start + Duration::from_millis(10 * i)).len())
+            .sum::<usize>();
+        assert_eq!(count, 20);
+        assert!(sim.next_due().is_some());
+
+        sim.set_enabled(FrameId(2), false);
+        assert!(sim.next_due().is_none());
+    }
+}
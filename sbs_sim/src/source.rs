@@ -0,0 +1,140 @@
+use std::f64::consts::TAU;
+use rand::Rng;
+use serde::Deserialize;
+
+/// Waveform that generates the values of a simulated signal, in engineering units
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Source {
+    Sine {
+        amplitude: f64,
+        /// Frequency in Hz
+        frequency: f64,
+        #[serde(default)]
+        offset: f64,
+        /// Phase in radians
+        #[serde(default)]
+        phase: f64,
+    },
+    Square {
+        amplitude: f64,
+        frequency: f64,
+        #[serde(default)]
+        offset: f64,
+        /// Fraction of the period the signal is high
+        #[serde(default = "default_duty")]
+        duty: f64,
+    },
+    /// Sawtooth from `min` to `max`, restarting every `period` seconds
+    Ramp {
+        min: f64,
+        max: f64,
+        period: f64,
+    },
+    /// Uniformly distributed noise around `offset`
+    Noise {
+        amplitude: f64,
+        #[serde(default)]
+        offset: f64,
+    },
+    /// Steps of at most `step` up or down every sample, kept within `min` and `max`
+    RandomWalk {
+        step: f64,
+        #[serde(default)]
+        start: f64,
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+}
+
+fn default_duty() -> f64 {
+    0.5
+}
+
+/// A source along with the state it needs between samples
+#[derive(Clone, Debug)]
+pub struct Generator {
+    source: Source,
+    value: f64,
+}
+
+impl Generator {
+    pub fn new(source: Source) -> Generator {
+        let value = match &source {
+            Source::RandomWalk { start, .. } => *start,
+            _ => 0.0,
+        };
+
+        Generator { source, value }
+    }
+
+    /// Returns the value at `t` seconds
+    pub fn sample(&mut self, t: f64, rng: &mut impl Rng) -> f64 {
+        match &self.source {
+            Source::Sine { amplitude, frequency, offset, phase } =>
+                offset + amplitude * (TAU * frequency * t + phase).sin(),
+            Source::Square { amplitude, frequency, offset, duty } => {
+                if (t * frequency).fract() < *duty {
+                    offset + amplitude
+                } else {
+                    offset - amplitude
+                }
+            }
+            Source::Ramp { min, max, period } =>
+                min + (max - min) * (t / period).fract(),
+            Source::Noise { amplitude, offset } =>
+                offset + amplitude * rng.gen_range(-1.0..=1.0),
+            Source::RandomWalk { step, min, max, .. } => {
+                let mut value = self.value + step * rng.gen_range(-1.0..=1.0);
+                if let Some(min) = min {
+                    value = value.max(*min);
+                }
+                if let Some(max) = max {
+                    value = value.min(*max);
+                }
+
+                self.value = value;
+                value
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn samples(source: Source, times: &[f64]) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut generator = Generator::new(source);
+        times.iter().map(|&t| generator.sample(t, &mut rng)).collect()
+    }
+
+    #[test]
+    fn generates_periodic_waveforms() {
+        let sine = samples(Source::Sine { amplitude: 2.0, frequency: 1.0, offset: 1.0, phase: 0.0 }, &[0.0, 0.25, 0.75]);
+        assert!((sine[0] - 1.0).abs() < 1e-9);
+        assert!((sine[1] - 3.0).abs() < 1e-9);
+        assert!((sine[2] + 1.0).abs() < 1e-9);
+
+        let square = samples(Source::Square { amplitude: 1.0, frequency: 2.0, offset: 0.0, duty: 0.25 }, &[0.0, 0.1, 0.2, 0.5]);
+        assert_eq!(square, vec![1.0, 1.0, -1.0, 1.0]);
+
+        let ramp = samples(Source::Ramp { min: -1.0, max: 1.0, period: 2.0 }, &[0.0, 1.0, 2.5]);
+        assert_eq!(ramp, vec![-1.0, 0.0, -0.5]);
+    }
+
+    #[test]
+    fn keeps_random_sources_in_range() {
+        let times = (0..1000).map(|i| i as f64).collect::<Vec<_>>();
+
+        let noise = samples(Source::Noise { amplitude: 0.5, offset: 10.0 }, &times);
+        assert!(noise.iter().all(|v| (9.5..=10.5).contains(v)));
+
+        let walk = samples(Source::RandomWalk { step: 1.0, start: 0.0, min: Some(-3.0), max: Some(3.0) }, &times);
+        assert!(walk.iter().all(|v| (-3.0..=3.0).contains(v)));
+        assert!(walk.windows(2).all(|w| (w[1] - w[0]).abs() <= 1.0));
+    }
+}
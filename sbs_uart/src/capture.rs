@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::transport::Transport;
+
+/// Start of every capture file, the last byte is the format version
+const MAGIC: &[u8; 8] = b"SBSCAP\x00\x01";
+
+/// Size of the record header: host time in microseconds, direction and data length
+const RECORD_HEADER_LEN: usize = 13;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Bytes received from the device, as fed to the decoder
+    Rx,
+    /// Commands sent to the device
+    Tx,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureRecord {
+    /// Host time since the capture started
+    pub time: Duration,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+/// Raw byte stream of a session, as recorded by a [`CaptureTransport`]
+///
+/// The file starts with `SBSCAP\0\x01`, followed by records of a u64 LE host time in
+/// microseconds, a direction byte (0 received, 1 sent), a u32 LE length and the bytes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Capture {
+    pub records: Vec<CaptureRecord>,
+}
+
+impl Capture {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Capture> {
+        Capture::parse(&std::fs::read(path)?)
+    }
+
+    /// Parses a capture, a record cut short by the recording being interrupted is left out
+    pub fn parse(mut bytes: &[u8]) -> io::Result<Capture> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not an SBS capture file"));
+        }
+        bytes = &bytes[MAGIC.len()..];
+
+        let mut records = Vec::new();
+        while bytes.len() >= RECORD_HEADER_LEN {
+            let time = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+            let direction = match bytes[8] {
+                0 => Direction::Rx,
+                1 => Direction::Tx,
+                d => return Err(io::Error::new(ErrorKind::InvalidData, format!("Invalid record direction {d}"))),
+            };
+            let len = u32::from_le_bytes(bytes[9..13].try_into().unwrap()) as usize;
+
+            let Some(data) = bytes.get(RECORD_HEADER_LEN..RECORD_HEADER_LEN + len) else {
+                break;
+            };
+
+            records.push(CaptureRecord {
+                time: Duration::from_micros(time),
+                direction,
+                data: data.to_vec(),
+            });
+            bytes = &bytes[RECORD_HEADER_LEN + len..];
+        }
+
+        Ok(Capture { records })
+    }
+
+    /// Host time of the last record
+    pub fn duration(&self) -> Duration {
+        self.records.last().map_or(Duration::ZERO, |r| r.time)
+    }
+}
+
+/// Appends records to a capture file
+#[derive(Debug)]
+pub struct CaptureWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    start: Instant,
+}
+
+impl CaptureWriter {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<CaptureWriter> {
+        let mut file = BufWriter::new(File::create(path.as_ref())?);
+        file.write_all(MAGIC)?;
+        file.flush()?;
+
+        Ok(CaptureWriter {
+            path: path.as_ref().to_path_buf(),
+            file,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a record, flushed right away so the capture survives a crash
+    pub fn record(&mut self, direction: Direction, data: &[u8]) -> io::Result<()> {
+        let time = self.start.elapsed().as_micros() as u64;
+
+        self.file.write_all(&time.to_le_bytes())?;
+        self.file.write_all(&[match direction {
+            Direction::Rx => 0,
+            Direction::Tx => 1,
+        }])?;
+        self.file.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.file.write_all(data)?;
+        self.file.flush()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptureConfig<C> {
+    /// Config of the transport that is recorded
+    pub inner: C,
+    /// Shared, so reconnects keep writing to the same capture
+    pub writer: Arc<Mutex<CaptureWriter>>,
+}
+
+impl<C> CaptureConfig<C> {
+    /// Creates the capture file, recording starts when the transport is opened
+    pub fn new(inner: C, path: impl AsRef<Path>) -> io::Result<CaptureConfig<C>> {
+        Ok(CaptureConfig {
+            inner,
+            writer: Arc::new(Mutex::new(CaptureWriter::create(path)?)),
+        })
+    }
+}
+
+/// [`Transport`] that records everything read from and written to another transport
+pub struct CaptureTransport<T> {
+    inner: T,
+    writer: Arc<Mutex<CaptureWriter>>,
+}
+
+impl<T: Transport> CaptureTransport<T> {
+    fn record(&self, direction: Direction, data: &[u8]) {
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(err) = writer.record(direction, data) {
+            eprintln!("Failed to write capture {}: {err}", writer.path().display());
+        }
+    }
+}
+
+impl<T: Transport> Transport for CaptureTransport<T> {
+    type Config = CaptureConfig<T::Config>;
+
+    fn open(config: &Self::Config) -> io::Result<Self> {
+        Ok(CaptureTransport {
+            inner: T::open(&config.inner)?,
+            writer: config.writer.clone(),
+        })
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        let n = self.inner.read(buf, timeout)?;
+        if n > 0 {
+            self.record(Direction::Rx, &buf[..n]);
+        }
+
+        Ok(n)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.record(Direction::Tx, data);
+        self.inner.write_all(data)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.inner.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_records() {
+        let path = std::env::temp_dir().join(format!("sbs_capture_{}.sbscap", std::process::id()));
+        let mut writer = CaptureWriter::create(&path).unwrap();
+        writer.record(Direction::Tx, b"lL").unwrap();
+        writer.record(Direction::Rx, &[0xBB; 5]).unwrap();
+        drop(writer);
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let capture = Capture::parse(&bytes).unwrap();
+        assert_eq!(capture.records.len(), 2);
+        assert_eq!((capture.records[0].direction, capture.records[0].data.as_slice()), (Direction::Tx, b"lL".as_slice()));
+        assert_eq!((capture.records[1].direction, capture.records[1].data.as_slice()), (Direction::Rx, [0xBB; 5].as_slice()));
+        assert!(capture.records[0].time <= capture.records[1].time);
+
+        // An interrupted recording loses only the last record
+        bytes.truncate(bytes.len() - 1);
+        assert_eq!(Capture::parse(&bytes).unwrap().records.len(), 1);
+
+        assert!(Capture::parse(b"not a capture").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+use crate::protocol::Command;
+
+/// Shortest read timeout, transports can't wait for a zero duration
+pub const MIN_READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// How long a command waits for its response, and how often it's sent again when none arrives
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommandPolicy {
+    /// Time the device gets to respond to the command
+    pub timeout: Duration,
+    /// Times the command is sent again after its response was broken, or after it timed out if the
+    /// device echoes sequence numbers
+    pub retries: u32,
+    /// Wait before the first retry, doubled after every further attempt
+    pub backoff: Duration,
+    /// Longest wait between two attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for CommandPolicy {
+    fn default() -> Self {
+        CommandPolicy {
+            timeout: Duration::from_millis(2000),
+            retries: 2,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl CommandPolicy {
+    /// Wait before the retry that follows `attempt`, counted from 0
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_backoff)
+    }
+}
+
+/// Timeouts and retries of the commands sent to a device
+///
+/// The defaults suit a device that answers right away, slow bootloaders and busy targets need
+/// longer timeouts or more retries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionConfig {
+    /// Time to open or close the link
+    pub connect_timeout: Duration,
+    /// Longest time a read from the link blocks the worker, so it keeps picking up commands, at
+    /// least [`MIN_READ_TIMEOUT`]
+    pub read_timeout: Duration,
+    /// Time without data frames while frames are enabled, after which the device is asked whether
+    /// they still are, to detect resets that didn't restart the timestamps. `None` turns it off,
+    /// for devices with frames that are slower than that.
+    pub silence_probe: Option<Duration>,
+    pub list_frames: CommandPolicy,
+    pub get_frame_info: CommandPolicy,
+    pub enable_frame: CommandPolicy,
+    pub disable_frame: CommandPolicy,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        ConnectionConfig {
+            connect_timeout: Duration::from_millis(2000),
+            read_timeout: Duration::from_millis(100),
+            silence_probe: Some(Duration::from_secs(2)),
+            list_frames: CommandPolicy::default(),
+            get_frame_info: CommandPolicy::default(),
+            enable_frame: CommandPolicy::default(),
+            disable_frame: CommandPolicy::default(),
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Sets the same policy for every command
+    pub fn with_command_policy(self, policy: CommandPolicy) -> ConnectionConfig {
+        ConnectionConfig {
+            list_frames: policy,
+            get_frame_info: policy,
+            enable_frame: policy,
+            disable_frame: policy,
+            ..self
+        }
+    }
+
+    /// Sets the longest time a read from the link blocks the worker, raised to [`MIN_READ_TIMEOUT`]
+    pub fn with_read_timeout(self, timeout: Duration) -> ConnectionConfig {
+        ConnectionConfig {
+            read_timeout: timeout.max(MIN_READ_TIMEOUT),
+            ..self
+        }
+    }
+
+    /// Sets the time without data frames after which the device is probed for a reset, `None` to never probe
+    pub fn with_silence_probe(self, interval: Option<Duration>) -> ConnectionConfig {
+        ConnectionConfig {
+            silence_probe: interval,
+            ..self
+        }
+    }
+
+    /// Gives every command `timeout` to respond, keeping the retries
+    pub fn with_response_timeout(mut self, timeout: Duration) -> ConnectionConfig {
+        for policy in [&mut self.list_frames, &mut self.get_frame_info, &mut self.enable_frame, &mut self.disable_frame] {
+            policy.timeout = timeout;
+        }
+        self
+    }
+
+    pub(crate) fn policy(&self, command: &Command) -> &CommandPolicy {
+        match command {
+            Command::ListFrames => &self.list_frames,
+            Command::GetFrameInfo(_) => &self.get_frame_info,
+            Command::EnableFrame(_) => &self.enable_frame,
+            Command::DisableFrame(_) => &self.disable_frame,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt::{Display, Formatter};
+use crate::frame_decoder::DecodeError;
 
 #[derive(Clone, Debug)]
 pub enum Error {
@@ -6,8 +7,13 @@ pub enum Error {
     SerialTimeout,
     Timeout,
     DecodeError(String),
+    CrcError,
     WrongFrame(String),
     InvalidCommand(String),
+    /// The device answered with a NACK
+    Rejected(String),
+    /// The device rejected a command for a frame it doesn't have
+    UnknownFrame(u32),
     Internal(String),
 }
 
@@ -18,15 +24,46 @@ impl Display for Error {
             Error::SerialTimeout => write!(f, "Serial timeout"),
             Error::Timeout => write!(f, "Timeout"),
             Error::DecodeError(e) => write!(f, "Decode error: {e}"),
+            Error::CrcError => write!(f, "Invalid frame CRC"),
             Error::WrongFrame(e) => write!(f, "Wrong frame: {e}"),
             Error::InvalidCommand(e) => write!(f, "Invalid command: {e}"),
+            Error::Rejected(e) => write!(f, "Command rejected: {e}"),
+            Error::UnknownFrame(id) => write!(f, "Unknown frame {id}"),
             Error::Internal(e) => write!(f, "Internal error: {e}")
         }
     }
 }
 
-impl From<Error> for String {
+impl Error {
+    /// Whether the command may pass when sent again, because it or its response got lost or damaged on the way
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::SerialTimeout | Error::Timeout | Error::DecodeError(_) | Error::CrcError | Error::WrongFrame(_))
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(value: DecodeError) -> Self {
+        match value {
+            DecodeError::Crc => Error::CrcError,
+            DecodeError::Malformed(e) => Error::DecodeError(e),
+        }
+    }
+}
+
+impl From<Error> for sbs_core::error::Error {
     fn from(value: Error) -> Self {
-        value.to_string()
+        use sbs_core::error::Error as ClientError;
+
+        match value {
+            Error::SerialError(e) => ClientError::Transport(e),
+            Error::SerialTimeout | Error::Timeout => ClientError::Timeout,
+            Error::DecodeError(e) => ClientError::Protocol(e),
+            Error::CrcError => ClientError::Crc,
+            Error::WrongFrame(e) => ClientError::Protocol(e),
+            Error::InvalidCommand(e) => ClientError::Internal(e),
+            Error::Rejected(e) => ClientError::Protocol(e),
+            Error::UnknownFrame(id) => ClientError::UnknownFrame(sbs_core::sbs::FrameId(id)),
+            Error::Internal(e) => ClientError::Internal(e),
+        }
     }
 }
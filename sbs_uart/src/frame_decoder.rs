@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
 use sbs_core::ty::{parse_type_name, Type};
+use crate::protocol::{NackReason, FRAME_END, FRAME_START, SEQUENCE_PREFIX};
 
 #[derive(Clone, Debug)]
 pub struct FrameInfo {
@@ -11,6 +12,11 @@ pub struct FrameInfo {
 pub struct SignalInfo {
     pub name: String,
     pub ty: Type,
+    pub unit: Option<String>,
+    pub scale: Option<f64>,
+    pub offset: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
 }
 
 #[derive(Clone, Debug)]
@@ -25,14 +31,23 @@ pub enum DecodedFrame {
     GetFrameInfo(FrameDetails),
     EnableFrame,
     DisableFrame,
+    /// The device rejected the command
+    Nack(NackReason),
 }
 
 #[derive(Clone, Debug)]
 pub enum DecodeResult {
     None,
-    CmdFrame(DecodedFrame),
+    /// Response to a command, with the sequence number of the command if the device echoed one
+    CmdFrame(DecodedFrame, Option<u8>),
     SignalFrame(RawSignalFrame),
-    Err(String),
+    Err(DecodeError),
+}
+
+#[derive(Clone, Debug)]
+pub enum DecodeError {
+    Crc,
+    Malformed(String),
 }
 
 #[derive(Clone, Debug)]
@@ -58,6 +73,10 @@ enum DecodeGetFrameInfoState {
     SignalName(u8),
     SignalTypeLen,
     SignalType(u8),
+    SignalUnitLen,
+    SignalUnit(u8),
+    SignalMetaFlags,
+    SignalMetaValues(u8),
 }
 
 #[derive(Clone, Debug)]
@@ -78,6 +97,7 @@ pub struct RawSignalFrame {
 #[derive(Clone, Debug, Default)]
 struct PartialGetFrameInfo {
     enabled: bool,
+    has_metadata: bool,
     num_signals: u32,
     signal_name: String,
     signals: Vec<SignalInfo>,
@@ -89,6 +109,7 @@ enum PayloadType {
     GetFrameInfo,
     EnableFrame,
     DisableFrame,
+    Nack,
     DataFrame,
     NullFrame,
 }
@@ -98,9 +119,11 @@ enum DecoderState {
     StartWord,
     FrameLength,
     PayloadStartChar,
+    SequenceNumber,
     DataFrame(DecodeDataFrameState),
     ListFrames(DecodeListFramesState),
     GetFrameInfo(DecodeGetFrameInfoState),
+    Nack,
     PayloadEndChar(PayloadType, u8),
     Crc(PayloadType),
     EndChar(PayloadType),
@@ -131,14 +154,24 @@ pub struct Decoder {
     offset: usize,
     frame_len: usize,
     frame_start_offset: usize,
+    seq: Option<u8>,
 
     data_frame: RawSignalFrame,
     list_frames: PartialListFrames,
     get_frame_info: PartialGetFrameInfo,
+    nack_reason: u8,
 }
 
-const FRAME_START: u32 = 0xBBBBBBBB;
-const FRAME_END: u8 = 0xEE;
+/// Flags in the first byte of a GetFrameInfo response
+const FRAME_INFO_ENABLED: u8 = 0x01;
+/// Set if every signal in the GetFrameInfo response is followed by a metadata block
+const FRAME_INFO_METADATA: u8 = 0x02;
+
+/// Flags in a signal metadata block, each set flag is followed by an f32 in this order
+const META_SCALE: u8 = 0x01;
+const META_OFFSET: u8 = 0x02;
+const META_MIN: u8 = 0x04;
+const META_MAX: u8 = 0x08;
 
 impl Decoder {
     pub fn new() -> Decoder {
@@ -148,13 +181,20 @@ impl Decoder {
             offset: 0,
             frame_len: 0,
             frame_start_offset: 0,
+            seq: None,
 
             data_frame: Default::default(),
             list_frames: Default::default(),
             get_frame_info: Default::default(),
+            nack_reason: 0,
         }
     }
 
+    /// Whether all data was decoded, without a partial frame or unread bytes left over
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, DecoderState::StartWord) && self.unread_bytes_count() == 0
+    }
+
     pub fn add_data(&mut self, data: &[u8]) {
         self.buffer.extend(data.iter().copied());
         self.buffer.make_contiguous();
@@ -185,6 +225,7 @@ impl Decoder {
                     .map(|fl| {
                         self.frame_len = fl as usize;
                         self.frame_start_offset = self.offset;
+                        self.seq = None;
                         DecoderState::PayloadStartChar
                     }),
                 DecoderState::PayloadStartChar => self.consume_u8()
@@ -203,11 +244,18 @@ impl Decoder {
                         }
                         b'e' => DecoderState::PayloadEndChar(PayloadType::EnableFrame, b'E'),
                         b'd' => DecoderState::PayloadEndChar(PayloadType::DisableFrame, b'D'),
+                        b'n' => DecoderState::Nack,
+                        b'(' => DecoderState::PayloadEndChar(PayloadType::NullFrame, b')'),
+                        SEQUENCE_PREFIX if self.seq.is_none() => DecoderState::SequenceNumber,
                         _ => {
                             clear_read = true;
                             DecoderState::StartWord
                         }
-                        b'(' => DecoderState::PayloadEndChar(PayloadType::NullFrame, b')'),
+                    }),
+                DecoderState::SequenceNumber => self.consume_u8()
+                    .map(|seq| {
+                        self.seq = Some(seq);
+                        DecoderState::PayloadStartChar
                     }),
                 DecoderState::DataFrame(inner) =>
                     self.decode_data_frame(inner),
@@ -217,17 +265,22 @@ impl Decoder {
                     match self.decode_get_frame_info(inner) {
                         Ok(state) => state,
                         Err(errmsg) => {
-                            result = DecodeResult::Err(errmsg);
+                            result = DecodeResult::Err(DecodeError::Malformed(errmsg));
                             clear_read = true;
                             Some(DecoderState::StartWord)
                         }
                     }
+                DecoderState::Nack => self.consume_u8()
+                    .map(|reason| {
+                        self.nack_reason = reason;
+                        DecoderState::PayloadEndChar(PayloadType::Nack, b'N')
+                    }),
                 DecoderState::PayloadEndChar(pt, ec) => {
                     self.consume_u8().map(|ec2| {
                         if ec == ec2 {
                             DecoderState::Crc(pt)
                         } else {
-                            result = DecodeResult::Err(format!("Invalid payload end char {ec2}"));
+                            result = DecodeResult::Err(DecodeError::Malformed(format!("Invalid payload end char {ec2}")));
                             clear_read = true;
                             DecoderState::StartWord
                         }
@@ -242,7 +295,7 @@ impl Decoder {
                         if crc == crc_calc {
                             DecoderState::EndChar(pt)
                         } else {
-                            result = DecodeResult::Err("Invalid frame CRC".to_string());
+                            result = DecodeResult::Err(DecodeError::Crc);
                             clear_read = true;
                             DecoderState::StartWord
                         }
@@ -254,13 +307,14 @@ impl Decoder {
                             clear_read = true;
 
                             result = match pt {
-                                PayloadType::ListFrames => DecodeResult::CmdFrame(DecodedFrame::ListFrames(self.list_frames.frames.clone())),
+                                PayloadType::ListFrames => DecodeResult::CmdFrame(DecodedFrame::ListFrames(self.list_frames.frames.clone()), self.seq),
                                 PayloadType::GetFrameInfo => DecodeResult::CmdFrame(DecodedFrame::GetFrameInfo(FrameDetails {
                                     enabled: self.get_frame_info.enabled,
                                     signals: self.get_frame_info.signals.clone(),
-                                })),
-                                PayloadType::EnableFrame => DecodeResult::CmdFrame(DecodedFrame::EnableFrame),
-                                PayloadType::DisableFrame => DecodeResult::CmdFrame(DecodedFrame::DisableFrame),
+                                }), self.seq),
+                                PayloadType::EnableFrame => DecodeResult::CmdFrame(DecodedFrame::EnableFrame, self.seq),
+                                PayloadType::DisableFrame => DecodeResult::CmdFrame(DecodedFrame::DisableFrame, self.seq),
+                                PayloadType::Nack => DecodeResult::CmdFrame(DecodedFrame::Nack(self.nack_reason.into()), self.seq),
                                 PayloadType::DataFrame => DecodeResult::SignalFrame(self.data_frame.clone()),
                                 PayloadType::NullFrame => result.clone(),
                             };
@@ -268,7 +322,7 @@ impl Decoder {
                             DecoderState::StartWord
                         }
                         _ => {
-                            result = DecodeResult::Err(format!("Invalid frame end character {ec}"));
+                            result = DecodeResult::Err(DecodeError::Malformed(format!("Invalid frame end character {ec}")));
                             clear_read = true;
                             DecoderState::StartWord
                         }
@@ -356,16 +410,12 @@ impl Decoder {
     fn decode_get_frame_info(&mut self, inner: DecodeGetFrameInfoState) -> Result<Option<DecoderState>, String> {
         match inner {
             DecodeGetFrameInfoState::IsEnabled => self.consume_u8()
-                .map(|ie| match ie {
-                    0x00 => {
-                        self.get_frame_info.enabled = false;
-                        Ok(DecodeGetFrameInfoState::NumSignals.into())
-                    }
-                    0x01 => {
-                        self.get_frame_info.enabled = true;
-                        Ok(DecodeGetFrameInfoState::NumSignals.into())
-                    }
-                    _ => Err(format!("Invalid frame enabled value {ie}"))
+                .map(|ie| if ie & !(FRAME_INFO_ENABLED | FRAME_INFO_METADATA) == 0 {
+                    self.get_frame_info.enabled = ie & FRAME_INFO_ENABLED != 0;
+                    self.get_frame_info.has_metadata = ie & FRAME_INFO_METADATA != 0;
+                    Ok(DecodeGetFrameInfoState::NumSignals.into())
+                } else {
+                    Err(format!("Invalid frame enabled value {ie}"))
                 }).transpose(),
             DecodeGetFrameInfoState::NumSignals => Ok(self.consume_u32_le()
                 .map(|ns| {
@@ -388,20 +438,74 @@ impl Decoder {
                 Ok(self.consume_u8()
                     .map(|stl| DecodeGetFrameInfoState::SignalType(stl).into())),
             DecodeGetFrameInfoState::SignalType(len) =>
-                Ok(self.consume_string(len as usize)
-                    .and_then(|tyname| parse_type_name(&tyname))
-                    .map(|ty| {
+                self.consume_string(len as usize)
+                    .map(|tyname| {
+                        let ty = parse_type_name(&tyname)
+                            .ok_or_else(|| format!("Invalid signal type {tyname}"))?;
+
                         self.get_frame_info.signals.push(SignalInfo {
                             name: self.get_frame_info.signal_name.clone(),
                             ty,
+                            unit: None,
+                            scale: None,
+                            offset: None,
+                            min: None,
+                            max: None,
                         });
 
-                        if self.get_frame_info.signals.len() == (self.get_frame_info.num_signals as usize) {
-                            DecoderState::PayloadEndChar(PayloadType::GetFrameInfo, b'I')
+                        if self.get_frame_info.has_metadata {
+                            Ok(DecodeGetFrameInfoState::SignalUnitLen.into())
                         } else {
-                            DecodeGetFrameInfoState::SignalNameLen.into()
+                            Ok(self.next_signal_state())
                         }
-                    }))
+                    }).transpose(),
+            DecodeGetFrameInfoState::SignalUnitLen =>
+                Ok(self.consume_u8()
+                    .map(|ul| DecodeGetFrameInfoState::SignalUnit(ul).into())),
+            DecodeGetFrameInfoState::SignalUnit(len) =>
+                Ok(self.consume_string(len as usize).map(|unit| {
+                    if !unit.is_empty() {
+                        self.get_frame_info.signals.last_mut().unwrap().unit = Some(unit);
+                    }
+                    DecodeGetFrameInfoState::SignalMetaFlags.into()
+                })),
+            // Unknown flags would be followed by values of unknown size, so they can't be skipped
+            DecodeGetFrameInfoState::SignalMetaFlags => self.consume_u8()
+                .map(|flags| if flags & !(META_SCALE | META_OFFSET | META_MIN | META_MAX) == 0 {
+                    Ok(DecodeGetFrameInfoState::SignalMetaValues(flags).into())
+                } else {
+                    Err(format!("Invalid signal metadata flags {flags:#04x}"))
+                }).transpose(),
+            DecodeGetFrameInfoState::SignalMetaValues(flags) =>
+                Ok(self.consume_bytes(4 * flags.count_ones() as usize).map(|bytes| {
+                    let mut values = bytes
+                        .chunks_exact(4)
+                        .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64);
+                    let signal = self.get_frame_info.signals.last_mut().unwrap();
+
+                    if flags & META_SCALE != 0 {
+                        signal.scale = values.next();
+                    }
+                    if flags & META_OFFSET != 0 {
+                        signal.offset = values.next();
+                    }
+                    if flags & META_MIN != 0 {
+                        signal.min = values.next();
+                    }
+                    if flags & META_MAX != 0 {
+                        signal.max = values.next();
+                    }
+
+                    self.next_signal_state()
+                })),
+        }
+    }
+
+    fn next_signal_state(&self) -> DecoderState {
+        if self.get_frame_info.signals.len() == (self.get_frame_info.num_signals as usize) {
+            DecoderState::PayloadEndChar(PayloadType::GetFrameInfo, b'I')
+        } else {
+            DecodeGetFrameInfoState::SignalNameLen.into()
         }
     }
 
@@ -447,11 +551,12 @@ impl Decoder {
         }
     }
 
+    /// Consumes a string sent by the device, replacing invalid UTF-8 rather than failing on it
     fn consume_string(&mut self, len: usize) -> Option<String> {
         if self.unread_bytes_count() < len {
             None
         } else {
-            let ret = String::from(core::str::from_utf8(&self.buffer.as_slices().0[self.offset..self.offset + len]).unwrap());
+            let ret = String::from_utf8_lossy(&self.buffer.as_slices().0[self.offset..self.offset + len]).into_owned();
             self.offset += len;
             Some(ret)
         }
@@ -481,4 +586,96 @@ impl DecodeResult {
     pub fn is_some(&self) -> bool {
         !matches!(self, DecodeResult::None)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sbs_core::sbs::{FrameId, SignalDescriptor, SignalFrameDescriptor};
+    use sbs_core::ty::Type;
+    use sbs_emu::encoder::{encode_frame, frame_info_payload};
+
+    fn frame_with_metadata() -> SignalFrameDescriptor {
+        let signal = |name: &str, unit: Option<&str>, scale, min| SignalDescriptor {
+            name: name.to_string(),
+            ty: Type::Int16,
+            unit: unit.map(str::to_string),
+            scale,
+            offset: None,
+            min,
+            max: None,
+        };
+
+        SignalFrameDescriptor {
+            id: FrameId(1),
+            name: "motor".to_string(),
+            enabled: true,
+            signals: vec![signal("current", Some("A"), Some(0.5), Some(-8.0)), signal("state", None, None, None)],
+        }
+    }
+
+    #[test]
+    fn decodes_frame_info_metadata() {
+        let frame = frame_with_metadata();
+        let mut decoder = Decoder::new();
+        decoder.add_data(&encode_frame(&frame_info_payload(&frame)));
+
+        let DecodeResult::CmdFrame(DecodedFrame::GetFrameInfo(details), None) = decoder.decode() else {
+            panic!("Expected a GetFrameInfo response");
+        };
+        assert!(details.enabled);
+        let decoded = details.signals.iter()
+            .map(|s| (s.name.as_str(), &s.ty, s.unit.as_deref(), s.scale, s.offset, s.min, s.max))
+            .collect::<Vec<_>>();
+        let expected = frame.signals.iter()
+            .map(|s| (s.name.as_str(), &s.ty, s.unit.as_deref(), s.scale, s.offset, s.min, s.max))
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, expected);
+        assert!(decoder.is_idle());
+    }
+
+    #[test]
+    fn skips_null_frames() {
+        let mut decoder = Decoder::new();
+        decoder.add_data(&encode_frame(b"()"));
+        assert!(matches!(decoder.decode(), DecodeResult::None));
+        assert!(decoder.is_idle());
+
+        decoder.add_data(&[encode_frame(b"()"), encode_frame(b"eE")].concat());
+        assert!(matches!(decoder.decode(), DecodeResult::CmdFrame(DecodedFrame::EnableFrame, None)));
+        assert!(decoder.is_idle());
+
+        // A null frame is checked like any other frame
+        let mut damaged = encode_frame(b"()");
+        damaged[9] = b']';
+        decoder.add_data(&damaged);
+        assert!(matches!(decoder.decode(), DecodeResult::Err(DecodeError::Malformed(_))));
+    }
+
+    #[test]
+    fn rejects_unknown_metadata_flags() {
+        let mut payload = frame_info_payload(&frame_with_metadata());
+        // Name, type and unit of the first signal come before its metadata flags
+        let flags_offset = 6 + (1 + "current".len()) + (1 + "int16".len()) + (1 + "A".len());
+        payload[flags_offset] |= 0x10;
+
+        let mut decoder = Decoder::new();
+        decoder.add_data(&encode_frame(&payload));
+        assert!(matches!(decoder.decode(), DecodeResult::Err(DecodeError::Malformed(_))));
+    }
+
+    #[test]
+    fn replaces_invalid_utf8_in_strings() {
+        let mut payload = frame_info_payload(&frame_with_metadata());
+        // Unit of the first signal
+        let unit_offset = 6 + (1 + "current".len()) + (1 + "int16".len()) + 1;
+        payload[unit_offset] = 0xff;
+
+        let mut decoder = Decoder::new();
+        decoder.add_data(&encode_frame(&payload));
+        let DecodeResult::CmdFrame(DecodedFrame::GetFrameInfo(details), None) = decoder.decode() else {
+            panic!("Expected a GetFrameInfo response");
+        };
+        assert_eq!(details.signals[0].unit.as_deref(), Some("\u{fffd}"));
+    }
+}
@@ -1,4 +1,10 @@
 mod serial_worker;
 mod frame_decoder;
+pub mod protocol;
+pub mod connection;
 pub mod error;
+pub mod transport;
+pub mod udp_transport;
+pub mod capture;
+pub mod replay;
 pub mod sbs_uart;
@@ -0,0 +1,177 @@
+use std::fmt::{Display, Formatter};
+
+pub(crate) const FRAME_START: u32 = 0xBBBBBBBB;
+pub(crate) const FRAME_END: u8 = 0xEE;
+
+/// Prefix of a command or response payload that carries a sequence number
+pub(crate) const SEQUENCE_PREFIX: u8 = b'#';
+
+/// Reasons in a NACK response
+const NACK_MALFORMED: u8 = 0x01;
+const NACK_UNKNOWN_COMMAND: u8 = 0x02;
+const NACK_UNKNOWN_FRAME: u8 = 0x03;
+
+/// Options of the SBS protocol spoken with a device, the device has to support the ones that are enabled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProtocolOptions {
+    /// Prefixes every command with `#` and a sequence number, which the device echoes at the start of
+    /// its response payload. Replies are matched to their command, so stale replies are dropped and
+    /// several commands can be sent before the first reply arrives. A NACK for a command whose sequence
+    /// number got damaged carries none, and is taken as the reply to the oldest command in flight.
+    pub sequence_numbers: bool,
+    /// Wraps every command in a frame with start word, length, CRC and end byte, like the responses.
+    /// The device answers a command it can't take with a NACK instead of ignoring it.
+    pub framed_commands: bool,
+}
+
+/// Reason the device gave for rejecting a command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NackReason {
+    /// The command frame was damaged, e.g. its CRC didn't match
+    Malformed,
+    UnknownCommand,
+    UnknownFrame,
+    Other(u8),
+}
+
+impl From<u8> for NackReason {
+    fn from(value: u8) -> Self {
+        match value {
+            NACK_MALFORMED => NackReason::Malformed,
+            NACK_UNKNOWN_COMMAND => NackReason::UnknownCommand,
+            NACK_UNKNOWN_FRAME => NackReason::UnknownFrame,
+            other => NackReason::Other(other),
+        }
+    }
+}
+
+impl Display for NackReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NackReason::Malformed => write!(f, "malformed command"),
+            NackReason::UnknownCommand => write!(f, "unknown command"),
+            NackReason::UnknownFrame => write!(f, "unknown frame"),
+            NackReason::Other(reason) => write!(f, "reason {reason}"),
+        }
+    }
+}
+
+/// Command from the host to a device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    ListFrames,
+    GetFrameInfo(u32),
+    EnableFrame(u32),
+    DisableFrame(u32),
+}
+
+impl Command {
+    /// Encodes the command, prefixed with its sequence number if it has one, and wrapped in a frame if `framed`
+    pub(crate) fn encode(&self, seq: Option<u8>, framed: bool) -> Vec<u8> {
+        let mut data = match seq {
+            Some(seq) => vec![SEQUENCE_PREFIX, seq],
+            None => Vec::with_capacity(6),
+        };
+
+        match self {
+            Command::ListFrames => data.extend_from_slice(b"lL"),
+            Command::GetFrameInfo(frame_id) => data.extend_from_slice(&frame_command(b'i', *frame_id)),
+            Command::EnableFrame(frame_id) => data.extend_from_slice(&frame_command(b'e', *frame_id)),
+            Command::DisableFrame(frame_id) => data.extend_from_slice(&frame_command(b'd', *frame_id)),
+        }
+
+        if framed {
+            encode_frame(&data)
+        } else {
+            data
+        }
+    }
+
+    /// Decodes a command written by the host, with its sequence number if it has one
+    pub(crate) fn decode(data: &[u8]) -> Option<(Option<u8>, Command)> {
+        let data = match data {
+            [0xBB, 0xBB, 0xBB, 0xBB, ..] => frame_payload(data)?,
+            _ => data,
+        };
+
+        let (seq, data) = match data {
+            [SEQUENCE_PREFIX, seq, rest @ ..] => (Some(*seq), rest),
+            _ => (None, data),
+        };
+
+        let command = match data {
+            [b'l', b'L', ..] => Command::ListFrames,
+            [cmd @ (b'i' | b'e' | b'd'), a, b, c, d, end, ..] if *end == cmd.to_ascii_uppercase() => {
+                let frame_id = u32::from_le_bytes([*a, *b, *c, *d]);
+                match cmd {
+                    b'i' => Command::GetFrameInfo(frame_id),
+                    b'e' => Command::EnableFrame(frame_id),
+                    _ => Command::DisableFrame(frame_id),
+                }
+            }
+            _ => return None,
+        };
+
+        Some((seq, command))
+    }
+}
+
+/// Wraps a payload in a frame: start word, payload length, payload, CRC-16/ARC and end byte
+///
+/// Like on the device, the CRC covers the upper three bytes of the length and the payload.
+pub(crate) fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 11);
+    frame.extend_from_slice(&FRAME_START.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload);
+
+    let crc = crc::Crc::<u16>::new(&crc::CRC_16_ARC).checksum(&frame[5..]);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame.push(FRAME_END);
+
+    frame
+}
+
+/// Returns the payload of a frame encoded by [`encode_frame`], if it's complete and intact
+fn frame_payload(frame: &[u8]) -> Option<&[u8]> {
+    let len = u32::from_le_bytes(frame.get(4..8)?.try_into().unwrap()) as usize;
+    let crc_offset = 8usize.checked_add(len)?;
+    let crc = u16::from_le_bytes(frame.get(crc_offset..crc_offset + 2)?.try_into().unwrap());
+
+    let intact = frame.get(crc_offset + 2) == Some(&FRAME_END)
+        && crc == crc::Crc::<u16>::new(&crc::CRC_16_ARC).checksum(&frame[5..crc_offset]);
+    intact.then(|| &frame[8..crc_offset])
+}
+
+/// Encodes a command that takes a frame ID, like `i<id>I`
+fn frame_command(cmd: u8, frame_id: u32) -> [u8; 6] {
+    let mut tx_buf = [cmd, 0, 0, 0, 0, cmd.to_ascii_uppercase()];
+    tx_buf[1..5].copy_from_slice(&frame_id.to_le_bytes());
+    tx_buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_decodes_commands() {
+        assert_eq!(Command::EnableFrame(2).encode(None, false), b"e\x02\x00\x00\x00E");
+        assert_eq!(Command::ListFrames.encode(Some(7), false), b"#\x07lL");
+        assert_eq!(Command::ListFrames.encode(None, true), encode_frame(b"lL"));
+
+        for command in [Command::ListFrames, Command::GetFrameInfo(1), Command::EnableFrame(2), Command::DisableFrame(3)] {
+            for seq in [None, Some(b'#')] {
+                for framed in [false, true] {
+                    assert_eq!(Command::decode(&command.encode(seq, framed)), Some((seq, command)));
+                }
+            }
+        }
+
+        assert_eq!(Command::decode(b"e\x02\x00\x00\x00D"), None);
+
+        let mut damaged = Command::DisableFrame(3).encode(None, true);
+        damaged[9] = 5;
+        assert_eq!(Command::decode(&damaged), None);
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use sbs_core::error::Error as ClientError;
+use sbs_core::overrides::SignalOverrides;
+use sbs_core::sbs::{CallbackHandle, Client, ConnectionState, FrameId, SignalFrameCallback, SignalFrameDescriptor};
+use sbs_core::schema::Schema;
+use sbs_core::subscription::{FrameFilter, Subscription, SubscriptionOptions};
+use crate::capture::{Capture, Direction};
+use crate::frame_decoder::{DecodedFrame, DecodeResult, Decoder, FrameDetails, FrameInfo};
+use crate::protocol::Command;
+use crate::sbs_uart::{frame_descriptor, FrameDiscovery, SbsUart};
+use crate::transport::Transport;
+
+/// Longest time the replay sleeps while paused, so it notices steps and speed changes
+const MAX_SLEEP: Duration = Duration::from_millis(20);
+
+#[derive(Debug)]
+struct ControlState {
+    speed: f64,
+    paused: bool,
+    steps: u32,
+    position: Duration,
+    finished: bool,
+}
+
+/// Playback controls of a replay, shared between the replay transport and the UI
+#[derive(Debug)]
+pub struct ReplayControl {
+    state: Mutex<ControlState>,
+    duration: Duration,
+}
+
+impl ReplayControl {
+    fn new(duration: Duration) -> ReplayControl {
+        ReplayControl {
+            state: Mutex::new(ControlState {
+                speed: 1.0,
+                paused: false,
+                steps: 0,
+                position: Duration::ZERO,
+                finished: false,
+            }),
+            duration,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.state.lock().unwrap().speed
+    }
+
+    /// Sets the playback speed relative to the recording, e.g. 2.0 to play twice as fast
+    pub fn set_speed(&self, speed: f64) {
+        self.state.lock().unwrap().speed = speed.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.state.lock().unwrap().paused = paused;
+    }
+
+    /// Pauses the replay, and plays the next received chunk
+    pub fn step(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = true;
+        state.steps += 1;
+    }
+
+    /// Host time in the recording up to which the replay got
+    pub fn position(&self) -> Duration {
+        self.state.lock().unwrap().position
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().finished
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayConfig {
+    pub capture: Arc<Capture>,
+    pub control: Arc<ReplayControl>,
+}
+
+/// [`Transport`] that plays back the bytes received in a capture, at their recorded host times
+///
+/// Commands written to it are ignored, the responses to the recorded commands are part of the replay.
+pub struct ReplayTransport {
+    capture: Arc<Capture>,
+    control: Arc<ReplayControl>,
+    next_record: usize,
+    last_update: Instant,
+    rx: VecDeque<u8>,
+}
+
+impl ReplayTransport {
+    fn next_rx_record(&self) -> Option<usize> {
+        (self.next_record..self.capture.records.len())
+            .find(|&i| self.capture.records[i].direction == Direction::Rx)
+    }
+
+    /// Queues the records that are due, returns the time until the next one in the recording
+    fn advance(&mut self) -> Option<Duration> {
+        let mut guard = self.control.state.lock().unwrap();
+        let state = &mut *guard;
+
+        let now = Instant::now();
+        if !state.paused {
+            state.position += now.duration_since(self.last_update).mul_f64(state.speed);
+        }
+        self.last_update = now;
+
+        while let Some(i) = self.next_rx_record() {
+            let record = &self.capture.records[i];
+            if record.time <= state.position {
+                // Playing catches up with the recording
+            } else if state.paused && state.steps > 0 {
+                state.steps -= 1;
+                state.position = record.time;
+            } else {
+                state.finished = false;
+                return (!state.paused && state.speed > 0.0)
+                    .then(|| (record.time - state.position).div_f64(state.speed));
+            }
+
+            self.rx.extend(&record.data);
+            self.next_record = i + 1;
+        }
+
+        state.steps = 0;
+        state.finished = true;
+        None
+    }
+}
+
+impl Transport for ReplayTransport {
+    type Config = ReplayConfig;
+
+    fn open(config: &ReplayConfig) -> io::Result<Self> {
+        Ok(ReplayTransport {
+            capture: config.capture.clone(),
+            control: config.control.clone(),
+            next_record: 0,
+            last_update: Instant::now(),
+            rx: VecDeque::new(),
+        })
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let next_due = self.advance();
+
+            if !self.rx.is_empty() {
+                let n = buf.len().min(self.rx.len());
+                for (dst, src) in buf.iter_mut().zip(self.rx.drain(..n)) {
+                    *dst = src;
+                }
+                return Ok(n);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ErrorKind::TimedOut.into());
+            }
+
+            thread::sleep(next_due.unwrap_or(MAX_SLEEP).min(MAX_SLEEP).min(deadline - now));
+        }
+    }
+
+    fn write_all(&mut self, _data: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What the device announced during the recorded session
+#[derive(Default)]
+struct RecordedDiscovery {
+    frames: Vec<FrameInfo>,
+    details: HashMap<u32, FrameDetails>,
+    frames_with_data: HashSet<FrameId>,
+}
+
+impl RecordedDiscovery {
+    /// Decodes the capture, matching the recorded responses with the commands they answer
+    fn scan(capture: &Capture) -> RecordedDiscovery {
+        let mut discovery = RecordedDiscovery::default();
+        let mut decoder = Decoder::new();
+        let mut last_command = None;
+        let mut sequenced = HashMap::new();
+
+        for record in &capture.records {
+            if record.direction == Direction::Tx {
+                match Command::decode(&record.data) {
+                    Some((Some(seq), command)) => { sequenced.insert(seq, command); }
+                    Some((None, command)) => last_command = Some(command),
+                    None => {}
+                }
+                continue;
+            }
+
+            decoder.add_data(&record.data);
+            loop {
+                match decoder.decode() {
+                    DecodeResult::None => break,
+                    DecodeResult::CmdFrame(DecodedFrame::ListFrames(frames), _) => discovery.frames = frames,
+                    DecodeResult::CmdFrame(DecodedFrame::GetFrameInfo(details), seq) => {
+                        let command = match seq {
+                            Some(seq) => sequenced.get(&seq).copied(),
+                            None => last_command,
+                        };
+                        if let Some(Command::GetFrameInfo(frame_id)) = command {
+                            discovery.details.insert(frame_id, details);
+                        }
+                    }
+                    DecodeResult::CmdFrame(..) | DecodeResult::Err(_) => {}
+                    DecodeResult::SignalFrame(frame) => {
+                        discovery.frames_with_data.insert(FrameId(frame.frame_id));
+                    }
+                }
+            }
+        }
+
+        discovery
+    }
+
+    fn descriptors(&self) -> Vec<SignalFrameDescriptor> {
+        self.frames.iter()
+            .filter_map(|info| match self.details.get(&info.id) {
+                Some(details) => Some(frame_descriptor(info, details)),
+                None => {
+                    eprintln!("Capture has no frame info for frame {}", info.id);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// [`Client`] replaying a capture through the decoder and [`SbsUart`], as if the device was connected
+///
+/// The frames are taken from the discovery in the recorded session, or from a schema. Enabling and
+/// disabling frames only changes what is reported, the replay contains what the device sent.
+pub struct SbsReplay {
+    client: SbsUart<ReplayTransport>,
+    capture: Arc<Capture>,
+    control: Arc<ReplayControl>,
+    frames: Vec<SignalFrameDescriptor>,
+    tick_frequency: Option<f64>,
+    enabled: HashSet<FrameId>,
+}
+
+impl SbsReplay {
+    pub fn new(capture: Capture) -> SbsReplay {
+        let discovery = RecordedDiscovery::scan(&capture);
+
+        SbsReplay {
+            client: SbsUart::new(),
+            control: Arc::new(ReplayControl::new(capture.duration())),
+            capture: Arc::new(capture),
+            frames: discovery.descriptors(),
+            tick_frequency: None,
+            enabled: discovery.frames_with_data,
+        }
+    }
+
+    pub fn control(&self) -> Arc<ReplayControl> {
+        self.control.clone()
+    }
+
+    /// Uses the frames from the schema instead of those announced in the recording
+    pub fn set_schema(&mut self, schema: Schema) {
+        self.tick_frequency = self.tick_frequency.or(schema.tick_frequency);
+        self.frames = schema.frames;
+    }
+
+    /// Overrides the tick frequency of the schema
+    pub fn set_tick_frequency(&mut self, tick_frequency: Option<f64>) {
+        if tick_frequency.is_some() {
+            self.tick_frequency = tick_frequency;
+        }
+    }
+
+    pub fn set_signal_overrides(&mut self, overrides: SignalOverrides) {
+        self.client.set_signal_overrides(overrides);
+    }
+
+    /// Starts playing back the capture
+    pub async fn start(&mut self) -> Result<(), ClientError> {
+        if self.frames.is_empty() {
+            return Err(ClientError::Protocol("Capture does not contain the frames of the device, a schema is needed".to_string()));
+        }
+
+        self.client.set_frame_discovery(FrameDiscovery::Schema(Schema::from(self.frames.clone())));
+        self.client.set_tick_frequency(self.tick_frequency);
+
+        // Frames are only decoded once their descriptors are loaded, which takes no device with a schema
+        self.client.get_frames().await?;
+
+        self.client.connect(ReplayConfig {
+            capture: self.capture.clone(),
+            control: self.control.clone(),
+        }).await
+    }
+
+    fn set_enabled(&mut self, frame_id: FrameId, enabled: bool) -> Result<(), ClientError> {
+        if !self.frames.iter().any(|f| f.id == frame_id) {
+            return Err(ClientError::UnknownFrame(frame_id));
+        }
+
+        if enabled {
+            self.enabled.insert(frame_id);
+        } else {
+            self.enabled.remove(&frame_id);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Client for SbsReplay {
+    async fn get_frames(&mut self) -> Result<Vec<SignalFrameDescriptor>, ClientError> {
+        let mut frames = self.client.get_frames().await?;
+        for frame in &mut frames {
+            frame.enabled = self.enabled.contains(&frame.id);
+        }
+
+        Ok(frames)
+    }
+
+    async fn enable_frame(&mut self, frame_id: FrameId) -> Result<(), ClientError> {
+        self.set_enabled(frame_id, true)
+    }
+
+    async fn disable_frame(&mut self, frame_id: FrameId) -> Result<(), ClientError> {
+        self.set_enabled(frame_id, false)
+    }
+
+    async fn add_callback(&mut self, filter: FrameFilter, cb: Box<dyn SignalFrameCallback>) -> CallbackHandle {
+        self.client.add_callback(filter, cb).await
+    }
+
+    async fn remove_callback(&mut self, handle: CallbackHandle) -> bool {
+        self.client.remove_callback(handle).await
+    }
+
+    async fn subscribe(&mut self, filter: FrameFilter, options: SubscriptionOptions) -> Subscription {
+        self.client.subscribe(filter, options).await
+    }
+
+    async fn disconnect(&mut self) -> Result<(), ClientError> {
+        self.enabled.clear();
+        self.client.disconnect().await
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        self.client.connection_state()
+    }
+}
@@ -1,98 +1,289 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use async_trait::async_trait;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::task::JoinHandle;
-use sbs_core::sbs::{Client, SignalFrameDescriptor, FrameId, SignalDescriptor, SignalFrameCallback};
+use sbs_core::clock::{Clock, DEFAULT_TICK_FREQUENCY};
+use sbs_core::dispatch::Dispatcher;
+use sbs_core::sbs::{CallbackHandle, Client, ConnectionState, SignalFrameDescriptor, FrameId, SignalDescriptor, SignalFrameCallback};
+use sbs_core::subscription::{FrameFilter, Subscription, SubscriptionOptions};
+use sbs_core::error::Error as ClientError;
+use sbs_core::overrides::SignalOverrides;
+use sbs_core::schema::Schema;
 use sbs_core::value::SignalFrameValue;
 use crate::error::Error;
-use crate::frame_decoder::RawSignalFrame;
-use crate::serial_worker::SerialWorker;
+use crate::frame_decoder::{DecodeError, FrameDetails, FrameInfo, RawSignalFrame};
+use crate::connection::ConnectionConfig;
+use crate::protocol::ProtocolOptions;
+use crate::serial_worker::{LinkEvent, SerialWorker};
+use crate::transport::{SerialTransport, TcpTransport, Transport};
+
+/// How [`SbsUart`] finds out which frames the device sends
+#[derive(Clone, Debug, Default)]
+pub enum FrameDiscovery {
+    /// Query the frames from the device with the `l` and `i` commands
+    #[default]
+    Introspect,
+    /// Take the frames from the schema without querying the device
+    Schema(Schema),
+    /// Query the frames from the device, and warn where they differ from the schema
+    CrossCheck(Schema),
+}
+
+/// Counters of the data frames received from the device, and of those that couldn't be decoded
+#[derive(Debug, Default)]
+pub struct DecodeStats {
+    frames: AtomicU64,
+    crc_errors: AtomicU64,
+    malformed: AtomicU64,
+    undecodable: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl DecodeStats {
+    pub fn snapshot(&self) -> DecodeStatsSnapshot {
+        DecodeStatsSnapshot {
+            frames: self.frames.load(Ordering::Relaxed),
+            crc_errors: self.crc_errors.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+            undecodable: self.undecodable.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn count_error(&self, err: &DecodeError) {
+        match err {
+            DecodeError::Crc => self.crc_errors.fetch_add(1, Ordering::Relaxed),
+            DecodeError::Malformed(_) => self.malformed.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub(crate) fn count_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodeStatsSnapshot {
+    /// Data frames decoded and dispatched
+    pub frames: u64,
+    /// Frames dropped because their CRC didn't match
+    pub crc_errors: u64,
+    /// Frames dropped because their envelope was invalid
+    pub malformed: u64,
+    /// Data frames of unknown frames, or with a payload that doesn't match the frame's signals
+    pub undecodable: u64,
+    /// Data frames dropped because decoding fell behind the device
+    pub dropped: u64,
+}
+
+impl DecodeStatsSnapshot {
+    pub fn errors(&self) -> u64 {
+        self.crc_errors + self.malformed + self.undecodable + self.dropped
+    }
+}
+
+impl Display for DecodeStatsSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} frames decoded, {} CRC errors, {} malformed, {} undecodable, {} dropped",
+               self.frames, self.crc_errors, self.malformed, self.undecodable, self.dropped)
+    }
+}
 
 struct FrameState {
     descriptor: SignalFrameDescriptor,
     latest_value: SignalFrameValue,
 }
 
-pub struct SbsUart {
-    serial_worker: SerialWorker,
-    frame_descriptors: Arc<RwLock<Option<HashMap<FrameId, FrameState>>>>,
-    #[allow(dead_code)]
+/// First delay before retrying to restore the session after a reconnect or device reset
+const RECOVERY_BACKOFF_MIN: Duration = Duration::from_millis(250);
+
+/// Longest delay between attempts to restore the session
+const RECOVERY_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Time the supervisor waits before checking again while there's no silence probe
+const SILENCE_PROBE_IDLE: Duration = Duration::from_secs(1);
+
+/// State shared between [`SbsUart`] and its background tasks
+struct Shared<T: Transport> {
+    serial_worker: tokio::sync::Mutex<SerialWorker<T>>,
+    frame_descriptors: RwLock<Option<HashMap<FrameId, FrameState>>>,
+    signal_overrides: Mutex<SignalOverrides>,
+    discovery: Mutex<FrameDiscovery>,
+    connection_state: watch::Sender<ConnectionState>,
+    clock: Mutex<Clock>,
+    /// Time the last data frame arrived
+    last_frame: Mutex<Instant>,
+    /// Silence probe interval of the current connection, see [`ConnectionConfig::silence_probe`]
+    silence_probe: Mutex<Option<Duration>>,
+}
+
+/// [`Client`] for a device speaking the SBS protocol, over a serial port unless another [`Transport`] is used
+///
+/// When the link is lost the port is reopened with backoff. After it was reopened, or when the
+/// device timestamps show the device was reset, the frames are discovered again and the frames
+/// that were enabled are enabled again. A reset device that streams nothing is found by asking it
+/// about the enabled frames once they went silent. Progress is reported through [`SbsUart::connection_events`].
+pub struct SbsUart<T: Transport = SerialTransport> {
+    shared: Arc<Shared<T>>,
     frame_reader_thread: JoinHandle<()>,
+    supervisor_task: JoinHandle<()>,
 
-    callbacks: Arc<RwLock<Vec<Box<dyn SignalFrameCallback>>>>,
+    dispatcher: Arc<Dispatcher>,
+
+    tick_frequency: Option<f64>,
+    protocol: ProtocolOptions,
+    connection: ConnectionConfig,
+    stats: Arc<DecodeStats>,
 }
 
 
+/// [`SbsUart`] speaking the same framed protocol over TCP, e.g. through ser2net or a Wi-Fi serial bridge
+pub type SbsTcp = SbsUart<TcpTransport>;
+
 #[async_trait]
-impl Client for SbsUart {
-    async fn get_frames(&mut self) -> Result<Vec<SignalFrameDescriptor>, String> {
-        self.ensure_frame_descriptors_loaded().await?;
+impl<T: Transport> Client for SbsUart<T> {
+    async fn get_frames(&mut self) -> Result<Vec<SignalFrameDescriptor>, ClientError> {
+        self.shared.load_frame_descriptors().await?;
 
-        let mut frames = self.frame_descriptors.read().await.as_ref().unwrap()
+        let mut frames = self.shared.frame_descriptors.read().await.as_ref().unwrap()
             .values()
             .map(|fs| fs.descriptor.clone()).collect::<Vec<_>>();
-        frames.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+        frames.sort_by_key(|frame| frame.id.0);
 
         Ok(frames)
     }
 
-    async fn enable_frame(&mut self, frame_id: FrameId) -> Result<(), String> {
-        self.serial_worker.enable_frame(frame_id.0).await?;
+    async fn enable_frame(&mut self, frame_id: FrameId) -> Result<(), ClientError> {
+        self.ensure_frame_known(frame_id).await?;
+        self.shared.serial_worker.lock().await.enable_frame(frame_id.0).await?;
+        self.shared.set_enabled(frame_id, true).await;
 
-        if let Some(ref mut descriptors) = &mut *self.frame_descriptors.write().await {
-            if let Some(entry) = descriptors.get_mut(&frame_id) {
-                entry.descriptor.enabled = true;
-            }
-        }
+        Ok(())
+    }
+
+    async fn disable_frame(&mut self, frame_id: FrameId) -> Result<(), ClientError> {
+        self.ensure_frame_known(frame_id).await?;
+        self.shared.serial_worker.lock().await.disable_frame(frame_id.0).await?;
+        self.shared.set_enabled(frame_id, false).await;
 
         Ok(())
     }
 
-    async fn disable_frame(&mut self, frame_id: FrameId) -> Result<(), String> {
-        self.serial_worker.disable_frame(frame_id.0).await?;
+    async fn add_callback(&mut self, filter: FrameFilter, cb: Box<dyn SignalFrameCallback>) -> CallbackHandle {
+        self.dispatcher.add_callback(filter, cb)
+    }
 
-        if let Some(ref mut descriptors) = &mut *self.frame_descriptors.write().await {
-            if let Some(entry) = descriptors.get_mut(&frame_id) {
-                entry.descriptor.enabled = false;
+    async fn remove_callback(&mut self, handle: CallbackHandle) -> bool {
+        self.dispatcher.remove_callback(handle)
+    }
+
+    async fn subscribe(&mut self, filter: FrameFilter, options: SubscriptionOptions) -> Subscription {
+        self.dispatcher.subscribe(filter, options)
+    }
+
+    async fn disconnect(&mut self) -> Result<(), ClientError> {
+        let previous = self.shared.connection_state.send_replace(ConnectionState::Disconnected);
+        self.dispatcher.clear();
+
+        // Frames can only be disabled while the link is up, the link is closed even if disabling fails
+        let mut serial_worker = self.shared.serial_worker.lock().await;
+        let mut result = Ok(());
+        if previous == ConnectionState::Connected {
+            for frame_id in self.shared.enabled_frames().await {
+                if let Err(err) = serial_worker.disable_frame(frame_id.0).await {
+                    eprintln!("Failed to disable frame {}: {err}", frame_id.0);
+                    result = Err(err);
+                }
             }
         }
 
-        Ok(())
+        serial_worker.disconnect().await?;
+        *self.shared.frame_descriptors.write().await = None;
+
+        Ok(result?)
     }
 
-    async fn add_callback(&mut self, cb: Box<dyn SignalFrameCallback>) {
-        let mut cbs = self.callbacks.write().await;
-        (*cbs).push(cb);
+    fn connection_state(&self) -> ConnectionState {
+        *self.shared.connection_state.borrow()
     }
 }
 
-impl SbsUart {
-    pub fn new() -> SbsUart {
+impl<T: Transport> Drop for SbsUart<T> {
+    fn drop(&mut self) {
+        // The tasks share the serial worker, stop them so it's dropped and its thread stops
+        self.frame_reader_thread.abort();
+        self.supervisor_task.abort();
+    }
+}
+
+impl<T: Transport> Default for SbsUart<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Transport> SbsUart<T> {
+    pub fn new() -> SbsUart<T> {
         let (raw_frame_tx, mut raw_frame_rx): (Sender<RawSignalFrame>, Receiver<RawSignalFrame>) = mpsc::channel(32);
+        let (link_tx, link_rx) = mpsc::unbounded_channel();
+
+        let dispatcher = Arc::new(Dispatcher::new());
+        let stats = Arc::new(DecodeStats::default());
 
-        let frame_descriptors = Arc::new(RwLock::new(None));
-        let callbacks = Arc::new(RwLock::new(Vec::<Box<dyn SignalFrameCallback>>::new()));
+        let shared = Arc::new(Shared {
+            serial_worker: tokio::sync::Mutex::new(SerialWorker::new(raw_frame_tx, stats.clone(), link_tx.clone())),
+            frame_descriptors: RwLock::new(None),
+            signal_overrides: Mutex::new(SignalOverrides::default()),
+            discovery: Mutex::new(FrameDiscovery::default()),
+            connection_state: watch::Sender::new(ConnectionState::Disconnected),
+            clock: Mutex::new(Clock::default()),
+            last_frame: Mutex::new(Instant::now()),
+            silence_probe: Mutex::new(None),
+        });
 
         SbsUart {
-            serial_worker: SerialWorker::new(raw_frame_tx),
-            frame_descriptors: Arc::clone(&frame_descriptors),
-            callbacks: callbacks.clone(),
+            shared: shared.clone(),
+            dispatcher: dispatcher.clone(),
+            tick_frequency: None,
+            protocol: ProtocolOptions::default(),
+            connection: ConnectionConfig::default(),
+            stats: stats.clone(),
+            supervisor_task: tokio::spawn(Shared::supervise(shared.clone(), link_rx)),
             frame_reader_thread: tokio::spawn(async move {
-                let descriptors_rwl = frame_descriptors.clone();
-                let callbacks = callbacks.clone();
                 while let Some(frame) = raw_frame_rx.recv().await {
                     let frame_id = FrameId(frame.frame_id);
 
-                    let mut descriptors_opt = descriptors_rwl.write().await;
-                    if let Some(ref mut descriptors) = &mut *descriptors_opt {
-                        if let Some(frame_state) = descriptors.get_mut(&frame_id) {
-                            frame_state.latest_value.update_from_bytes(frame.timestamp, frame.data.as_slice());
+                    *shared.last_frame.lock().unwrap() = Instant::now();
+                    let (timestamp, reset) = shared.clock.lock().unwrap().update(frame.timestamp);
+                    if reset {
+                        eprintln!("Device reset detected");
+                        let _ = link_tx.send(LinkEvent::DeviceReset);
+                    }
 
-                            for cb in callbacks.read().await.iter() {
-                                (*cb)(frame_id, &frame_state.latest_value);
-                            }
+                    // Decode while holding the lock, but dispatch after releasing it so slow consumers
+                    // don't block discovery or enabling frames
+                    let value = {
+                        let mut descriptors_opt = shared.frame_descriptors.write().await;
+                        descriptors_opt.as_mut()
+                            .and_then(|descriptors| descriptors.get_mut(&frame_id))
+                            .and_then(|frame_state| {
+                                let value = &mut frame_state.latest_value;
+                                value.update_from_bytes(timestamp, frame.data.as_slice()).then(|| value.clone())
+                            })
+                    };
+
+                    match value {
+                        Some(value) => {
+                            stats.frames.fetch_add(1, Ordering::Relaxed);
+                            dispatcher.dispatch(frame_id, &value);
+                        }
+                        None => {
+                            stats.undecodable.fetch_add(1, Ordering::Relaxed);
                         }
                     }
                 }
@@ -100,35 +291,290 @@ impl SbsUart {
         }
     }
 
-    pub async fn connect(&mut self, port: &str, baud: u32) -> Result<(), Error> {
-        self.serial_worker.connect(port, baud).await
+    pub async fn connect(&mut self, config: T::Config) -> Result<(), ClientError> {
+        let mut serial_worker = self.shared.serial_worker.lock().await;
+        serial_worker.connect(config, self.protocol, self.connection.clone()).await?;
+
+        let schema_tick_frequency = match &*self.shared.discovery.lock().unwrap() {
+            FrameDiscovery::Schema(schema) | FrameDiscovery::CrossCheck(schema) => schema.tick_frequency,
+            FrameDiscovery::Introspect => None,
+        };
+        let tick_frequency = self.tick_frequency
+            .or(schema_tick_frequency)
+            .unwrap_or(DEFAULT_TICK_FREQUENCY);
+
+        *self.shared.last_frame.lock().unwrap() = Instant::now();
+        *self.shared.silence_probe.lock().unwrap() = self.connection.silence_probe;
+        let mut clock = self.shared.clock.lock().unwrap();
+        clock.restart();
+        clock.set_tick_frequency(tick_frequency);
+
+        self.shared.connection_state.send_replace(ConnectionState::Connected);
+        Ok(())
     }
 
-    async fn ensure_frame_descriptors_loaded(&mut self) -> Result<(), Error> {
-        let mut result = HashMap::<FrameId, FrameState>::new();
-        let frames = self.serial_worker.list_frames().await?;
+    /// Sets the frequency of the device timestamp counter in Hz, used at the next connect
+    ///
+    /// Without it the tick frequency of the schema is used, or [`DEFAULT_TICK_FREQUENCY`] if there is none.
+    pub fn set_tick_frequency(&mut self, tick_frequency: Option<f64>) {
+        self.tick_frequency = tick_frequency;
+    }
 
-        for frame in frames {
-            let frame_details = self.serial_worker.get_frame_info(frame.id).await?;
+    /// Sets the protocol options the device supports, used at the next connect
+    pub fn set_protocol_options(&mut self, protocol: ProtocolOptions) {
+        self.protocol = protocol;
+    }
 
-            let descriptor = SignalFrameDescriptor {
-                id: FrameId(frame.id),
-                name: frame.name.clone(),
-                enabled: frame_details.enabled,
-                signals: frame_details.signals.iter().map(|s| SignalDescriptor {
-                    name: s.name.clone(),
-                    ty: s.ty.clone(),
-                }).collect::<Vec<_>>(),
-            };
+    /// Sets the timeouts and retries of the commands, used at the next connect
+    pub fn set_connection_config(&mut self, connection: ConnectionConfig) {
+        self.connection = connection;
+    }
 
-            result.insert(FrameId(frame.id), FrameState {
-                descriptor: descriptor.clone(),
-                latest_value: SignalFrameValue::new(descriptor.clone()),
-            });
+    /// Counters of the received frames, shared so they can be shown while connected
+    pub fn decode_stats(&self) -> Arc<DecodeStats> {
+        self.stats.clone()
+    }
+
+    /// Returns a receiver of the connection state, which changes as the link is lost and recovered
+    pub fn connection_events(&self) -> watch::Receiver<ConnectionState> {
+        self.shared.connection_state.subscribe()
+    }
+
+    /// Fails with [`ClientError::UnknownFrame`] if the frames were discovered and `frame_id` isn't one of them
+    async fn ensure_frame_known(&self, frame_id: FrameId) -> Result<(), ClientError> {
+        match &*self.shared.frame_descriptors.read().await {
+            Some(descriptors) if !descriptors.contains_key(&frame_id) => Err(ClientError::UnknownFrame(frame_id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets host-side signal metadata, applied on top of what the device announces at the next frame discovery
+    pub fn set_signal_overrides(&mut self, overrides: SignalOverrides) {
+        *self.shared.signal_overrides.lock().unwrap() = overrides;
+    }
+
+    /// Sets how the frames are discovered, used at the next frame discovery
+    pub fn set_frame_discovery(&mut self, discovery: FrameDiscovery) {
+        *self.shared.discovery.lock().unwrap() = discovery;
+    }
+}
+
+impl<T: Transport> Shared<T> {
+    async fn load_frame_descriptors(&self) -> Result<(), Error> {
+        let discovery = self.discovery.lock().unwrap().clone();
+        let mut descriptors = match discovery {
+            FrameDiscovery::Introspect => self.introspect_frames().await?,
+            FrameDiscovery::Schema(schema) => {
+                // The device can't tell which frames are enabled, so keep what was enabled through this client
+                let current = self.frame_descriptors.read().await;
+                schema.frames.into_iter()
+                    .map(|mut frame| {
+                        frame.enabled = current.as_ref()
+                            .and_then(|c| c.get(&frame.id))
+                            .is_some_and(|fs| fs.descriptor.enabled);
+                        frame
+                    })
+                    .collect()
+            }
+            FrameDiscovery::CrossCheck(schema) => {
+                let frames = self.introspect_frames().await?;
+                for mismatch in schema.compare(&frames) {
+                    eprintln!("Device does not match schema: {mismatch}");
+                }
+                frames
+            }
+        };
+
+        for unmatched in self.signal_overrides.lock().unwrap().apply(&mut descriptors) {
+            eprintln!("Signal override for {:?}/{} does not match any signal", unmatched.frame, unmatched.name);
         }
 
+        let result = descriptors.into_iter()
+            .map(|descriptor| (descriptor.id, FrameState {
+                descriptor: descriptor.clone(),
+                latest_value: SignalFrameValue::new(descriptor),
+            }))
+            .collect::<HashMap<_, _>>();
+
         let mut descriptors = self.frame_descriptors.write().await;
         *descriptors = Some(result);
         Ok(())
     }
+
+    /// Queries the frames and their signals from the device
+    async fn introspect_frames(&self) -> Result<Vec<SignalFrameDescriptor>, Error> {
+        let mut serial_worker = self.serial_worker.lock().await;
+        let frames = serial_worker.list_frames().await?;
+        let frame_ids = frames.iter().map(|frame| frame.id).collect::<Vec<_>>();
+        let frame_details = serial_worker.get_frame_infos(&frame_ids).await?;
+
+        Ok(frames.iter()
+            .zip(&frame_details)
+            .map(|(frame, details)| frame_descriptor(frame, details))
+            .collect())
+    }
+
+    fn is_disconnected(&self) -> bool {
+        *self.connection_state.borrow() == ConnectionState::Disconnected
+    }
+
+    async fn set_enabled(&self, frame_id: FrameId, enabled: bool) {
+        if let Some(ref mut descriptors) = &mut *self.frame_descriptors.write().await {
+            if let Some(entry) = descriptors.get_mut(&frame_id) {
+                entry.descriptor.enabled = enabled;
+            }
+        }
+    }
+
+    async fn enabled_frames(&self) -> Vec<FrameId> {
+        match &*self.frame_descriptors.read().await {
+            Some(descriptors) => descriptors.values()
+                .filter(|fs| fs.descriptor.enabled)
+                .map(|fs| fs.descriptor.id)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Asks the device about the enabled frames after no data frame arrived for a while, returns
+    /// whether it reports any of them disabled or unknown, as it does after a reset
+    ///
+    /// Frames taken from a schema may not be known to the device, so they aren't probed.
+    async fn was_reset_silently(&self) -> bool {
+        let Some(interval) = *self.silence_probe.lock().unwrap() else {
+            return false;
+        };
+        if *self.connection_state.borrow() != ConnectionState::Connected
+            || self.last_frame.lock().unwrap().elapsed() < interval
+            || matches!(*self.discovery.lock().unwrap(), FrameDiscovery::Schema(_)) {
+            return false;
+        }
+
+        let enabled = self.enabled_frames().await.iter().map(|frame_id| frame_id.0).collect::<Vec<_>>();
+        if enabled.is_empty() {
+            return false;
+        }
+
+        let reset = match self.serial_worker.lock().await.get_frame_infos(&enabled).await {
+            Ok(details) => details.iter().any(|details| !details.enabled),
+            Err(Error::UnknownFrame(_)) => true,
+            Err(_) => false,
+        };
+
+        *self.last_frame.lock().unwrap() = Instant::now();
+        if reset {
+            self.clock.lock().unwrap().resync();
+        }
+        reset
+    }
+
+    /// Discovers the frames again, and enables the frames that were enabled before
+    async fn restore(&self, enabled: &[FrameId]) -> Result<(), Error> {
+        if self.frame_descriptors.read().await.is_some() {
+            self.load_frame_descriptors().await?;
+        }
+
+        for frame_id in enabled {
+            if self.frame_descriptors.read().await.as_ref().is_some_and(|d| !d.contains_key(frame_id)) {
+                eprintln!("Frame {} no longer exists, not enabling it again", frame_id.0);
+                continue;
+            }
+
+            self.serial_worker.lock().await.enable_frame(frame_id.0).await?;
+            self.set_enabled(*frame_id, true).await;
+        }
+
+        Ok(())
+    }
+
+    /// Follows the link events of the serial worker and frame reader, and restores the session after
+    /// the link was reopened or the device was reset
+    async fn supervise(shared: Arc<Shared<T>>, mut events: mpsc::UnboundedReceiver<LinkEvent>) {
+        let mut pending = None;
+
+        loop {
+            let event = match pending.take() {
+                Some(event) => event,
+                None => tokio::select! {
+                    event = events.recv() => match event {
+                        Some(event) => event,
+                        None => break,
+                    },
+                    _ = tokio::time::sleep(shared.silence_probe.lock().unwrap().unwrap_or(SILENCE_PROBE_IDLE)) => {
+                        if !shared.was_reset_silently().await {
+                            continue;
+                        }
+                        eprintln!("Device reports enabled frames disabled, device reset detected");
+                        LinkEvent::DeviceReset
+                    }
+                }
+            };
+
+            match event {
+                LinkEvent::Lost => {
+                    if !shared.is_disconnected() {
+                        shared.connection_state.send_replace(ConnectionState::Reconnecting);
+                    }
+                }
+                LinkEvent::Restored | LinkEvent::DeviceReset => {
+                    if shared.is_disconnected() {
+                        continue;
+                    }
+                    if event == LinkEvent::Restored {
+                        // The device may have been reset while the link was down, its timestamps starting
+                        // over is not another reset
+                        shared.clock.lock().unwrap().resync();
+                    }
+                    shared.connection_state.send_replace(ConnectionState::Recovering);
+
+                    // Take the enabled frames before the first attempt, discovery marks them disabled
+                    let enabled = shared.enabled_frames().await;
+                    let mut backoff = RECOVERY_BACKOFF_MIN;
+                    while let Err(err) = shared.restore(&enabled).await {
+                        if shared.is_disconnected() {
+                            break;
+                        }
+
+                        eprintln!("Failed to restore session: {err}, retrying in {} ms", backoff.as_millis());
+
+                        tokio::select! {
+                            _ = tokio::time::sleep(backoff) => backoff = (backoff * 2).min(RECOVERY_BACKOFF_MAX),
+                            event = events.recv() => {
+                                pending = event;
+                                break;
+                            }
+                        }
+                    }
+
+                    if pending.is_none() {
+                        shared.connection_state.send_if_modified(|state| {
+                            let recovering = *state == ConnectionState::Recovering;
+                            if recovering {
+                                *state = ConnectionState::Connected;
+                            }
+                            recovering
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds the descriptor of a frame from the responses to the `l` and `i` commands
+pub(crate) fn frame_descriptor(frame: &FrameInfo, details: &FrameDetails) -> SignalFrameDescriptor {
+    SignalFrameDescriptor {
+        id: FrameId(frame.id),
+        name: frame.name.clone(),
+        enabled: details.enabled,
+        signals: details.signals.iter().map(|s| SignalDescriptor {
+            name: s.name.clone(),
+            ty: s.ty.clone(),
+            unit: s.unit.clone(),
+            scale: s.scale,
+            offset: s.offset,
+            min: s.min,
+            max: s.max,
+        }).collect::<Vec<_>>(),
+    }
 }
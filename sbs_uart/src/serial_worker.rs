@@ -1,29 +1,40 @@
+use std::collections::VecDeque;
 use std::io::ErrorKind;
+use std::sync::Arc;
 use std::thread;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::mpsc::error::{SendError, TryRecvError};
-use tokio;
-use std::time::Duration;
+use tokio::sync::mpsc::error::{SendError, TryRecvError, TrySendError};
+use std::time::{Duration, Instant};
 use pollster::FutureExt;
-use serialport::{ClearBuffer, SerialPort};
 use tokio::time::error::Elapsed;
 use tokio::time::timeout;
+use crate::connection::{CommandPolicy, ConnectionConfig, MIN_READ_TIMEOUT};
 use crate::error::Error;
 use crate::frame_decoder::{DecodedFrame, Decoder, DecodeResult, FrameDetails, FrameInfo, RawSignalFrame};
+use crate::protocol::{Command, NackReason, ProtocolOptions};
+use crate::sbs_uart::DecodeStats;
+use crate::transport::{SerialTransport, Transport};
 
 #[derive(Clone, Debug)]
-#[allow(dead_code)]
-enum CommandReq {
-    Connect(String, u32),
+enum CommandReq<C> {
+    Connect(C, ProtocolOptions, Box<ConnectionConfig>),
     Disconnect,
     Stop,
-    ListFrames,
-    GetFrameInfo(u32),
-    EnableFrame(u32),
-    DisableFrame(u32),
+    Command(Command),
 }
 
+/// Most commands sent to the device before their responses arrive, when it echoes sequence numbers
+const PIPELINE_DEPTH: usize = 8;
+
+/// Time before the first attempt to reopen a lost link, doubled after every failed attempt
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_millis(250);
+
+/// Longest time between attempts to reopen a lost link
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Time the worker sleeps between checks for commands while waiting to reconnect
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 impl<T> From<SendError<T>> for Error {
     fn from(value: SendError<T>) -> Self {
@@ -32,8 +43,18 @@ impl<T> From<SendError<T>> for Error {
 }
 
 
+/// Changes of the link, reported by the worker and the frame reader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LinkEvent {
+    /// Reading or writing failed, and the worker is reopening the link
+    Lost,
+    /// The link was reopened after it was lost
+    Restored,
+    /// The device timestamps jumped back, so the device was reset while the link stayed up
+    DeviceReset,
+}
+
 #[derive(Clone, Debug)]
-#[allow(dead_code)]
 enum CommandRes {
     Connect(Result<(), Error>),
     Disconnect(Result<(), Error>),
@@ -44,60 +65,112 @@ enum CommandRes {
     Error(Error),
 }
 
-pub struct SerialWorker {
-    txchan_tx: Sender<CommandReq>,
-    rxchan_rx: Receiver<CommandRes>,
+impl CommandRes {
+    /// Result of `command`, answered with `frame`
+    fn from_response(command: Command, frame: DecodedFrame) -> CommandRes {
+        match (command, frame) {
+            (Command::ListFrames, DecodedFrame::ListFrames(frames)) => CommandRes::ListFrames(Ok(frames)),
+            (Command::GetFrameInfo(_), DecodedFrame::GetFrameInfo(details)) => CommandRes::GetFrameInfo(Ok(details)),
+            (Command::EnableFrame(_), DecodedFrame::EnableFrame) => CommandRes::EnableFrame(Ok(())),
+            (Command::DisableFrame(_), DecodedFrame::DisableFrame) => CommandRes::DisableFrame(Ok(())),
+            (Command::GetFrameInfo(frame_id) | Command::EnableFrame(frame_id) | Command::DisableFrame(frame_id),
+             DecodedFrame::Nack(NackReason::UnknownFrame)) => CommandRes::Error(Error::UnknownFrame(frame_id)),
+            // The command got damaged on the way, like a response with a broken CRC
+            (_, DecodedFrame::Nack(NackReason::Malformed)) => CommandRes::Error(Error::CrcError),
+            (command, DecodedFrame::Nack(reason)) =>
+                CommandRes::Error(Error::Rejected(format!("Device rejected {command:?}: {reason}"))),
+            (command, frame) =>
+                CommandRes::Error(Error::WrongFrame(format!("Wrong response frame to {command:?}, got {frame:?}"))),
+        }
+    }
+}
+
+/// Speaks the SBS protocol with a device over a [`Transport`], on a thread of its own
+///
+/// Requests and responses are tagged, so a response that arrives after its request timed out is
+/// not taken as the response to a later request.
+pub struct SerialWorker<T: Transport = SerialTransport> {
+    txchan_tx: Sender<(u32, CommandReq<T::Config>)>,
+    rxchan_rx: Receiver<(u32, CommandRes)>,
+    next_tag: u32,
+    protocol: ProtocolOptions,
+    connection: ConnectionConfig,
+    #[allow(dead_code)]
     reader_thread: thread::JoinHandle<()>,
 }
 
-impl Drop for SerialWorker {
+impl<T: Transport> Drop for SerialWorker<T> {
     fn drop(&mut self) {
-        let _ = self.txchan_tx.send(CommandReq::Stop).block_on();
+        let _ = self.txchan_tx.send((self.next_tag, CommandReq::Stop)).block_on();
     }
 }
 
-impl SerialWorker {
-    pub fn new(raw_frame_tx: Sender<RawSignalFrame>) -> SerialWorker {
-        let (txchan_tx, txchan_rx): (Sender<CommandReq>, Receiver<CommandReq>) = mpsc::channel(16);
-        let (rxchan_tx, rxchan_rx): (Sender<CommandRes>, Receiver<CommandRes>) = mpsc::channel(16);
+impl<T: Transport> SerialWorker<T> {
+    pub(crate) fn new(raw_frame_tx: Sender<RawSignalFrame>,
+                      stats: Arc<DecodeStats>,
+                      link_tx: mpsc::UnboundedSender<LinkEvent>) -> SerialWorker<T> {
+        let (txchan_tx, txchan_rx) = mpsc::channel::<(u32, CommandReq<T::Config>)>(16);
+        let (rxchan_tx, rxchan_rx) = mpsc::channel::<(u32, CommandRes)>(16);
 
 
         SerialWorker {
             txchan_tx,
             rxchan_rx,
+            next_tag: 0,
+            protocol: ProtocolOptions::default(),
+            connection: ConnectionConfig::default(),
             reader_thread: thread::spawn(move || {
-                let mut worker = SerialWorkerThread::new(txchan_rx, rxchan_tx, raw_frame_tx);
+                let mut worker = SerialWorkerThread::<T>::new(txchan_rx, rxchan_tx, raw_frame_tx, stats, link_tx);
                 worker.run();
             }),
         }
     }
 
-    pub async fn connect(&mut self, port: &str, baud: u32) -> Result<(), Error> {
-        match self.request(CommandReq::Connect(port.to_string(), baud), Duration::from_millis(2000)).await? {
+    pub async fn connect(&mut self, config: T::Config, protocol: ProtocolOptions, connection: ConnectionConfig) -> Result<(), Error> {
+        self.protocol = protocol;
+        self.connection = connection.clone();
+        match self.request(CommandReq::Connect(config, protocol, Box::new(connection)), self.connection.connect_timeout).await? {
             CommandRes::Connect(r) => r,
             CommandRes::Error(e) => Err(e),
             res => Err(Error::Internal(format!("Invalid response from worker {res:?}")))
         }
     }
 
-    pub async fn list_frames(&mut self) -> Result<Vec<FrameInfo>, Error> {
-        match self.request(CommandReq::ListFrames, Duration::from_millis(2000)).await? {
-            CommandRes::ListFrames(r) => r,
+    /// Closes the link, and stops reconnecting to it
+    pub async fn disconnect(&mut self) -> Result<(), Error> {
+        match self.request(CommandReq::Disconnect, self.connection.connect_timeout).await? {
+            CommandRes::Disconnect(r) => r,
             CommandRes::Error(e) => Err(e),
             res => Err(Error::Internal(format!("Invalid response from worker {res:?}")))
         }
     }
 
-    pub async fn get_frame_info(&mut self, frame_id: u32) -> Result<FrameDetails, Error> {
-        match self.request(CommandReq::GetFrameInfo(frame_id), Duration::from_millis(2000)).await? {
-            CommandRes::GetFrameInfo(r) => r,
+    pub async fn list_frames(&mut self) -> Result<Vec<FrameInfo>, Error> {
+        match self.command(Command::ListFrames).await? {
+            CommandRes::ListFrames(r) => r,
             CommandRes::Error(e) => Err(e),
             res => Err(Error::Internal(format!("Invalid response from worker {res:?}")))
         }
     }
 
+    /// Gets the details of every frame in `frame_ids`, with several commands in flight if the device echoes sequence numbers
+    pub async fn get_frame_infos(&mut self, frame_ids: &[u32]) -> Result<Vec<FrameDetails>, Error> {
+        let commands = frame_ids.iter()
+            .map(|frame_id| Command::GetFrameInfo(*frame_id))
+            .collect();
+
+        self.command_all(commands, self.connection.get_frame_info).await?
+            .into_iter()
+            .map(|res| match res {
+                CommandRes::GetFrameInfo(r) => r,
+                CommandRes::Error(e) => Err(e),
+                res => Err(Error::Internal(format!("Invalid response from worker {res:?}")))
+            })
+            .collect()
+    }
+
     pub async fn enable_frame(&mut self, frame_id: u32) -> Result<(), Error> {
-        match self.request(CommandReq::EnableFrame(frame_id), Duration::from_millis(2000)).await? {
+        match self.command(Command::EnableFrame(frame_id)).await? {
             CommandRes::EnableFrame(r) => r,
             CommandRes::Error(e) => Err(e),
             res => Err(Error::Internal(format!("Invalid response from worker {res:?}")))
@@ -105,18 +178,91 @@ impl SerialWorker {
     }
 
     pub async fn disable_frame(&mut self, frame_id: u32) -> Result<(), Error> {
-        match self.request(CommandReq::DisableFrame(frame_id), Duration::from_millis(2000)).await? {
+        match self.command(Command::DisableFrame(frame_id)).await? {
             CommandRes::DisableFrame(r) => r,
             CommandRes::Error(e) => Err(e),
             res => Err(Error::Internal(format!("Invalid response from worker {res:?}")))
         }
     }
 
-    async fn request(&mut self, req: CommandReq, to: Duration) -> Result<CommandRes, Error> {
-        self.txchan_tx.send(req).await?;
+    async fn command(&mut self, command: Command) -> Result<CommandRes, Error> {
+        let policy = *self.connection.policy(&command);
+        Ok(self.command_all(vec![command], policy).await?.remove(0))
+    }
+
+    /// Sends the commands, and sends those that failed in a way that may pass on another attempt
+    /// again, as often as `policy` allows
+    ///
+    /// Commands that timed out are only sent again with sequence numbers. Without them, the late
+    /// response to the first attempt can't be told apart from the response to the retry.
+    async fn command_all(&mut self, commands: Vec<Command>, policy: CommandPolicy) -> Result<Vec<CommandRes>, Error> {
+        let sequence_numbers = self.protocol.sequence_numbers;
+        let mut responses = self.request_all(commands.iter().map(|c| CommandReq::Command(*c)).collect(), policy.timeout).await?;
+
+        for attempt in 0..policy.retries {
+            let failed = responses.iter()
+                .enumerate()
+                .filter(|(_, res)| matches!(res, CommandRes::Error(e) if e.is_transient() && (sequence_numbers || !matches!(e, Error::Timeout))))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            if failed.is_empty() {
+                break;
+            }
+
+            tokio::time::sleep(policy.backoff(attempt)).await;
+            eprintln!("Retrying {} of {} commands", failed.len(), commands.len());
+
+            let reqs = failed.iter().map(|index| CommandReq::Command(commands[*index])).collect();
+            for (index, res) in failed.into_iter().zip(self.request_all(reqs, policy.timeout).await?) {
+                responses[index] = res;
+            }
+        }
 
-        timeout(to, self.rxchan_rx.recv()).await?
-            .ok_or(Error::Internal("Failed to receive".to_string()))
+        Ok(responses)
+    }
+
+    async fn request(&mut self, req: CommandReq<T::Config>, to: Duration) -> Result<CommandRes, Error> {
+        Ok(self.request_all(vec![req], to).await?.remove(0))
+    }
+
+    /// Sends the requests with as many of them waiting for a response as the worker may have in
+    /// flight, returns the responses in the order of the requests
+    ///
+    /// When no response arrives within `to`, the requests sent so far get [`Error::Timeout`].
+    async fn request_all(&mut self, reqs: Vec<CommandReq<T::Config>>, to: Duration) -> Result<Vec<CommandRes>, Error> {
+        // The worker fails commands that wait longer than their timeout to be written, so only as
+        // many are handed over as it writes right away
+        let depth = if self.protocol.sequence_numbers { PIPELINE_DEPTH } else { 1 };
+        let first_tag = self.next_tag;
+        let mut responses = vec![None; reqs.len()];
+        let mut reqs = reqs.into_iter();
+        let (mut sent, mut received) = (0, 0);
+
+        while received < responses.len() {
+            while sent < responses.len() && sent - received < depth {
+                self.txchan_tx.send((self.next_tag, reqs.next().unwrap())).await?;
+                self.next_tag = self.next_tag.wrapping_add(1);
+                sent += 1;
+            }
+
+            let Ok(response) = timeout(to, self.rxchan_rx.recv()).await else {
+                for res in responses[..sent].iter_mut().filter(|res| res.is_none()) {
+                    *res = Some(CommandRes::Error(Error::Timeout));
+                    received += 1;
+                }
+                continue;
+            };
+            let (tag, res) = response.ok_or(Error::Internal("Failed to receive".to_string()))?;
+
+            // Responses to earlier requests that timed out are dropped
+            let index = tag.wrapping_sub(first_tag) as usize;
+            if index < sent && responses[index].is_none() {
+                responses[index] = Some(res);
+                received += 1;
+            }
+        }
+
+        Ok(responses.into_iter().map(Option::unwrap).collect())
     }
 }
 
@@ -124,34 +270,73 @@ impl SerialWorker {
 enum WorkerState {
     Disconnected,
     Connected,
-    ListFrames,
-    GetFrameInfo,
-    EnableFrame,
-    DisableFrame,
+    Reconnecting,
 }
 
-struct SerialWorkerThread {
-    txchan_rx: Receiver<CommandReq>,
-    rxchan_tx: Sender<CommandRes>,
+/// Command waiting until it can be written to the device
+struct Queued {
+    tag: u32,
+    command: Command,
+    /// Time the requester gives up on the command, after which it must not be written anymore
+    deadline: Instant,
+}
+
+/// Command written to the device, waiting for its response
+struct InFlight {
+    tag: u32,
+    seq: Option<u8>,
+    command: Command,
+    deadline: Instant,
+    /// Past its first deadline, still waiting so the late response isn't taken for that of the next command
+    late: bool,
+}
+
+struct SerialWorkerThread<T: Transport> {
+    txchan_rx: Receiver<(u32, CommandReq<T::Config>)>,
+    rxchan_tx: Sender<(u32, CommandRes)>,
     raw_frame_tx: Sender<RawSignalFrame>,
+    link_tx: mpsc::UnboundedSender<LinkEvent>,
     state: WorkerState,
     quit: bool,
-    serial: Option<Box<dyn SerialPort>>,
+    serial: Option<T>,
+    /// Config of the current connection, to reconnect with after the link was lost
+    config: Option<T::Config>,
+    protocol: ProtocolOptions,
+    connection: ConnectionConfig,
+    reconnect_at: Instant,
+    reconnect_backoff: Duration,
     decoder: Decoder,
+    /// Commands waiting until they can be written to the device
+    queued: VecDeque<Queued>,
+    in_flight: VecDeque<InFlight>,
+    next_seq: u8,
+    stats: Arc<DecodeStats>,
 }
 
-impl SerialWorkerThread {
-    fn new(txchan_rx: Receiver<CommandReq>,
-           rxchan_tx: Sender<CommandRes>,
-           raw_frame_tx: Sender<RawSignalFrame>) -> SerialWorkerThread {
+impl<T: Transport> SerialWorkerThread<T> {
+    fn new(txchan_rx: Receiver<(u32, CommandReq<T::Config>)>,
+           rxchan_tx: Sender<(u32, CommandRes)>,
+           raw_frame_tx: Sender<RawSignalFrame>,
+           stats: Arc<DecodeStats>,
+           link_tx: mpsc::UnboundedSender<LinkEvent>) -> SerialWorkerThread<T> {
         SerialWorkerThread {
             txchan_rx,
             rxchan_tx,
             raw_frame_tx,
+            link_tx,
             state: WorkerState::Disconnected,
             quit: false,
             serial: None,
+            config: None,
+            protocol: ProtocolOptions::default(),
+            connection: ConnectionConfig::default(),
+            reconnect_at: Instant::now(),
+            reconnect_backoff: RECONNECT_BACKOFF_MIN,
             decoder: Decoder::new(),
+            queued: VecDeque::new(),
+            in_flight: VecDeque::new(),
+            next_seq: 0,
+            stats,
         }
     }
 
@@ -162,10 +347,7 @@ impl SerialWorkerThread {
             let new_state = match current_state {
                 WorkerState::Disconnected => self.handle_disconnected_state(),
                 WorkerState::Connected => self.handle_connected_state(),
-                WorkerState::ListFrames => self.handle_list_frames_state(),
-                WorkerState::GetFrameInfo => self.handle_get_frame_info_state(),
-                WorkerState::EnableFrame => self.handle_enable_frame_state(),
-                WorkerState::DisableFrame => self.handle_disable_frame_state(),
+                WorkerState::Reconnecting => self.handle_reconnecting_state(),
             };
 
             self.state = new_state.unwrap_or(current_state);
@@ -178,36 +360,43 @@ impl SerialWorkerThread {
 
     fn handle_disconnected_state(&mut self) -> Option<WorkerState> {
         match self.txchan_rx.blocking_recv() {
-            Some(CommandReq::Connect(port_name, baud)) => {
-                let port_builder = serialport::new(port_name, baud).timeout(Duration::from_millis(100));
-
-                match port_builder.open().and_then(|port| {
-                    port.clear(ClearBuffer::All)?;
-                    Ok(port)
-                }) {
-                    Ok(port) => {
-                        self.serial = Some(port);
-                        self.decoder = Decoder::new();
-
-                        self.send_response(CommandRes::Connect(Ok(())));
-                        Some(WorkerState::Connected)
-                    }
-                    Err(err) => {
-                        self.send_response(CommandRes::Connect(Err(Error::SerialError(format!("Failed to open serial port: {err}")))));
-                        None
-                    }
-                }
+            Some((tag, CommandReq::Connect(config, protocol, connection))) => self.connect(tag, config, protocol, *connection),
+            Some((tag, CommandReq::Disconnect)) => {
+                self.send_response(tag, CommandRes::Disconnect(Ok(())));
+                None
             }
-            Some(CommandReq::Stop) => {
+            Some((_, CommandReq::Stop)) => {
                 self.quit = true;
                 None
             }
-            Some(cmd) => {
-                self.send_response(CommandRes::Error(Error::InvalidCommand(format!("Invalid command {cmd:?}"))));
+            Some((tag, cmd)) => {
+                self.send_response(tag, CommandRes::Error(Error::InvalidCommand(format!("Invalid command {cmd:?}"))));
                 None
             }
             None => {
-                println!("Failed to receive command");
+                eprintln!("Failed to receive command");
+                None
+            }
+        }
+    }
+
+    fn connect(&mut self, tag: u32, config: T::Config, protocol: ProtocolOptions, connection: ConnectionConfig) -> Option<WorkerState> {
+        match T::open(&config).and_then(|mut transport| {
+            transport.clear()?;
+            Ok(transport)
+        }) {
+            Ok(transport) => {
+                self.serial = Some(transport);
+                self.config = Some(config);
+                self.protocol = protocol;
+                self.connection = connection;
+                self.decoder = Decoder::new();
+
+                self.send_response(tag, CommandRes::Connect(Ok(())));
+                Some(WorkerState::Connected)
+            }
+            Err(err) => {
+                self.send_response(tag, CommandRes::Connect(Err(Error::SerialError(format!("Failed to open {config:?}: {err}")))));
                 None
             }
         }
@@ -216,93 +405,58 @@ impl SerialWorkerThread {
     fn handle_connected_state(&mut self) -> Option<WorkerState> {
         let mut serial_buf: Vec<u8> = vec![0; 2048];
 
-        let cmd_result = match self.txchan_rx.try_recv() {
-            Ok(CommandReq::Disconnect) => {
-                self.serial = None;
-                Some(WorkerState::Disconnected)
-            }
-            Ok(CommandReq::ListFrames) => {
-                let ser = self.serial.as_mut().unwrap();
-                match ser.write(b"lL") {
-                    Ok(_) => Some(WorkerState::ListFrames),
-                    Err(e) => {
-                        self.send_response(CommandRes::ListFrames(Err(Error::SerialError(format!("Failed to send data: {e:?}")))));
-                        None
-                    }
+        loop {
+            match self.txchan_rx.try_recv() {
+                Ok((tag, CommandReq::Command(command))) => {
+                    let deadline = Instant::now() + self.connection.policy(&command).timeout;
+                    self.queued.push_back(Queued { tag, command, deadline });
                 }
-            }
-            Ok(CommandReq::GetFrameInfo(frame_id)) => {
-                let fid_bytes = frame_id.to_le_bytes();
-                let mut tx_buf: [u8; 6] = [b'i', 0, 0, 0, 0, b'I'];
-                tx_buf.as_mut_slice()[1..5].copy_from_slice(&fid_bytes);
-
-                let ser = self.serial.as_mut().unwrap();
-                match ser.write(tx_buf.as_slice()) {
-                    Ok(_) => Some(WorkerState::GetFrameInfo),
-                    Err(e) => {
-                        self.send_response(CommandRes::GetFrameInfo(Err(Error::SerialError(format!("Failed to send data: {e:?}")))));
-                        None
-                    }
+                Ok((tag, CommandReq::Disconnect)) => {
+                    self.serial = None;
+                    self.config = None;
+                    self.fail_commands(Error::SerialError("Disconnected".to_string()));
+                    self.send_response(tag, CommandRes::Disconnect(Ok(())));
+                    return Some(WorkerState::Disconnected);
                 }
-            }
-            Ok(CommandReq::EnableFrame(frame_id)) => {
-                let fid_bytes = frame_id.to_le_bytes();
-                let mut tx_buf: [u8; 6] = [b'e', 0, 0, 0, 0, b'E'];
-                tx_buf.as_mut_slice()[1..5].copy_from_slice(&fid_bytes);
-
-                let ser = self.serial.as_mut().unwrap();
-                match ser.write(tx_buf.as_slice()) {
-                    Ok(_) => Some(WorkerState::EnableFrame),
-                    Err(e) => {
-                        self.send_response(CommandRes::EnableFrame(Err(Error::SerialError(format!("Failed to send data: {e:?}")))));
-                        None
-                    }
+                Ok((_, CommandReq::Stop)) => {
+                    self.quit = true;
+                    return None;
                 }
-            }
-            Ok(CommandReq::DisableFrame(frame_id)) => {
-                let fid_bytes = frame_id.to_le_bytes();
-                let mut tx_buf: [u8; 6] = [b'd', 0, 0, 0, 0, b'D'];
-                tx_buf.as_mut_slice()[1..5].copy_from_slice(&fid_bytes);
-
-                let ser = self.serial.as_mut().unwrap();
-                match ser.write(tx_buf.as_slice()) {
-                    Ok(_) => Some(WorkerState::DisableFrame),
-                    Err(e) => {
-                        self.send_response(CommandRes::DisableFrame(Err(Error::SerialError(format!("Failed to send data: {e:?}")))));
-                        None
-                    }
+                Ok((tag, cmd)) => {
+                    self.send_response(tag, CommandRes::Error(Error::InvalidCommand(format!("Invalid command {cmd:?}"))));
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(_) => {
+                    self.quit = true;
+                    return None;
                 }
             }
-            Ok(CommandReq::Stop) => {
-                self.quit = true;
-                None
-            }
-            Ok(cmd) => {
-                self.send_response(CommandRes::Error(Error::InvalidCommand(format!("Invalid command {cmd:?}"))));
-                None
-            }
-            Err(TryRecvError::Empty) => None,
-            Err(err) => {
-                self.send_response(CommandRes::ListFrames(Err(Error::SerialError(format!("Failed to receive data: {err:?}")))));
-                None
-            }
-        };
+        }
 
-        if cmd_result.is_some() {
-            return cmd_result;
+        if let Some(state) = self.send_queued_commands() {
+            return Some(state);
         }
+        self.expire_commands();
 
         let ser = self.serial.as_mut().unwrap();
-        match ser.read(serial_buf.as_mut_slice()) {
+        match ser.read(serial_buf.as_mut_slice(), self.connection.read_timeout.max(MIN_READ_TIMEOUT)) {
             Ok(nb) => {
                 self.decoder.add_data(&serial_buf.as_slice()[..nb]);
                 loop {
                     match self.decoder.decode() {
                         DecodeResult::None => break,
-                        DecodeResult::CmdFrame(frame) =>
-                            self.send_response(CommandRes::GetFrameInfo(Err(Error::WrongFrame(format!("Unexpected frame {frame:?}"))))),
-                        DecodeResult::Err(err) =>
-                            self.send_response(CommandRes::GetFrameInfo(Err(Error::DecodeError(err)))),
+                        DecodeResult::CmdFrame(frame, seq) => self.complete_command(frame, seq),
+                        DecodeResult::Err(err) => {
+                            self.stats.count_error(&err);
+                            eprintln!("Failed to decode frame: {}", Error::from(err.clone()));
+
+                            // Without sequence numbers, the broken frame is taken to be the response to the command in flight
+                            if !self.protocol.sequence_numbers {
+                                if let Some(command) = self.in_flight.pop_front() {
+                                    self.finish_command(command, CommandRes::Error(err.into()));
+                                }
+                            }
+                        }
                         DecodeResult::SignalFrame(rsf) =>
                             self.send_signal_frame(rsf),
                     };
@@ -312,89 +466,187 @@ impl SerialWorkerThread {
                 None
             }
             Err(err) if err.kind() == ErrorKind::TimedOut => None,
-            Err(err) => {
-                self.send_response(CommandRes::GetFrameInfo(Err(Error::SerialError(format!("Failed to read from serial: {err:?}")))));
+            Err(err) => self.link_lost(&err),
+        }
+    }
+
+    /// Reopens the link after it was lost with increasing delays, until it succeeds or the host disconnects
+    fn handle_reconnecting_state(&mut self) -> Option<WorkerState> {
+        match self.txchan_rx.try_recv() {
+            Ok((tag, CommandReq::Connect(config, protocol, connection))) => return self.connect(tag, config, protocol, *connection),
+            Ok((tag, CommandReq::Disconnect)) => {
+                self.config = None;
+                self.send_response(tag, CommandRes::Disconnect(Ok(())));
+                return Some(WorkerState::Disconnected);
+            }
+            Ok((_, CommandReq::Stop)) => {
+                self.quit = true;
+                return None;
+            }
+            Ok((tag, _)) => {
+                self.send_response(tag, CommandRes::Error(Error::SerialError("Connection lost, reconnecting".to_string())));
+                return None;
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(_) => {
+                self.quit = true;
+                return None;
+            }
+        }
+
+        if Instant::now() < self.reconnect_at {
+            thread::sleep(RECONNECT_POLL_INTERVAL);
+            return None;
+        }
+
+        let config = self.config.clone()?;
+        match T::open(&config) {
+            Ok(transport) => {
+                eprintln!("Reconnected to {config:?}");
+                self.serial = Some(transport);
+                self.decoder = Decoder::new();
+                self.reconnect_backoff = RECONNECT_BACKOFF_MIN;
+                let _ = self.link_tx.send(LinkEvent::Restored);
                 Some(WorkerState::Connected)
             }
+            Err(_) => {
+                self.reconnect_backoff = (self.reconnect_backoff * 2).min(RECONNECT_BACKOFF_MAX);
+                self.reconnect_at = Instant::now() + self.reconnect_backoff;
+                None
+            }
         }
     }
 
-    fn handle_list_frames_state(&mut self) -> Option<WorkerState> {
-        self.read_response(|frame| match frame {
-            DecodedFrame::ListFrames(frames) =>
-                CommandRes::ListFrames(Ok(frames)),
-            frame =>
-                CommandRes::ListFrames(Err(Error::WrongFrame(format!("Wrong response frame, expected ListFrames, got {frame:?}")))),
-        })
-    }
+    /// Writes queued commands to the device, as many as may wait for a response at the same time
+    ///
+    /// Without sequence numbers responses can't be told apart, so only one command is in flight.
+    /// Commands the requester gave up on while they were queued are failed without writing them, so
+    /// a command that failed with a timeout doesn't take effect later on.
+    fn send_queued_commands(&mut self) -> Option<WorkerState> {
+        let max_in_flight = if self.protocol.sequence_numbers { PIPELINE_DEPTH } else { 1 };
+
+        let now = Instant::now();
+        let (expired, queued) = self.queued.drain(..).partition::<Vec<_>, _>(|queued| queued.deadline <= now);
+        self.queued = queued.into();
+        for queued in expired {
+            eprintln!("Dropping {:?}, it timed out before it could be sent", queued.command);
+            self.send_response(queued.tag, CommandRes::Error(Error::Timeout));
+        }
 
-    fn handle_get_frame_info_state(&mut self) -> Option<WorkerState> {
-        self.read_response(|frame| match frame {
-            DecodedFrame::GetFrameInfo(details) =>
-                CommandRes::GetFrameInfo(Ok(details)),
-            frame =>
-                CommandRes::ListFrames(Err(Error::WrongFrame(format!("Wrong response frame, expected GetFrameInfo, got {frame:?}")))),
-        })
-    }
+        while self.in_flight.len() < max_in_flight {
+            let Some(Queued { tag, command, deadline }) = self.queued.pop_front() else {
+                break;
+            };
+
+            let seq = self.protocol.sequence_numbers.then(|| {
+                let seq = self.next_seq;
+                self.next_seq = seq.wrapping_add(1);
+                seq
+            });
+
+            let ser = self.serial.as_mut().unwrap();
+            if let Err(e) = ser.write_all(&command.encode(seq, self.protocol.framed_commands)) {
+                self.send_response(tag, CommandRes::Error(Error::SerialError(format!("Failed to send data: {e:?}"))));
+                return self.link_lost(&e);
+            }
+
+            self.in_flight.push_back(InFlight { tag, seq, command, deadline, late: false });
+        }
 
-    fn handle_enable_frame_state(&mut self) -> Option<WorkerState> {
-        self.read_response(|frame| match frame {
-            DecodedFrame::EnableFrame =>
-                CommandRes::EnableFrame(Ok(())),
-            frame =>
-                CommandRes::ListFrames(Err(Error::WrongFrame(format!("Wrong response frame, expected GetFrameInfo, got {frame:?}")))),
-        })
+        None
     }
 
-    fn handle_disable_frame_state(&mut self) -> Option<WorkerState> {
-        self.read_response(|frame| match frame {
-            DecodedFrame::DisableFrame =>
-                CommandRes::DisableFrame(Ok(())),
-            frame =>
-                CommandRes::ListFrames(Err(Error::WrongFrame(format!("Wrong response frame, expected DisableFrame, got {frame:?}")))),
-        })
+    /// Sends the response to the command it answers, a response nobody waits for is dropped
+    ///
+    /// A command frame too damaged to read its sequence number is rejected without one. Which command
+    /// that was can't be told, so the NACK goes to the oldest command in flight, which is only sure to
+    /// be right while a single command is in flight.
+    fn complete_command(&mut self, frame: DecodedFrame, seq: Option<u8>) {
+        let index = self.in_flight.iter().position(|command| command.seq == seq)
+            .or_else(|| match frame {
+                DecodedFrame::Nack(_) if seq.is_none() && !self.in_flight.is_empty() => Some(0),
+                _ => None,
+            });
+
+        match index {
+            Some(index) => {
+                let command = self.in_flight.remove(index).unwrap();
+                let res = CommandRes::from_response(command.command, frame);
+                self.finish_command(command, res);
+            }
+            None => eprintln!("Dropping unexpected response {frame:?}"),
+        }
     }
 
-    fn read_response<F>(&mut self, map_frame: F) -> Option<WorkerState>
-        where F: FnOnce(DecodedFrame) -> CommandRes {
-        let mut serial_buf: Vec<u8> = vec![0; 32];
-        let ser = self.serial.as_mut().unwrap();
+    /// Sends the response to the requester, unless it gave up on the command
+    fn finish_command(&mut self, command: InFlight, res: CommandRes) {
+        if command.late {
+            eprintln!("Dropping late response to {:?}", command.command);
+        } else {
+            self.send_response(command.tag, res);
+        }
+    }
 
-        match ser.read(serial_buf.as_mut_slice()) {
-            Ok(nb) => {
-                self.decoder.add_data(&serial_buf.as_slice()[..nb]);
-                loop {
-                    match self.decoder.decode() {
-                        DecodeResult::None => return None,
-                        DecodeResult::CmdFrame(frame) => {
-                            self.send_response(map_frame(frame));
-                            return Some(WorkerState::Connected);
-                        }
-                        DecodeResult::Err(err) => {
-                            self.send_response(CommandRes::Error(Error::DecodeError(err)));
-                            return Some(WorkerState::Connected);
-                        }
-                        DecodeResult::SignalFrame(rsf) => self.send_signal_frame(rsf),
-                    }
-                }
+    /// Stops waiting for responses that didn't arrive in time, the requester already gave up on them
+    ///
+    /// Without sequence numbers the next response is taken for that of the command in flight, so a
+    /// command that timed out stays in flight for another timeout to catch its late response.
+    fn expire_commands(&mut self) {
+        let now = Instant::now();
+        let sequence_numbers = self.protocol.sequence_numbers;
+        let connection = &self.connection;
+
+        self.in_flight.retain_mut(|command| {
+            if command.deadline > now {
+                return true;
             }
-            Err(err) if err.kind() == ErrorKind::TimedOut => None,
-            Err(err) => {
-                self.send_response(CommandRes::Error(Error::SerialError(format!("Failed to read from serial: {err:?}"))));
-                Some(WorkerState::Connected)
+
+            if !sequence_numbers && !command.late {
+                eprintln!("No response to {:?} yet, waiting for a late one", command.command);
+                command.late = true;
+                command.deadline = now + connection.policy(&command.command).timeout;
+                return true;
             }
+
+            eprintln!("No response to {:?}", command.command);
+            false
+        });
+    }
+
+    /// Fails every command that is queued or waiting for a response
+    fn fail_commands(&mut self, err: Error) {
+        let tags = self.in_flight.drain(..).map(|command| command.tag)
+            .chain(self.queued.drain(..).map(|queued| queued.tag))
+            .collect::<Vec<_>>();
+
+        for tag in tags {
+            self.send_response(tag, CommandRes::Error(err.clone()));
         }
     }
 
-    fn send_response(&mut self, msg: CommandRes) {
-        if let Err(send_err) = self.rxchan_tx.blocking_send(msg) {
-            println!("Failed to send response: {send_err:?}");
+    /// Drops the transport after a read or write failed, and starts reconnecting
+    fn link_lost(&mut self, err: &std::io::Error) -> Option<WorkerState> {
+        eprintln!("Connection lost: {err}, reconnecting");
+        self.serial = None;
+        self.fail_commands(Error::SerialError(format!("Connection lost: {err}")));
+        self.reconnect_backoff = RECONNECT_BACKOFF_MIN;
+        self.reconnect_at = Instant::now() + self.reconnect_backoff;
+        let _ = self.link_tx.send(LinkEvent::Lost);
+        Some(WorkerState::Reconnecting)
+    }
+
+    fn send_response(&mut self, tag: u32, msg: CommandRes) {
+        if let Err(send_err) = self.rxchan_tx.blocking_send((tag, msg)) {
+            eprintln!("Failed to send response: {send_err:?}");
         }
     }
 
     fn send_signal_frame(&mut self, rsf: RawSignalFrame) {
         if let Err(send_err) = self.raw_frame_tx.try_send(rsf) {
-            println!("Failed to send signal frame: {send_err:?}");
+            if matches!(send_err, TrySendError::Full(_)) {
+                self.stats.count_dropped();
+            }
+            eprintln!("Failed to send signal frame: {send_err:?}");
         }
     }
 }
@@ -404,3 +656,257 @@ impl From<Elapsed> for Error {
         Error::Timeout
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io;
+    use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use crate::protocol::encode_frame;
+    use super::*;
+
+    /// Encodes a ListFrames response with a single frame, with the sequence number prefix in `prefix`
+    fn list_frames_response(prefix: &[u8], frame_id: u32) -> Vec<u8> {
+        encode_frame(&[prefix, b"l", &1u32.to_le_bytes(), &frame_id.to_le_bytes(), b"\x05frameL"].concat())
+    }
+
+    /// Response of the mock device, with the time it is sent
+    type DelayedResponse = (Instant, Vec<u8>);
+
+    /// Device that answers the n-th `lL` it answers with frame n, echoing its sequence number
+    #[derive(Clone, Debug, Default)]
+    struct MockDevice {
+        responses: Arc<Mutex<VecDeque<DelayedResponse>>>,
+        /// Number of commands to ignore
+        ignore: Arc<AtomicUsize>,
+        /// Number of commands to reject with a NACK that has no sequence number
+        reject: Arc<AtomicUsize>,
+        /// Time the device takes to respond
+        delay: Arc<Mutex<Duration>>,
+        answered: Arc<AtomicU32>,
+        /// Commands written to the device
+        written: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl MockDevice {
+        fn respond(&self, response: Vec<u8>) {
+            let due = Instant::now() + *self.delay.lock().unwrap();
+            self.responses.lock().unwrap().push_back((due, response));
+        }
+    }
+
+    struct MockTransport(MockDevice, VecDeque<u8>);
+
+    impl Transport for MockTransport {
+        type Config = MockDevice;
+
+        fn open(config: &MockDevice) -> io::Result<Self> {
+            Ok(MockTransport(config.clone(), VecDeque::new()))
+        }
+
+        fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+            let deadline = Instant::now() + timeout;
+            while self.1.is_empty() {
+                let now = Instant::now();
+                let mut responses = self.0.responses.lock().unwrap();
+                while responses.front().is_some_and(|(due, _)| *due <= now) {
+                    self.1.extend(responses.pop_front().unwrap().1);
+                }
+                drop(responses);
+
+                if self.1.is_empty() {
+                    if now >= deadline {
+                        return Err(ErrorKind::TimedOut.into());
+                    }
+                    thread::sleep((deadline - now).min(Duration::from_millis(5)));
+                }
+            }
+
+            let n = buf.len().min(self.1.len());
+            for (dst, src) in buf.iter_mut().zip(self.1.drain(..n)) {
+                *dst = src;
+            }
+            Ok(n)
+        }
+
+        fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+            self.0.written.lock().unwrap().push(data.to_vec());
+
+            let ignored = self.0.ignore.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).is_ok();
+            if ignored {
+                return Ok(());
+            }
+
+            let rejected = self.0.reject.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).is_ok();
+            if rejected {
+                self.0.respond(encode_frame(b"n\x01N"));
+                return Ok(());
+            }
+
+            if let Some(prefix) = data.strip_suffix(b"lL") {
+                let frame_id = self.0.answered.fetch_add(1, Ordering::Relaxed) + 1;
+                self.0.respond(list_frames_response(prefix, frame_id));
+            }
+            Ok(())
+        }
+
+        fn clear(&mut self) -> io::Result<()> {
+            self.0.responses.lock().unwrap().clear();
+            self.1.clear();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn runs_over_mock_transport() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (raw_frame_tx, _raw_frame_rx) = mpsc::channel(1);
+        let mut worker = SerialWorker::<MockTransport>::new(raw_frame_tx, Arc::default(), mpsc::unbounded_channel().0);
+
+        runtime.block_on(async {
+            worker.connect(MockDevice::default(), ProtocolOptions::default(), ConnectionConfig::default()).await.unwrap();
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 1);
+        });
+    }
+
+    #[test]
+    fn drops_stale_responses() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (raw_frame_tx, _raw_frame_rx) = mpsc::channel(1);
+        let mut worker = SerialWorker::<MockTransport>::new(raw_frame_tx, Arc::default(), mpsc::unbounded_channel().0);
+        let device = MockDevice::default();
+
+        runtime.block_on(async {
+            let protocol = ProtocolOptions { sequence_numbers: true, ..Default::default() };
+            worker.connect(device.clone(), protocol, ConnectionConfig::default()).await.unwrap();
+
+            // Late response to a command that timed out, which would be taken for the next response without sequence numbers
+            device.respond(list_frames_response(b"#\x05", 99));
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 1);
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 2);
+        });
+    }
+
+    #[test]
+    fn retries_commands_without_response() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (raw_frame_tx, _raw_frame_rx) = mpsc::channel(1);
+        let mut worker = SerialWorker::<MockTransport>::new(raw_frame_tx, Arc::default(), mpsc::unbounded_channel().0);
+        let device = MockDevice::default();
+        let policy = CommandPolicy {
+            timeout: Duration::from_millis(300),
+            retries: 2,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(10),
+        };
+
+        runtime.block_on(async {
+            let protocol = ProtocolOptions { sequence_numbers: true, ..Default::default() };
+            worker.connect(device.clone(), protocol, ConnectionConfig::default().with_command_policy(policy)).await.unwrap();
+
+            device.ignore.store(2, Ordering::Relaxed);
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 1);
+
+            device.ignore.store(3, Ordering::Relaxed);
+            assert!(matches!(worker.list_frames().await, Err(Error::Timeout)));
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 2);
+        });
+    }
+
+    #[test]
+    fn waits_for_late_responses_without_sequence_numbers() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (raw_frame_tx, _raw_frame_rx) = mpsc::channel(1);
+        let mut worker = SerialWorker::<MockTransport>::new(raw_frame_tx, Arc::default(), mpsc::unbounded_channel().0);
+        let device = MockDevice::default();
+        let policy = CommandPolicy {
+            timeout: Duration::from_millis(300),
+            retries: 2,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(10),
+        };
+
+        let connection = ConnectionConfig { read_timeout: Duration::from_millis(10), ..ConnectionConfig::default() }
+            .with_command_policy(policy);
+
+        runtime.block_on(async {
+            worker.connect(device.clone(), ProtocolOptions::default(), connection).await.unwrap();
+
+            // The timed out command isn't sent again, and its late response, which arrives while the next
+            // command waits for its own, isn't taken for the response to that one
+            *device.delay.lock().unwrap() = Duration::from_millis(350);
+            assert!(matches!(worker.list_frames().await, Err(Error::Timeout)));
+            *device.delay.lock().unwrap() = Duration::from_millis(100);
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 2);
+            assert_eq!(device.answered.load(Ordering::Relaxed), 2);
+        });
+    }
+
+    #[test]
+    fn drops_commands_that_timed_out_before_they_were_sent() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (raw_frame_tx, _raw_frame_rx) = mpsc::channel(1);
+        let mut worker = SerialWorker::<MockTransport>::new(raw_frame_tx, Arc::default(), mpsc::unbounded_channel().0);
+        let device = MockDevice::default();
+        let policy = |timeout| CommandPolicy { timeout: Duration::from_millis(timeout), retries: 0, ..CommandPolicy::default() };
+        let connection = ConnectionConfig {
+            read_timeout: Duration::from_millis(10),
+            list_frames: policy(300),
+            enable_frame: policy(100),
+            ..ConnectionConfig::default()
+        };
+
+        runtime.block_on(async {
+            worker.connect(device.clone(), ProtocolOptions::default(), connection).await.unwrap();
+
+            // The unanswered command stays in flight for another timeout, so the next one waits in the queue
+            device.ignore.store(1, Ordering::Relaxed);
+            assert!(matches!(worker.list_frames().await, Err(Error::Timeout)));
+            assert!(matches!(worker.enable_frame(1).await, Err(Error::Timeout)));
+
+            tokio::time::sleep(Duration::from_millis(400)).await;
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 1);
+            assert_eq!(*device.written.lock().unwrap(), vec![b"lL".to_vec(), b"lL".to_vec()]);
+        });
+    }
+
+    #[test]
+    fn takes_nack_without_sequence_number_for_oldest_command() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (raw_frame_tx, _raw_frame_rx) = mpsc::channel(1);
+        let mut worker = SerialWorker::<MockTransport>::new(raw_frame_tx, Arc::default(), mpsc::unbounded_channel().0);
+        let device = MockDevice::default();
+        let connection = ConnectionConfig::default().with_command_policy(CommandPolicy { retries: 0, ..CommandPolicy::default() });
+
+        runtime.block_on(async {
+            let protocol = ProtocolOptions { sequence_numbers: true, ..Default::default() };
+            worker.connect(device.clone(), protocol, connection).await.unwrap();
+
+            device.reject.store(1, Ordering::Relaxed);
+            assert!(matches!(worker.list_frames().await, Err(Error::CrcError)));
+            assert_eq!(worker.list_frames().await.unwrap()[0].id, 1);
+        });
+    }
+
+    #[test]
+    fn counts_dropped_signal_frames() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (raw_frame_tx, mut raw_frame_rx) = mpsc::channel(1);
+        let stats = Arc::new(DecodeStats::default());
+        let mut worker = SerialWorker::<MockTransport>::new(raw_frame_tx, stats.clone(), mpsc::unbounded_channel().0);
+        let device = MockDevice::default();
+
+        runtime.block_on(async {
+            worker.connect(device.clone(), ProtocolOptions::default(), ConnectionConfig::default()).await.unwrap();
+
+            // Nobody takes the frames, so only the first fits in the channel
+            for timestamp in 0..3u32 {
+                device.respond(encode_frame(&[&b"s"[..], &1u32.to_le_bytes(), &timestamp.to_le_bytes(), &0u32.to_le_bytes(), b"S"].concat()));
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert_eq!(stats.snapshot().dropped, 2);
+            assert_eq!(raw_frame_rx.recv().await.unwrap().timestamp, 0);
+        });
+    }
+}
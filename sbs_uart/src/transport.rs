@@ -0,0 +1,152 @@
+use std::fmt::Debug;
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use serialport::{ClearBuffer, SerialPort};
+
+/// Byte-level link to a device, over which the serial worker speaks the SBS protocol
+///
+/// Implementations are opened and used on the worker thread only.
+pub trait Transport: Send + Sized + 'static {
+    /// Everything needed to open the link, e.g. a port name and baud rate
+    type Config: Clone + Debug + Send + 'static;
+
+    fn open(config: &Self::Config) -> io::Result<Self>;
+
+    /// Reads the available bytes into `buf`, waiting at most `timeout` for the first one
+    ///
+    /// Fails with [`io::ErrorKind::TimedOut`] when no data arrived in time.
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize>;
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()>;
+
+    /// Discards any data that was received or queued for sending, but not yet handled
+    fn clear(&mut self) -> io::Result<()>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerialConfig {
+    pub port: String,
+    pub baud: u32,
+}
+
+impl SerialConfig {
+    pub fn new(port: &str, baud: u32) -> SerialConfig {
+        SerialConfig {
+            port: port.to_string(),
+            baud,
+        }
+    }
+}
+
+/// [`Transport`] over a serial port
+pub struct SerialTransport {
+    port: Box<dyn SerialPort>,
+}
+
+impl Transport for SerialTransport {
+    type Config = SerialConfig;
+
+    fn open(config: &SerialConfig) -> io::Result<Self> {
+        let port = serialport::new(&config.port, config.baud)
+            .timeout(Duration::from_millis(100))
+            .open()?;
+
+        Ok(SerialTransport { port })
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        if self.port.timeout() != timeout {
+            self.port.set_timeout(timeout)?;
+        }
+
+        self.port.read(buf)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.port.write_all(data)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        Ok(self.port.clear(ClearBuffer::All)?)
+    }
+}
+
+/// Time to wait for a TCP connection to be established, per resolved address
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_millis(1500);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcpConfig {
+    /// Address as `host:port`
+    pub address: String,
+}
+
+impl TcpConfig {
+    pub fn new(address: &str) -> TcpConfig {
+        TcpConfig {
+            address: address.to_string(),
+        }
+    }
+}
+
+/// [`Transport`] over a TCP connection, e.g. to a ser2net server in raw mode or a Wi-Fi serial bridge
+pub struct TcpTransport {
+    stream: TcpStream,
+    read_timeout: Option<Duration>,
+}
+
+impl Transport for TcpTransport {
+    type Config = TcpConfig;
+
+    fn open(config: &TcpConfig) -> io::Result<Self> {
+        let mut last_err = io::Error::new(ErrorKind::NotFound, format!("{} did not resolve to any address", config.address));
+
+        for addr in config.address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, TCP_CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_nodelay(true)?;
+                    return Ok(TcpTransport { stream, read_timeout: None });
+                }
+                Err(err) => last_err = err,
+            }
+        }
+
+        Err(last_err)
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        if self.read_timeout != Some(timeout) {
+            self.stream.set_read_timeout(Some(timeout))?;
+            self.read_timeout = Some(timeout);
+        }
+
+        match self.stream.read(buf) {
+            Ok(0) => Err(io::Error::new(ErrorKind::UnexpectedEof, "Connection closed by peer")),
+            // Unix reports an expired read timeout as WouldBlock
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Err(ErrorKind::TimedOut.into()),
+            result => result,
+        }
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream.write_all(data)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 256];
+
+        self.stream.set_nonblocking(true)?;
+        let result = loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => break Err(io::Error::new(ErrorKind::UnexpectedEof, "Connection closed by peer")),
+                Ok(_) => continue,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        self.stream.set_nonblocking(false)?;
+
+        result
+    }
+}
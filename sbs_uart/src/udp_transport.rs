@@ -0,0 +1,354 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use crate::frame_decoder::{DecodeResult, Decoder};
+use crate::protocol::Command;
+use crate::transport::Transport;
+
+/// Number of consecutive datagrams older than the newest one, after which the device is assumed to have been reset
+const RESET_CONFIRMATIONS: u32 = 3;
+
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+#[derive(Clone, Debug)]
+pub struct UdpConfig {
+    /// Address of the device as `host:port`
+    pub address: String,
+    /// Local address to receive on, any port on all interfaces if not set
+    pub bind_address: Option<String>,
+    /// Statistics of the link, shared so they can be shown while connected
+    pub stats: Arc<UdpStats>,
+}
+
+impl UdpConfig {
+    pub fn new(address: &str) -> UdpConfig {
+        UdpConfig {
+            address: address.to_string(),
+            bind_address: None,
+            stats: Arc::default(),
+        }
+    }
+}
+
+/// Packet counters of a UDP link
+#[derive(Debug, Default)]
+pub struct UdpStats {
+    received: AtomicU64,
+    lost: AtomicU64,
+    reordered: AtomicU64,
+    malformed: AtomicU64,
+}
+
+impl UdpStats {
+    pub fn snapshot(&self) -> UdpStatsSnapshot {
+        UdpStatsSnapshot {
+            received: self.received.load(Ordering::Relaxed),
+            lost: self.lost.load(Ordering::Relaxed),
+            reordered: self.reordered.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn count(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UdpStatsSnapshot {
+    /// Datagrams with valid frames
+    pub received: u64,
+    /// Data frames missing from the timestamp sequence of their frame, including those dropped for arriving late
+    pub lost: u64,
+    /// Datagrams dropped because a newer one arrived first
+    pub reordered: u64,
+    /// Datagrams that didn't contain complete, valid frames
+    pub malformed: u64,
+}
+
+impl UdpStatsSnapshot {
+    /// Fraction of the data frames sent by the device that were lost
+    pub fn loss_ratio(&self) -> f64 {
+        let total = self.received + self.lost;
+        if total == 0 {
+            0.0
+        } else {
+            self.lost as f64 / total as f64
+        }
+    }
+}
+
+impl Display for UdpStatsSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} received, {} lost ({:.1} %), {} out of order, {} malformed",
+               self.received, self.lost, self.loss_ratio() * 100.0, self.reordered, self.malformed)
+    }
+}
+
+/// Timestamps of a data frame, to find gaps in its sequence
+struct FrameTracker {
+    last_timestamp: u32,
+    /// Smallest timestamp difference seen, taken as the period of the frame
+    interval: Option<u32>,
+}
+
+/// [`Transport`] for devices that send every frame in a datagram of its own
+///
+/// Commands go out as one datagram each, a lost command or response is left to the retries of the
+/// worker, see [`ConnectionConfig`](crate::connection::ConnectionConfig). Datagrams that arrive after
+/// a newer one are dropped, since timestamps must not go backwards.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    stats: Arc<UdpStats>,
+    datagram: Vec<u8>,
+    rx: VecDeque<u8>,
+    newest_timestamp: Option<u32>,
+    behind: u32,
+    frames: HashMap<u32, FrameTracker>,
+}
+
+impl Transport for UdpTransport {
+    type Config = UdpConfig;
+
+    fn open(config: &UdpConfig) -> io::Result<Self> {
+        let remote = config.address.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("{} did not resolve to any address", config.address)))?;
+
+        let socket = match &config.bind_address {
+            Some(bind_address) => UdpSocket::bind(bind_address.as_str())?,
+            None if remote.is_ipv4() => UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0)))?,
+            None => UdpSocket::bind(SocketAddr::from(([0u16; 8], 0)))?,
+        };
+        socket.connect(remote)?;
+
+        Ok(UdpTransport {
+            socket,
+            stats: config.stats.clone(),
+            datagram: vec![0; MAX_DATAGRAM_SIZE],
+            rx: VecDeque::new(),
+            newest_timestamp: None,
+            behind: 0,
+            frames: HashMap::new(),
+        })
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        let deadline = Instant::now() + timeout;
+
+        while self.rx.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ErrorKind::TimedOut.into());
+            }
+
+            self.socket.set_read_timeout(Some(deadline.saturating_duration_since(now).max(Duration::from_millis(1))))?;
+
+            match self.socket.recv(&mut self.datagram) {
+                Ok(n) => self.receive_datagram(n),
+                // A connected socket reports an unreachable device as a refused connection
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::ConnectionRefused) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let n = buf.len().min(self.rx.len());
+        for (dst, src) in buf.iter_mut().zip(self.rx.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        // Enabling or disabling a frame leaves a gap in its timestamps that isn't loss
+        if let Some((_, Command::EnableFrame(frame_id) | Command::DisableFrame(frame_id))) = Command::decode(data) {
+            self.frames.remove(&frame_id);
+        }
+
+        self.send(data)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.socket.set_nonblocking(true)?;
+        let result = loop {
+            match self.socket.recv(&mut self.datagram) {
+                Ok(_) => continue,
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::ConnectionRefused) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        self.socket.set_nonblocking(false)?;
+
+        self.rx.clear();
+        self.newest_timestamp = None;
+        self.behind = 0;
+        self.frames.clear();
+
+        result
+    }
+}
+
+impl UdpTransport {
+    /// Sends a datagram, an unreachable device is left to the command retries of the worker
+    fn send(&self, data: &[u8]) -> io::Result<()> {
+        match self.socket.send(data) {
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Checks the first `len` bytes of the datagram buffer, and queues them for the worker if they should be decoded
+    fn receive_datagram(&mut self, len: usize) {
+        let mut decoder = Decoder::new();
+        decoder.add_data(&self.datagram[..len]);
+
+        let mut data_frames = Vec::new();
+        loop {
+            match decoder.decode() {
+                DecodeResult::None => break,
+                DecodeResult::CmdFrame(..) => {}
+                DecodeResult::SignalFrame(frame) => data_frames.push((frame.frame_id, frame.timestamp)),
+                DecodeResult::Err(_) => {
+                    UdpStats::count(&self.stats.malformed, 1);
+                    return;
+                }
+            }
+        }
+
+        // The worker decoder must not see partial frames, as the rest will never arrive
+        if !decoder.is_idle() {
+            UdpStats::count(&self.stats.malformed, 1);
+            return;
+        }
+
+        if let Some(&(_, timestamp)) = data_frames.first() {
+            if !self.is_in_order(timestamp) {
+                UdpStats::count(&self.stats.reordered, 1);
+                return;
+            }
+
+            for &(frame_id, timestamp) in &data_frames {
+                self.track_loss(frame_id, timestamp);
+            }
+        }
+
+        UdpStats::count(&self.stats.received, 1);
+        self.rx.extend(&self.datagram[..len]);
+    }
+
+    /// Whether a data frame with this timestamp may be passed on, which it may not if a newer one already was
+    fn is_in_order(&mut self, timestamp: u32) -> bool {
+        if let Some(newest) = self.newest_timestamp {
+            if timestamp.wrapping_sub(newest) > u32::MAX / 2 {
+                // A single late datagram is reordering, a run of them means the device restarted its counter
+                self.behind += 1;
+                if self.behind < RESET_CONFIRMATIONS {
+                    return false;
+                }
+
+                self.frames.clear();
+            }
+        }
+
+        self.behind = 0;
+        self.newest_timestamp = Some(timestamp);
+        true
+    }
+
+    fn track_loss(&mut self, frame_id: u32, timestamp: u32) {
+        let Some(tracker) = self.frames.get_mut(&frame_id) else {
+            self.frames.insert(frame_id, FrameTracker { last_timestamp: timestamp, interval: None });
+            return;
+        };
+
+        let delta = timestamp.wrapping_sub(tracker.last_timestamp);
+        if delta == 0 || delta > u32::MAX / 2 {
+            return;
+        }
+
+        if let Some(interval) = tracker.interval {
+            let missing = (delta as f64 / interval as f64).round() as u64;
+            UdpStats::count(&self.stats.lost, missing.saturating_sub(1));
+        }
+
+        tracker.interval = Some(tracker.interval.map_or(delta, |interval| interval.min(delta)));
+        tracker.last_timestamp = timestamp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_frame(frame_id: u32, timestamp: u32) -> Vec<u8> {
+        let mut frame = vec![0xBB, 0xBB, 0xBB, 0xBB, 14, 0, 0, 0, b's'];
+        frame.extend_from_slice(&frame_id.to_le_bytes());
+        frame.extend_from_slice(&timestamp.to_le_bytes());
+        frame.extend_from_slice(&0u32.to_le_bytes());
+        frame.push(b'S');
+        let crc = crc::Crc::<u16>::new(&crc::CRC_16_ARC).checksum(&frame[5..]);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame.push(0xEE);
+        frame
+    }
+
+    #[test]
+    fn counts_lost_reordered_and_malformed_datagrams() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = UdpConfig::new(&device.local_addr().unwrap().to_string());
+        let stats = config.stats.clone();
+        let mut transport = UdpTransport::open(&config).unwrap();
+        transport.write_all(b"e\x01\x00\x00\x00E").unwrap();
+
+        let mut buf = [0u8; 64];
+        let (_, host) = device.recv_from(&mut buf).unwrap();
+
+        // Frame 1 every 10 ticks, with 40 lost and 30 arriving late
+        for timestamp in [10, 20, 50, 30, 60] {
+            device.send_to(&data_frame(1, timestamp), host).unwrap();
+        }
+        device.send_to(&data_frame(1, 70)[..12], host).unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(n) = transport.read(&mut buf, Duration::from_millis(400)) {
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(received.len(), 4 * data_frame(1, 0).len());
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.received, snapshot.lost, snapshot.reordered, snapshot.malformed), (4, 2, 1, 1));
+        assert!((snapshot.loss_ratio() - 2.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn forgets_frame_timing_on_sequenced_and_framed_commands() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = UdpConfig::new(&device.local_addr().unwrap().to_string());
+        let stats = config.stats.clone();
+        let mut transport = UdpTransport::open(&config).unwrap();
+        transport.write_all(&Command::EnableFrame(1).encode(Some(1), true)).unwrap();
+
+        let mut buf = [0u8; 64];
+        let (_, host) = device.recv_from(&mut buf).unwrap();
+        let mut read_frames = |transport: &mut UdpTransport, timestamps: &[u32]| {
+            for &timestamp in timestamps {
+                device.send_to(&data_frame(1, timestamp), host).unwrap();
+            }
+            while transport.read(&mut buf, Duration::from_millis(100)).is_ok() {}
+        };
+
+        read_frames(&mut transport, &[10, 20, 30]);
+
+        // The gap while the frame was disabled is not loss
+        transport.write_all(&Command::DisableFrame(1).encode(Some(2), true)).unwrap();
+        transport.write_all(&Command::EnableFrame(1).encode(Some(3), false)).unwrap();
+        read_frames(&mut transport, &[500, 510]);
+
+        assert_eq!(stats.snapshot().lost, 0);
+    }
+}
@@ -2,16 +2,15 @@ mod view;
 mod views;
 mod signals;
 
-use crate::view::{ChildView, State, UpdateTopLevelView, View};
+use crate::view::UpdateTopLevelView;
 use crate::views::main_view::MainView;
 use eframe::egui;
 use eframe::egui::{Style, Visuals};
-use sbs_core::sbs::Client;
 
 
 #[tokio::main]
 async fn main() {
-    let mut native_options = eframe::NativeOptions::default();
+    let native_options = eframe::NativeOptions::default();
     eframe::run_native(
         "My egui App",
         native_options,
@@ -32,12 +31,10 @@ struct MyEguiApp {
 }
 
 impl MyEguiApp {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut result = MyEguiApp {
+    fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        MyEguiApp {
             main_view: MainView::new(),
-        };
-
-        result
+        }
     }
 }
 
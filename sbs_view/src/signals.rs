@@ -1 +1,2 @@
-pub mod window_buffer;
\ No newline at end of file
+pub mod window_buffer;
+pub mod session;
\ No newline at end of file
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use async_trait::async_trait;
+use sbs_core::dispatch::Dispatcher;
+use sbs_core::error::Error;
+use sbs_core::recording::{RecordingError, RecordingReader};
+use sbs_core::sbs::{CallbackHandle, Client, ConnectionState, FrameId, SignalFrameCallback, SignalFrameDescriptor};
+use sbs_core::subscription::{FrameFilter, Subscription, SubscriptionOptions};
+use crate::signals::window_buffer::{Snapshot, SignalTrace};
+
+/// Recorded session, with every signal leaf over the whole session
+pub struct Session {
+    pub frames: Vec<SignalFrameDescriptor>,
+    pub traces: Arc<Snapshot>,
+    /// Time in seconds of the last frame
+    pub duration: f64,
+}
+
+impl Session {
+    pub fn load(path: impl AsRef<Path>) -> Result<Session, RecordingError> {
+        let reader = RecordingReader::open(path)?;
+        let frames = reader.frames().to_vec();
+        let duration = reader.duration();
+
+        let mut traces = HashMap::new();
+        for value in reader {
+            let value = value?;
+
+            for (descriptor, data) in value.descriptor.signals.iter().zip(&value.data) {
+                for (path, leaf) in data.leaves(&descriptor.name) {
+                    let trace = traces.entry((value.descriptor.id, path)).or_insert_with(|| SignalTrace {
+                        descriptor: Some(descriptor.clone()),
+                        samples: Default::default(),
+                    });
+                    trace.samples.push_back((value.timestamp, leaf));
+                }
+            }
+        }
+
+        Ok(Session {
+            frames,
+            traces: Arc::new(traces.into_iter()
+                .map(|(signal_id, trace)| (signal_id, Arc::new(trace)))
+                .collect()),
+            duration,
+        })
+    }
+}
+
+/// [`Client`] for a recorded session, listing its frames so their signals can be plotted
+///
+/// The samples are taken from the [`Session`] directly, so no frames are dispatched.
+pub struct SessionClient {
+    frames: Vec<SignalFrameDescriptor>,
+    dispatcher: Dispatcher,
+    state: ConnectionState,
+}
+
+impl SessionClient {
+    pub fn new(session: &Session) -> SessionClient {
+        SessionClient {
+            frames: session.frames.iter()
+                .map(|frame| SignalFrameDescriptor { enabled: true, ..frame.clone() })
+                .collect(),
+            dispatcher: Dispatcher::new(),
+            state: ConnectionState::Connected,
+        }
+    }
+
+    fn check_frame(&self, frame_id: FrameId) -> Result<(), Error> {
+        if self.frames.iter().any(|f| f.id == frame_id) {
+            Ok(())
+        } else {
+            Err(Error::UnknownFrame(frame_id))
+        }
+    }
+}
+
+#[async_trait]
+impl Client for SessionClient {
+    async fn get_frames(&mut self) -> Result<Vec<SignalFrameDescriptor>, Error> {
+        Ok(self.frames.clone())
+    }
+
+    async fn enable_frame(&mut self, frame_id: FrameId) -> Result<(), Error> {
+        self.check_frame(frame_id)
+    }
+
+    async fn disable_frame(&mut self, frame_id: FrameId) -> Result<(), Error> {
+        self.check_frame(frame_id)
+    }
+
+    async fn add_callback(&mut self, filter: FrameFilter, cb: Box<dyn SignalFrameCallback>) -> CallbackHandle {
+        self.dispatcher.add_callback(filter, cb)
+    }
+
+    async fn remove_callback(&mut self, handle: CallbackHandle) -> bool {
+        self.dispatcher.remove_callback(handle)
+    }
+
+    async fn subscribe(&mut self, filter: FrameFilter, options: SubscriptionOptions) -> Subscription {
+        self.dispatcher.subscribe(filter, options)
+    }
+
+    async fn disconnect(&mut self) -> Result<(), Error> {
+        self.dispatcher.clear();
+        self.state = ConnectionState::Disconnected;
+        Ok(())
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        self.state
+    }
+}
@@ -1,27 +1,76 @@
 use std::collections::{HashMap, VecDeque};
 use std::sync::{mpsc, Arc};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::AtomicBool;
 use std::thread;
 use std::thread::JoinHandle;
-use pollster::FutureExt;
 use tokio::sync::RwLock;
-use sbs_core::sbs::{FrameId, SignalFrameCallback, SignalId};
+use sbs_core::clock::Timestamp;
+use sbs_core::export::Series;
+use sbs_core::sbs::{FrameId, SignalDescriptor, SignalFrameCallback, SignalId};
 use sbs_core::value::{SignalFrameValue, Value};
 
-pub type Snapshot = HashMap<SignalId, VecDeque<(u32, Value)>>;
+/// Traces of the signals, shared so taking a snapshot doesn't copy the samples
+///
+/// A live trace is only copied when a frame arrives while a snapshot of it is still held.
+pub type Snapshot = HashMap<SignalId, Arc<SignalTrace>>;
+
+#[derive(Clone, Debug, Default)]
+pub struct SignalTrace {
+    /// Descriptor of the signal the samples belong to, known once the first frame was received
+    pub descriptor: Option<SignalDescriptor>,
+    /// Samples with their time since the start of the connection
+    pub samples: VecDeque<(Timestamp, Value)>,
+}
+
+impl SignalTrace {
+    /// Returns the samples converted to engineering units, as plot points
+    pub fn points(&self) -> impl Iterator<Item=[f64; 2]> + '_ {
+        self.samples.iter().map(|(t, v)| {
+            let y = match &self.descriptor {
+                Some(descriptor) => descriptor.to_engineering(v),
+                None => v.clone().into(),
+            };
+            [t.seconds, y]
+        })
+    }
+
+    /// Returns the last sample at or before `t` in engineering units
+    pub fn value_at(&self, t: f64) -> Option<f64> {
+        let i = self.samples.partition_point(|(ts, _)| ts.seconds <= t).checked_sub(1)?;
+        let value = &self.samples[i].1;
+
+        Some(match &self.descriptor {
+            Some(descriptor) => descriptor.to_engineering(value),
+            None => value.clone().into(),
+        })
+    }
+
+    /// Returns the samples as a [`Series`] for exporting
+    pub fn series(&self, id: &SignalId) -> Series {
+        Series {
+            id: id.clone(),
+            descriptor: self.descriptor.clone(),
+            samples: self.samples.iter().cloned().collect(),
+        }
+    }
+}
 
 enum Cmd {
     SetWindow(f32),
     AddSignal(SignalId),
     RemoveSignal(SignalId),
     ProcessFrame(FrameId, SignalFrameValue),
+    SetSession(Option<Arc<Snapshot>>),
     TakeSnapshot,
     Quit,
 }
 
 pub struct WindowBuffer {
-    signals_buffer: Arc<RwLock<HashMap<SignalId, VecDeque<(u32, Value)>>>>,
+    #[allow(dead_code)]
+    signals_buffer: Arc<RwLock<Snapshot>>,
+    #[allow(dead_code)]
     snapshot_ready: Arc<AtomicBool>,
+    #[allow(dead_code)]
     rw_thread: JoinHandle<()>,
     cmd_tx: mpsc::Sender<Cmd>,
     snapshot_rx: mpsc::Receiver<Snapshot>,
@@ -42,32 +91,50 @@ impl WindowBuffer {
         WindowBuffer {
             signals_buffer: Arc::new(RwLock::new(HashMap::new())),
             rw_thread: thread::spawn(move || {
-                let mut window: u32 = 10_000;
+                let mut window: f64 = 10.0;
                 let mut buf = Snapshot::default();
+                // Recorded session the traces are taken from instead of the received frames
+                let mut session: Option<Arc<Snapshot>> = None;
 
                 while let Ok(cmd) = cmd_rx.recv() {
                     match cmd {
                         Cmd::SetWindow(new_window) => {
-                            window = (new_window * 1000.0) as u32;
-                            println!("{window}");
+                            window = new_window as f64;
                         },
-                        Cmd::AddSignal(signal_id) =>
-                            if !buf.contains_key(&signal_id) {
-                                buf.insert(signal_id, VecDeque::new());
-                            }
+                        Cmd::AddSignal(signal_id) => {
+                            let trace = session.as_ref()
+                                .and_then(|session| session.get(&signal_id).cloned())
+                                .unwrap_or_default();
+                            buf.entry(signal_id).or_insert(trace);
+                        }
                         Cmd::RemoveSignal(signal_id) =>
                             if buf.contains_key(&signal_id) {
                                 buf.remove(&signal_id);
                             },
+                        Cmd::ProcessFrame(_, _) if session.is_some() => {}
                         Cmd::ProcessFrame(frame_id, value) => {
-                            for (i, descriptor) in value.descriptor.signals.iter().enumerate() {
-                                let signal_id = (frame_id, descriptor.name.clone());
+                            let leaves = value.descriptor.signals
+                                .iter()
+                                .zip(&value.data)
+                                .flat_map(|(descriptor, data)| data.leaves(&descriptor.name)
+                                    .into_iter()
+                                    .map(move |(path, leaf)| (descriptor, path, leaf)));
+
+                            for (descriptor, path, leaf) in leaves {
+                                let signal_id = (frame_id, path);
+
+                                if let Some(trace) = buf.get_mut(&signal_id) {
+                                    let trace = Arc::make_mut(trace);
+                                    if trace.descriptor.is_none() {
+                                        trace.descriptor = Some(descriptor.clone());
+                                    }
 
-                                if let Some(sig_buf) = buf.get_mut(&signal_id) {
-                                    sig_buf.push_back((value.timestamp, value.data[i].clone()));
+                                    let sig_buf = &mut trace.samples;
+                                    let t = value.timestamp;
+                                    sig_buf.push_back((t, leaf));
 
                                     while let Some((ts, _)) = sig_buf.front() {
-                                        if (value.timestamp - ts) > window {
+                                        if (t.seconds - ts.seconds) > window {
                                             sig_buf.pop_front();
                                         } else {
                                             break;
@@ -76,6 +143,14 @@ impl WindowBuffer {
                                 }
                             }
                         }
+                        Cmd::SetSession(new_session) => {
+                            for (signal_id, trace) in buf.iter_mut() {
+                                *trace = new_session.as_ref()
+                                    .and_then(|session| session.get(signal_id).cloned())
+                                    .unwrap_or_default();
+                            }
+                            session = new_session;
+                        }
                         Cmd::TakeSnapshot => {
                             let snapshot = buf.clone();
                             snapshot_tx.send(snapshot).expect("Failed to send snapshot");
@@ -94,7 +169,8 @@ impl WindowBuffer {
         Box::new({
             let cmd_tx = self.cmd_tx.clone();
             move |frame_id: FrameId, value: &SignalFrameValue| {
-                cmd_tx.send(Cmd::ProcessFrame(frame_id, value.clone())).expect("Failed to send signal");
+                // The buffer may already be dropped when the callback is invoked while being removed
+                let _ = cmd_tx.send(Cmd::ProcessFrame(frame_id, value.clone()));
             }
         })
     }
@@ -111,15 +187,17 @@ impl WindowBuffer {
         self.cmd_tx.send(Cmd::SetWindow(window)).expect("Failed to send Cmd");
     }
 
+    /// Shows the whole of a recorded session instead of a window of the received frames, or
+    /// clears the traces to receive frames again if `None`
+    pub fn set_session(&mut self, session: Option<Arc<Snapshot>>) {
+        self.cmd_tx.send(Cmd::SetSession(session)).expect("Failed to send Cmd");
+    }
+
     pub fn request_snapshot(&mut self) {
         self.cmd_tx.send(Cmd::TakeSnapshot).expect("Failed to send Cmd");
     }
 
     pub fn poll_snapshot(&mut self) -> Option<Snapshot> {
-        if let Ok(snapshot) = self.snapshot_rx.try_recv() {
-            Some(snapshot)
-        } else {
-            None
-        }
+        self.snapshot_rx.try_recv().ok()
     }
 }
\ No newline at end of file
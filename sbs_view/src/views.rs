@@ -2,4 +2,6 @@ pub mod connect_view;
 pub mod main_view;
 mod signals_view;
 mod plot_view;
+// Not wired into the main view yet
+#[allow(dead_code, unused_variables, unused_must_use)]
 mod sidebar_settings_view;
\ No newline at end of file
@@ -3,24 +3,62 @@ use crate::views::main_view::MainViewAction;
 use eframe::egui;
 use eframe::egui::{Align, InnerResponse, Ui};
 use regex::Regex;
+use sbs_uart::protocol::ProtocolOptions;
 use std::collections::LinkedList;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Clone, Debug)]
 pub enum ConnectViewAction {
     Rescan,
-    Connect(Port),
+    Connect(Port, ConnectOptions),
+}
+
+/// Host-side settings applied to the client before connecting
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+    pub overrides_path: Option<PathBuf>,
+    /// Frame schema to use instead of querying the frames from the device
+    pub schema_path: Option<PathBuf>,
+    /// Query the frames from the device anyway, and warn where they differ from the schema
+    pub cross_check: bool,
+    /// Frequency of the device timestamp counter, taken from the schema or the default if not set
+    pub tick_frequency: Option<f64>,
+    /// File to record the raw byte stream to
+    pub capture_path: Option<PathBuf>,
+    pub protocol: ProtocolOptions,
+    /// Time the device gets to respond to a command, the default if not set
+    pub response_timeout: Option<Duration>,
+    /// Longest time a read from the link waits for data, the default if not set
+    pub read_timeout: Option<Duration>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Port {
-    SerialPort(String)
+    SerialPort(String),
+    /// Device behind a TCP server like ser2net, at `host:port`
+    Tcp(String),
+    /// Device sending a frame per UDP datagram, at `host:port`
+    Udp(String),
+    /// Replay of a raw capture file
+    Capture(String),
+    /// Decoded session recorded by the viewer
+    Recording(String),
+    /// In-process simulated device with demo frames
+    Simulator,
 }
 
 impl Display for Port {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Port::SerialPort(port_name) => write!(f, "Serial - {port_name}"),
+            Port::Tcp(address) => write!(f, "TCP - {address}"),
+            Port::Udp(address) => write!(f, "UDP - {address}"),
+            Port::Capture(path) => write!(f, "Capture - {path}"),
+            Port::Recording(path) => write!(f, "Recording - {path}"),
+            Port::Simulator => write!(f, "Simulator"),
         }
     }
 }
@@ -29,41 +67,55 @@ impl Display for Port {
 pub struct ConnectViewState {
     available_ports: Vec<Port>,
     selected_port: Option<Port>,
+    /// Address for the network ports, kept when switching between them
+    address: String,
+    replay_path: String,
+    capture_path: String,
+    recording_path: String,
+    overrides_path: String,
+    schema_path: String,
+    cross_check: bool,
+    tick_frequency: String,
+    response_timeout: String,
+    read_timeout: String,
+    sequence_numbers: bool,
+    framed_commands: bool,
 }
 
 impl State<ConnectViewAction> for ConnectViewState {
     fn apply(&mut self, action: ConnectViewAction) {
         match action {
             ConnectViewAction::Rescan => self.rescan(),
-            ConnectViewAction::Connect(_) => {}
+            ConnectViewAction::Connect(..) => {}
         }
     }
 }
 
 impl ConnectViewState {
     fn rescan(&mut self) {
-        if let Ok(ports) = serialport::available_ports() {
-            let (mut port_names, mut unlikely_port_names): (Vec<_>, Vec<_>) = ports
-                .iter()
-                .map(|p| p.port_name.clone())
-                .partition(|p| Self::is_likely_port_name(p));
-
-            port_names.append(&mut unlikely_port_names);
-
-            self.available_ports = port_names
-                .into_iter()
-                .map(|p| Port::SerialPort(p))
-                .collect::<Vec<_>>();
-
-            if let Some(prev_selected) = self.selected_port.take() {
-                if self.available_ports.contains(&prev_selected) {
-                    self.selected_port = Some(prev_selected);
-                } else {
-                    self.selected_port = self.available_ports.first().cloned();
-                }
+        // The simulator is listed even if the serial ports can't be enumerated
+        let ports = serialport::available_ports().unwrap_or_default();
+        let (mut port_names, mut unlikely_port_names): (Vec<_>, Vec<_>) = ports
+            .iter()
+            .map(|p| p.port_name.clone())
+            .partition(|p| Self::is_likely_port_name(p));
+
+        port_names.append(&mut unlikely_port_names);
+
+        self.available_ports = port_names
+            .into_iter()
+            .map(Port::SerialPort)
+            .chain([Port::Simulator])
+            .collect::<Vec<_>>();
+
+        if let Some(prev_selected) = self.selected_port.take() {
+            if matches!(prev_selected, Port::Tcp(_) | Port::Udp(_) | Port::Capture(_) | Port::Recording(_)) || self.available_ports.contains(&prev_selected) {
+                self.selected_port = Some(prev_selected);
             } else {
                 self.selected_port = self.available_ports.first().cloned();
             }
+        } else {
+            self.selected_port = self.available_ports.first().cloned();
         }
     }
 
@@ -88,6 +140,18 @@ impl ConnectView {
             state
         }
     }
+
+    /// Checks for the `host:port` form, the host is resolved when connecting
+    fn is_valid_network_address(address: &str) -> bool {
+        address.trim().rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+    }
+
+    fn optional_path(path: &str) -> Option<PathBuf> {
+        Some(path.trim())
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+    }
 }
 
 impl View<ConnectViewState, ConnectViewAction, MainViewAction> for ConnectView {
@@ -108,7 +172,27 @@ impl View<ConnectViewState, ConnectViewAction, MainViewAction> for ConnectView {
                         .unwrap_or("No port selected".to_string()))
                     .show_ui(ui, |ui| {
                         for port in &self.state.available_ports {
-                            ui.selectable_value(&mut self.state.selected_port, Some(port.clone()), format!("🔌 {}", port.to_string()));
+                            ui.selectable_value(&mut self.state.selected_port, Some(port.clone()), format!("🔌 {port}"));
+                        }
+
+                        let tcp_selected = matches!(self.state.selected_port, Some(Port::Tcp(_)));
+                        if ui.selectable_label(tcp_selected, "🌐 TCP").clicked() {
+                            self.state.selected_port = Some(Port::Tcp(self.state.address.clone()));
+                        }
+
+                        let udp_selected = matches!(self.state.selected_port, Some(Port::Udp(_)));
+                        if ui.selectable_label(udp_selected, "🌐 UDP").clicked() {
+                            self.state.selected_port = Some(Port::Udp(self.state.address.clone()));
+                        }
+
+                        let capture_selected = matches!(self.state.selected_port, Some(Port::Capture(_)));
+                        if ui.selectable_label(capture_selected, "📂 Capture file").clicked() {
+                            self.state.selected_port = Some(Port::Capture(self.state.replay_path.clone()));
+                        }
+
+                        let recording_selected = matches!(self.state.selected_port, Some(Port::Recording(_)));
+                        if ui.selectable_label(recording_selected, "📼 Recording file").clicked() {
+                            self.state.selected_port = Some(Port::Recording(self.state.recording_path.clone()));
                         }
                     });
 
@@ -117,11 +201,110 @@ impl View<ConnectViewState, ConnectViewAction, MainViewAction> for ConnectView {
                 }
             });
 
+            if let Some(Port::Tcp(address) | Port::Udp(address)) = &mut self.state.selected_port {
+                ui.horizontal(|ui| {
+                    ui.label("Address");
+                    if ui.add(egui::TextEdit::singleline(&mut self.state.address)
+                        .hint_text("host:port")).changed() {
+                        address.clone_from(&self.state.address);
+                    }
+                });
+            }
+
+            if let Some(Port::Recording(path)) = &mut self.state.selected_port {
+                ui.horizontal(|ui| {
+                    ui.label("Recording file");
+                    if ui.add(egui::TextEdit::singleline(&mut self.state.recording_path)
+                        .hint_text("Recorded session to open")).changed() {
+                        path.clone_from(&self.state.recording_path);
+                    }
+                });
+            } else if let Some(Port::Capture(path)) = &mut self.state.selected_port {
+                ui.horizontal(|ui| {
+                    ui.label("Capture file");
+                    if ui.add(egui::TextEdit::singleline(&mut self.state.replay_path)
+                        .hint_text("Raw capture to replay")).changed() {
+                        path.clone_from(&self.state.replay_path);
+                    }
+                });
+            } else if !matches!(self.state.selected_port, Some(Port::Simulator) | None) {
+                ui.horizontal(|ui| {
+                    ui.label("Record capture");
+                    ui.add(egui::TextEdit::singleline(&mut self.state.capture_path)
+                        .hint_text("Optional file to record the raw byte stream to"));
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Signal overrides");
+                ui.add(egui::TextEdit::singleline(&mut self.state.overrides_path)
+                    .hint_text("Optional .toml file with units and scaling"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Frame schema");
+                ui.add(egui::TextEdit::singleline(&mut self.state.schema_path)
+                    .hint_text("Optional .toml or .json file describing the frames"));
+                ui.add_enabled(!self.state.schema_path.trim().is_empty(),
+                               egui::Checkbox::new(&mut self.state.cross_check, "Cross-check with device"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Tick frequency (Hz)");
+                ui.add(egui::TextEdit::singleline(&mut self.state.tick_frequency)
+                    .hint_text("From schema or 1000"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Response timeout (ms)");
+                ui.add(egui::TextEdit::singleline(&mut self.state.response_timeout)
+                    .hint_text("2000"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Read timeout (ms)");
+                ui.add(egui::TextEdit::singleline(&mut self.state.read_timeout)
+                    .hint_text("100"));
+            });
+
+            ui.checkbox(&mut self.state.sequence_numbers, "Sequence numbers")
+                .on_hover_text("Number the commands, for devices that echo the number in their responses");
+            ui.checkbox(&mut self.state.framed_commands, "Framed commands")
+                .on_hover_text("Send the commands in CRC-checked frames, for devices that reject broken commands");
+
+            let tick_frequency = self.state.tick_frequency.trim();
+            let tick_frequency_valid = tick_frequency.is_empty() || tick_frequency.parse::<f64>().is_ok_and(|f| f > 0.0);
+            let response_timeout = self.state.response_timeout.trim();
+            let response_timeout_valid = response_timeout.is_empty() || response_timeout.parse::<u64>().is_ok_and(|t| t > 0);
+            let read_timeout = self.state.read_timeout.trim();
+            let read_timeout_valid = read_timeout.is_empty() || read_timeout.parse::<u64>().is_ok_and(|t| t > 0);
+
+            let port_valid = match &self.state.selected_port {
+                Some(Port::Tcp(address) | Port::Udp(address)) => Self::is_valid_network_address(address),
+                Some(Port::Capture(path) | Port::Recording(path)) => !path.trim().is_empty(),
+                Some(_) => true,
+                None => false,
+            };
+
             if ui.add_enabled(
-                self.state.selected_port.is_some(),
+                port_valid && tick_frequency_valid && response_timeout_valid && read_timeout_valid,
                 egui::Button::new("Connect"),
             ).clicked() {
-                result.push_back(ConnectViewAction::Connect(self.state.selected_port.clone().unwrap()));
+                let options = ConnectOptions {
+                    overrides_path: Self::optional_path(&self.state.overrides_path),
+                    schema_path: Self::optional_path(&self.state.schema_path),
+                    cross_check: self.state.cross_check,
+                    tick_frequency: tick_frequency.parse().ok(),
+                    capture_path: Self::optional_path(&self.state.capture_path),
+                    protocol: ProtocolOptions {
+                        sequence_numbers: self.state.sequence_numbers,
+                        framed_commands: self.state.framed_commands,
+                    },
+                    response_timeout: response_timeout.parse().ok().map(Duration::from_millis),
+                    read_timeout: read_timeout.parse().ok().map(Duration::from_millis),
+                };
+
+                result.push_back(ConnectViewAction::Connect(self.state.selected_port.clone().unwrap(), options));
             }
 
             result
@@ -130,8 +313,8 @@ impl View<ConnectViewState, ConnectViewAction, MainViewAction> for ConnectView {
 
     fn action_to_parent_action(&self, action: &ConnectViewAction) -> Option<MainViewAction> {
         match action {
-            ConnectViewAction::Connect(port) =>
-                Some(MainViewAction::Connect(port.clone())),
+            ConnectViewAction::Connect(port, options) =>
+                Some(MainViewAction::Connect(port.clone(), options.clone())),
             _ => None
         }
     }
@@ -5,19 +5,33 @@ use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
 use eframe::egui::{ComboBox, Response, Ui};
 use pollster::FutureExt;
 use tokio::sync::Mutex;
 
-use crate::signals::window_buffer::WindowBuffer;
+use crate::signals::session::{Session, SessionClient};
+use crate::signals::window_buffer::{Snapshot, WindowBuffer};
 use crate::view::{AsyncProcess, ChildView, State, TopLevelView, View};
-use crate::views::connect_view::{ConnectView, Port};
-use crate::views::plot_view::{PlotView, PlotViewParentAction};
+use crate::views::connect_view::{ConnectOptions, ConnectView, Port};
+use crate::views::plot_view::{PlotView, PlotViewAction, PlotViewParentAction};
 use crate::views::sidebar_settings_view::SidebarSettingsView;
 use crate::views::signals_view::{SignalsView, SignalsViewAction};
-use sbs_core::sbs::{Client, SignalId};
-use sbs_uart::sbs_uart::SbsUart;
+use sbs_core::overrides::SignalOverrides;
+use sbs_core::recording::RecordingWriter;
+use sbs_core::schema::Schema;
+use sbs_core::sbs::{CallbackHandle, Client, ConnectionState, FrameId, SignalFrameDescriptor, SignalId};
+use sbs_core::subscription::FrameFilter;
+use sbs_core::value::SignalFrameValue;
+use sbs_sim::config::SimConfig;
+use sbs_sim::sim_client::SimClient;
+use sbs_uart::connection::ConnectionConfig;
+use sbs_uart::sbs_uart::{FrameDiscovery, SbsUart};
+use sbs_uart::transport::{SerialConfig, SerialTransport, TcpConfig, TcpTransport, Transport};
+use sbs_uart::udp_transport::{UdpConfig, UdpStats, UdpTransport};
+use sbs_uart::capture::{Capture, CaptureConfig, CaptureTransport};
+use sbs_uart::replay::{ReplayControl, SbsReplay};
 
 #[derive(PartialEq)]
 pub enum PlotsLayout {
@@ -41,9 +55,11 @@ impl Display for PlotsLayout {
 pub enum MainViewAction {
     SetActivePlot(u32),
 
-    Connect(Port),
+    Connect(Port, ConnectOptions),
     ConnectSuccess(Box<dyn Client + Send>),
     ConnectFailed(String),
+    Disconnect,
+    DisconnectDone(Result<(), String>),
 
     AddSignalToCurrentPlot(SignalId),
     RemoveSignalFromCurrentPlot(SignalId),
@@ -51,18 +67,31 @@ pub enum MainViewAction {
     SetPlotWindow(u32, f32),
 
     SetLayout(PlotsLayout),
+
+    SetReplayPaused(bool),
+    StepReplay,
+    SetReplaySpeed(f64),
+
+    /// Starts recording the given frames, as the signals view knows them
+    StartRecording(Vec<SignalFrameDescriptor>),
+    StopRecording,
+
+    SetSessionCursor(f64),
+    SetSessionPlaying(bool),
 }
 
 enum ConnectState {
     Disconnected,
-    Connecting(AsyncProcess<Result<Box<SbsUart>, String>>),
+    Connecting(AsyncProcess<Result<Box<dyn Client + Send>, String>>),
     Connected,
+    Disconnecting(AsyncProcess<Result<(), String>>),
 }
 
 struct PlotState {
     #[allow(dead_code)]
     enabled_signals: HashSet<SignalId>,
     window_buffer: Rc<RefCell<WindowBuffer>>,
+    callback: Option<CallbackHandle>,
 }
 
 impl PlotState {
@@ -70,13 +99,52 @@ impl PlotState {
         PlotState {
             enabled_signals: HashSet::new(),
             window_buffer,
+            callback: None,
         }
     }
+
+    fn add_callback(&mut self, client: &Mutex<Box<dyn Client + Send>>) {
+        let cb = self.window_buffer.borrow().callback();
+        self.callback = Some(client.lock().block_on().add_callback(FrameFilter::All, cb).block_on());
+    }
+}
+
+/// Recording of the decoded frames of the current connection
+struct ActiveRecording {
+    path: String,
+    writer: Arc<std::sync::Mutex<Option<RecordingWriter>>>,
+    callback: CallbackHandle,
+}
+
+/// Recorded session opened for viewing, with the time the user scrubbed to
+struct SessionState {
+    traces: Arc<Snapshot>,
+    duration: f64,
+    cursor: f64,
+    /// When playing, the moment the cursor was at `cursor`
+    playing_since: Option<Instant>,
+}
+
+impl SessionState {
+    fn cursor(&self) -> f64 {
+        let elapsed = self.playing_since.map_or(0.0, |since| since.elapsed().as_secs_f64());
+        (self.cursor + elapsed).min(self.duration)
+    }
 }
 
 pub struct MainViewState {
     connect_state: ConnectState,
     client: Option<Arc<Mutex<Box<dyn Client + Send>>>>,
+    /// State of the link of the client, as of the last time the client wasn't busy
+    connection_state: ConnectionState,
+    /// Packet statistics when connected over UDP
+    udp_stats: Option<Arc<UdpStats>>,
+    /// Playback controls when replaying a capture
+    replay_control: Option<Arc<ReplayControl>>,
+    /// File the next recording of decoded frames is written to
+    record_path: String,
+    recording: Option<ActiveRecording>,
+    session: Option<SessionState>,
     selected_plot_id: Arc<AtomicU32>,
     plots: HashMap<u32, PlotState>,
     view_layout: PlotsLayout,
@@ -88,19 +156,31 @@ impl State<MainViewAction> for MainViewState {
     fn apply(&mut self, action: MainViewAction) {
         match action {
             // Connection
-            MainViewAction::Connect(port) => self.connect(port),
-            MainViewAction::ConnectSuccess(mut client) => {
-                for (_, state) in &mut self.plots {
-                    client.add_callback(state.window_buffer.borrow_mut().callback()).block_on();
+            MainViewAction::Connect(port, options) => self.connect(port, options),
+            MainViewAction::ConnectSuccess(client) => {
+                self.remove_plot_callbacks();
+
+                let client = Arc::new(Mutex::new(client));
+                let traces = self.session.as_ref().map(|session| session.traces.clone());
+                for state in self.plots.values_mut() {
+                    state.window_buffer.borrow_mut().set_session(traces.clone());
+                    state.add_callback(&client);
                 }
 
-                self.client = Some(Arc::new(Mutex::new(client)));
+                self.client = Some(client);
                 self.connect_state = ConnectState::Connected;
             }
             MainViewAction::ConnectFailed(err) => {
                 println!("Connect failed: {err}");
                 self.connect_state = ConnectState::Disconnected;
             }
+            MainViewAction::Disconnect => self.disconnect(),
+            MainViewAction::DisconnectDone(result) => {
+                if let Err(err) = result {
+                    println!("Disconnect failed: {err}");
+                }
+                self.connect_state = ConnectState::Disconnected;
+            }
 
             // Active plot
             MainViewAction::SetActivePlot(id) => {
@@ -125,6 +205,41 @@ impl State<MainViewAction> for MainViewState {
             MainViewAction::SetLayout(layout) => {
                 self.view_layout = layout;
             }
+
+            // Replay
+            MainViewAction::SetReplayPaused(paused) => {
+                if let Some(control) = &self.replay_control {
+                    control.set_paused(paused);
+                }
+            }
+            MainViewAction::StepReplay => {
+                if let Some(control) = &self.replay_control {
+                    control.step();
+                }
+            }
+            MainViewAction::SetReplaySpeed(speed) => {
+                if let Some(control) = &self.replay_control {
+                    control.set_speed(speed);
+                }
+            }
+
+            // Recording
+            MainViewAction::StartRecording(frames) => self.start_recording(&frames),
+            MainViewAction::StopRecording => self.stop_recording(),
+
+            // Recorded session
+            MainViewAction::SetSessionCursor(cursor) => {
+                if let Some(session) = &mut self.session {
+                    session.cursor = cursor.clamp(0.0, session.duration);
+                    session.playing_since = session.playing_since.map(|_| Instant::now());
+                }
+            }
+            MainViewAction::SetSessionPlaying(playing) => {
+                if let Some(session) = &mut self.session {
+                    session.cursor = session.cursor();
+                    session.playing_since = playing.then(Instant::now);
+                }
+            }
         }
     }
 }
@@ -134,6 +249,12 @@ impl MainViewState {
         MainViewState {
             connect_state: ConnectState::Disconnected,
             client: None,
+            connection_state: ConnectionState::Disconnected,
+            udp_stats: None,
+            replay_control: None,
+            record_path: "session.sbsrec".to_string(),
+            recording: None,
+            session: None,
             selected_plot_id,
             plots: Default::default(),
             view_layout: PlotsLayout::Single,
@@ -142,25 +263,142 @@ impl MainViewState {
         }
     }
 
-    fn connect(&mut self, port: Port) {
+    fn connect(&mut self, port: Port, options: ConnectOptions) {
+        self.stop_recording();
+        self.udp_stats = None;
+        self.replay_control = None;
+        self.session = None;
+
         match port {
             Port::SerialPort(port_name) => {
-                self.connect_state = ConnectState::Connecting(AsyncProcess::<Result<Box<SbsUart>, String>>::new({
-                    async move {
-                        let mut result = Box::new(SbsUart::new());
-                        let connect_result = result.connect(&port_name, 115_200).await;
-
-                        match connect_result {
-                            Ok(_) => Ok(result),
-                            Err(e) => Err(e.to_string())
+                self.connect_state = ConnectState::Connecting(AsyncProcess::<Result<Box<dyn Client + Send>, String>>::new(
+                    Self::connect_port::<SerialTransport>(SerialConfig::new(&port_name, 115_200), options)
+                ));
+            }
+            Port::Tcp(address) => {
+                self.connect_state = ConnectState::Connecting(AsyncProcess::<Result<Box<dyn Client + Send>, String>>::new(
+                    Self::connect_port::<TcpTransport>(TcpConfig::new(address.trim()), options)
+                ));
+            }
+            Port::Udp(address) => {
+                let config = UdpConfig::new(address.trim());
+                self.udp_stats = Some(config.stats.clone());
+                self.connect_state = ConnectState::Connecting(AsyncProcess::<Result<Box<dyn Client + Send>, String>>::new(
+                    Self::connect_port::<UdpTransport>(config, options)
+                ));
+            }
+            Port::Capture(path) => match Capture::load(path.trim()) {
+                Ok(capture) => {
+                    let replay = SbsReplay::new(capture);
+                    self.replay_control = Some(replay.control());
+                    self.connect_state = ConnectState::Connecting(AsyncProcess::<Result<Box<dyn Client + Send>, String>>::new(
+                        Self::start_replay(replay, options)
+                    ));
+                }
+                Err(e) => println!("Failed to load capture {path}: {e}"),
+            },
+            Port::Recording(path) => match Session::load(path.trim()) {
+                Ok(session) => {
+                    let client = SessionClient::new(&session);
+                    self.session = Some(SessionState {
+                        traces: session.traces,
+                        duration: session.duration,
+                        cursor: 0.0,
+                        playing_since: None,
+                    });
+                    self.connect_state = ConnectState::Connecting(AsyncProcess::<Result<Box<dyn Client + Send>, String>>::new({
+                        async move {
+                            Ok(Box::new(client) as Box<dyn Client + Send>)
                         }
-                    }
+                    }));
                 }
-                ));
+                Err(e) => println!("Failed to load recording {path}: {e}"),
+            },
+            Port::Simulator => {
+                self.connect_state = ConnectState::Connecting(AsyncProcess::<Result<Box<dyn Client + Send>, String>>::new({
+                    async move {
+                        Ok(Box::new(SimClient::new(SimConfig::demo())) as Box<dyn Client + Send>)
+                    }
+                }));
             }
         }
     }
 
+    /// Removes the callbacks from the client, and disconnects it in the background
+    fn disconnect(&mut self) {
+        self.stop_recording();
+        self.remove_plot_callbacks();
+        self.udp_stats = None;
+        self.replay_control = None;
+        self.session = None;
+
+        let Some(client) = self.client.take() else {
+            return;
+        };
+        self.connection_state = ConnectionState::Disconnected;
+        self.connect_state = ConnectState::Disconnecting(AsyncProcess::<Result<(), String>>::new(async move {
+            client.lock().await.disconnect().await.map_err(|e| e.to_string())
+        }));
+    }
+
+    /// Connects over transport `T`, recording a raw capture if the options ask for it
+    async fn connect_port<T: Transport>(config: T::Config, options: ConnectOptions) -> Result<Box<dyn Client + Send>, String> {
+        match options.capture_path.clone() {
+            Some(path) => {
+                let config = CaptureConfig::new(config, &path)
+                    .map_err(|e| format!("Failed to create capture {}: {e}", path.display()))?;
+                Self::connect_sbs::<CaptureTransport<T>>(config, options).await
+            }
+            None => Self::connect_sbs::<T>(config, options).await,
+        }
+    }
+
+    async fn start_replay(mut replay: SbsReplay, options: ConnectOptions) -> Result<Box<dyn Client + Send>, String> {
+        if let Some(path) = options.overrides_path {
+            replay.set_signal_overrides(SignalOverrides::load(path).map_err(|e| e.to_string())?);
+        }
+        if let Some(path) = options.schema_path {
+            replay.set_schema(Schema::load(path).map_err(|e| e.to_string())?);
+        }
+        replay.set_tick_frequency(options.tick_frequency);
+
+        replay.start().await.map_err(|e| e.to_string())?;
+        Ok(Box::new(replay))
+    }
+
+    /// Sets up an [`SbsUart`] over transport `T` with the connect options, and connects it
+    async fn connect_sbs<T: Transport>(config: T::Config, options: ConnectOptions) -> Result<Box<dyn Client + Send>, String> {
+        let mut result = Box::new(SbsUart::<T>::new());
+        if let Some(path) = options.overrides_path {
+            result.set_signal_overrides(SignalOverrides::load(path).map_err(|e| e.to_string())?);
+        }
+        if let Some(path) = options.schema_path {
+            let schema = Schema::load(path).map_err(|e| e.to_string())?;
+            result.set_frame_discovery(if options.cross_check {
+                FrameDiscovery::CrossCheck(schema)
+            } else {
+                FrameDiscovery::Schema(schema)
+            });
+        }
+        result.set_tick_frequency(options.tick_frequency);
+        result.set_protocol_options(options.protocol);
+        let mut connection = ConnectionConfig::default();
+        if let Some(timeout) = options.response_timeout {
+            connection = connection.with_response_timeout(timeout);
+        }
+        if let Some(timeout) = options.read_timeout {
+            connection = connection.with_read_timeout(timeout);
+        }
+        result.set_connection_config(connection);
+
+        let connect_result = result.connect(config).await;
+
+        match connect_result {
+            Ok(_) => Ok(result as Box<dyn Client + Send>),
+            Err(e) => Err(e.to_string())
+        }
+    }
+
     fn check_connecting_state(&mut self) -> Option<MainViewAction> {
         match &mut self.connect_state {
             ConnectState::Disconnected => None,
@@ -176,12 +414,94 @@ impl MainViewState {
                     None
                 }
             }
-            ConnectState::Connected => None
+            ConnectState::Connected => None,
+            ConnectState::Disconnecting(ref mut proc) => {
+                if proc.is_done() {
+                    Some(MainViewAction::DisconnectDone(proc.get()))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Picks up the state of the link from the client, unless a command holds the client
+    fn update_connection_state(&mut self) {
+        if let Some(client) = self.client.as_ref().and_then(|client| client.try_lock().ok()) {
+            self.connection_state = client.connection_state();
+        }
+    }
+
+    /// Starts writing the frames of the connected client to `record_path`
+    fn start_recording(&mut self, frames: &[SignalFrameDescriptor]) {
+        self.stop_recording();
+        let Some(client) = &self.client else {
+            return;
+        };
+
+        let path = self.record_path.trim().to_string();
+        let writer = match RecordingWriter::create(&path, frames) {
+            Ok(writer) => Arc::new(std::sync::Mutex::new(Some(writer))),
+            Err(e) => {
+                println!("Failed to create recording {path}: {e}");
+                return;
+            }
+        };
+
+        let callback = client.lock().block_on().add_callback(FrameFilter::All, Box::new({
+            let writer = writer.clone();
+            move |_: FrameId, value: &SignalFrameValue| {
+                if let Some(writer) = writer.lock().unwrap().as_mut() {
+                    if let Err(e) = writer.write(value) {
+                        println!("Failed to record frame: {e}");
+                    }
+                }
+            }
+        })).block_on();
+
+        self.recording = Some(ActiveRecording { path, writer, callback });
+    }
+
+    fn stop_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+
+        if let Some(client) = &self.client {
+            client.lock().block_on().remove_callback(recording.callback).block_on();
+        }
+
+        let writer = recording.writer.lock().unwrap().take();
+        if let Some(Err(e)) = writer.map(RecordingWriter::finish) {
+            println!("Failed to finish recording {}: {e}", recording.path);
         }
     }
 
     fn add_plot(&mut self, plot_id: u32, buffer: Rc<RefCell<WindowBuffer>>) {
-        self.plots.insert(plot_id, PlotState::new(buffer));
+        if let Some(session) = &self.session {
+            buffer.borrow_mut().set_session(Some(session.traces.clone()));
+        }
+
+        let mut state = PlotState::new(buffer);
+        if let Some(client) = &self.client {
+            state.add_callback(client);
+        }
+
+        self.plots.insert(plot_id, state);
+    }
+
+    /// Removes the window buffer callbacks from the current client, so they aren't registered twice
+    fn remove_plot_callbacks(&mut self) {
+        let Some(client) = &self.client else {
+            return;
+        };
+
+        let mut client = client.lock().block_on();
+        for state in self.plots.values_mut() {
+            if let Some(handle) = state.callback.take() {
+                client.remove_callback(handle).block_on();
+            }
+        }
     }
 }
 
@@ -191,6 +511,7 @@ pub struct MainView {
 
     connect_view: ConnectView,
 
+    #[allow(dead_code)]
     sidebar_settings: SidebarSettingsView,
     signals_view: Option<SignalsView>,
 
@@ -257,11 +578,13 @@ impl TopLevelView<MainViewState, MainViewAction> for MainView {
             ConnectState::Disconnected => {
                 result.append(&mut self.view_disconnected(ctx, frame));
             }
-            ConnectState::Connecting(_) => {
+            ConnectState::Connecting(_) | ConnectState::Disconnecting(_) => {
                 // ui.spinner();
+                self.signals_view = None;
                 self.view_connecting(ctx, frame);
             }
             ConnectState::Connected => {
+                self.state.update_connection_state();
                 result.append(&mut self.view_connected(ctx, frame));
             }
         }
@@ -291,7 +614,7 @@ impl MainView {
         });
     }
 
-    fn view_connected(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) -> LinkedList<MainViewAction> {
+    fn view_connected(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) -> LinkedList<MainViewAction> {
         if self.signals_view.is_none() {
             let mut signals_view = SignalsView::new(self.state.client.as_ref().unwrap().clone(), self.state.selected_plot_id.clone());
             signals_view.state().apply(SignalsViewAction::FetchSignals);
@@ -303,19 +626,42 @@ impl MainView {
         let mut signals_view_actions = egui::SidePanel::left("signals")
             .exact_width(240.0)
             .show(ctx, |ui| {
-                ComboBox::from_id_source("Layout").selected_text(self.state.view_layout.to_string()).show_ui(ui, |ui| {
-                    for layout in [
-                        PlotsLayout::Single,
-                        PlotsLayout::TwoHorizontal,
-                        PlotsLayout::TwoVertical,
-                        PlotsLayout::TwoByTwoGrid,
-                    ] {
-                        if ui.selectable_label(self.state.view_layout == layout, format!("{layout}")).clicked() {
-                            result.push_back(MainViewAction::SetLayout(layout));
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_source("Layout").selected_text(self.state.view_layout.to_string()).show_ui(ui, |ui| {
+                        for layout in [
+                            PlotsLayout::Single,
+                            PlotsLayout::TwoHorizontal,
+                            PlotsLayout::TwoVertical,
+                            PlotsLayout::TwoByTwoGrid,
+                        ] {
+                            if ui.selectable_label(self.state.view_layout == layout, format!("{layout}")).clicked() {
+                                result.push_back(MainViewAction::SetLayout(layout));
+                            }
                         }
+                    });
+
+                    if ui.button("Disconnect").clicked() {
+                        result.push_back(MainViewAction::Disconnect);
                     }
                 });
 
+                if self.state.connection_state != ConnectionState::Connected {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("Connection: {}", self.state.connection_state));
+                }
+
+                if let Some(stats) = &self.state.udp_stats {
+                    ui.small(format!("UDP: {}", stats.snapshot()));
+                }
+
+                if let Some(control) = &self.state.replay_control {
+                    Self::view_replay_controls(control, ui, &mut result);
+                }
+
+                match &self.state.session {
+                    Some(session) => Self::view_session_controls(session, ui, &mut result),
+                    None => self.view_record_controls(ui, &mut result),
+                }
+
                 ui.separator();
                 self.signals_view.as_mut().unwrap().render(ui)
             }).inner;
@@ -333,6 +679,11 @@ impl MainView {
         };
 
 
+        let cursor = self.state.session.as_ref().map(SessionState::cursor);
+        for plot in &mut self.plot_view {
+            plot.state().apply(PlotViewAction::SetCursor(cursor));
+        }
+
         egui::CentralPanel::default()
             .show(ctx, |ui| {
                 egui::Grid::new("plots").num_columns(2).spacing([8.0, 8.0]).show(ui, |ui| {
@@ -356,6 +707,71 @@ impl MainView {
         result
     }
 
+    fn view_replay_controls(control: &ReplayControl, ui: &mut Ui, actions: &mut LinkedList<MainViewAction>) {
+        ui.separator();
+        ui.label(format!("Replay {:.1} / {:.1} s{}",
+                         control.position().as_secs_f64(),
+                         control.duration().as_secs_f64(),
+                         if control.is_finished() { " (finished)" } else { "" }));
+
+        ui.horizontal(|ui| {
+            let paused = control.is_paused();
+            if ui.button(if paused { "▶ Play" } else { "⏸ Pause" }).clicked() {
+                actions.push_back(MainViewAction::SetReplayPaused(!paused));
+            }
+            if ui.button("Step").clicked() {
+                actions.push_back(MainViewAction::StepReplay);
+            }
+
+            let speed = control.speed();
+            ComboBox::from_id_source("replay_speed").selected_text(format!("{speed}x")).show_ui(ui, |ui| {
+                for option in [0.25, 0.5, 1.0, 2.0, 5.0, 10.0] {
+                    if ui.selectable_label(speed == option, format!("{option}x")).clicked() {
+                        actions.push_back(MainViewAction::SetReplaySpeed(option));
+                    }
+                }
+            });
+        });
+    }
+
+    fn view_record_controls(&mut self, ui: &mut Ui, actions: &mut LinkedList<MainViewAction>) {
+        ui.horizontal(|ui| {
+            let recording = self.state.recording.is_some();
+            // The frames are taken from the signals view, rather than querying the device again
+            let frames = self.signals_view.as_ref().and_then(SignalsView::frames);
+            if ui.add_enabled(recording || frames.is_some(), egui::SelectableLabel::new(recording, "⏺ Record")).clicked() {
+                actions.push_back(match frames {
+                    Some(frames) if !recording => MainViewAction::StartRecording(frames.to_vec()),
+                    _ => MainViewAction::StopRecording,
+                });
+            }
+
+            ui.add_enabled(!recording, egui::TextEdit::singleline(&mut self.state.record_path)
+                .hint_text("Recording file"));
+        });
+    }
+
+    fn view_session_controls(session: &SessionState, ui: &mut Ui, actions: &mut LinkedList<MainViewAction>) {
+        ui.separator();
+
+        let mut cursor = session.cursor();
+        ui.horizontal(|ui| {
+            let playing = session.playing_since.is_some() && cursor < session.duration;
+            if ui.button(if playing { "⏸ Pause" } else { "▶ Play" }).clicked() {
+                if !playing && cursor >= session.duration {
+                    actions.push_back(MainViewAction::SetSessionCursor(0.0));
+                }
+                actions.push_back(MainViewAction::SetSessionPlaying(!playing));
+            }
+
+            ui.label(format!("{cursor:.1} / {:.1} s", session.duration));
+        });
+
+        if ui.add(egui::Slider::new(&mut cursor, 0.0..=session.duration).show_value(false)).changed() {
+            actions.push_back(MainViewAction::SetSessionCursor(cursor));
+        }
+    }
+
     fn render_plot(plot: &mut PlotView, ui: &mut Ui, actions: &mut LinkedList<MainViewAction>) -> Response {
         let ir = plot.render(ui);
 
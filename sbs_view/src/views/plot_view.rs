@@ -1,10 +1,14 @@
 use crate::signals::window_buffer::{Snapshot, WindowBuffer};
 use crate::view::{State, View};
 use eframe::egui;
-use eframe::egui::{DragValue, InnerResponse, Ui};
-use egui_plot::{Line, Plot, PlotBounds, PlotPoints};
+use eframe::egui::{ComboBox, DragValue, InnerResponse, Ui};
+use egui_plot::{Line, Plot, PlotPoints, VLine};
+use sbs_core::columnar::{write_series, ColumnarFormat};
+use sbs_core::export::{write_table, ExportOptions, TableFormat, TableLayout, TimeFormat};
 use std::cell::RefCell;
 use std::collections::LinkedList;
+use std::fs::File;
+use std::io::BufWriter;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
@@ -16,6 +20,8 @@ pub enum PlotViewAction {
     TakeSnapshot,
     UpdateSnapshot(Snapshot),
     SetWindow(f32),
+    SetCursor(Option<f64>),
+    Export,
 }
 
 pub enum PlotViewParentAction {
@@ -23,6 +29,24 @@ pub enum PlotViewParentAction {
     SetWindow(f32),
 }
 
+/// Format of an exported plot, either a single text table or a directory of tables, a table per frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Table(TableFormat),
+    Columnar(ColumnarFormat),
+}
+
+impl ExportFormat {
+    fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Table(TableFormat::Csv) => "CSV",
+            ExportFormat::Table(TableFormat::Tsv) => "TSV",
+            ExportFormat::Columnar(ColumnarFormat::Parquet) => "Parquet",
+            ExportFormat::Columnar(ColumnarFormat::ArrowIpc) => "Arrow",
+        }
+    }
+}
+
 pub enum SnapshotState {
     Idle,
     TakingSnapshot,
@@ -38,6 +62,11 @@ pub struct PlotViewState {
     buf_snapshot: Snapshot,
     snapshot_state: SnapshotState,
     last_snapshot_at: SystemTime,
+    /// Time shown when scrubbing through a recorded session
+    cursor: Option<f64>,
+    export_path: String,
+    export_format: ExportFormat,
+    export_options: ExportOptions,
 }
 
 impl State<PlotViewAction> for PlotViewState {
@@ -58,6 +87,15 @@ impl State<PlotViewAction> for PlotViewState {
             PlotViewAction::SetWindow(new_window) => {
                 self.stored_window = new_window;
             }
+            PlotViewAction::SetCursor(cursor) => {
+                self.cursor = cursor;
+            }
+            PlotViewAction::Export => {
+                let path = self.export_path.trim();
+                if let Err(e) = self.export(path) {
+                    println!("Failed to export to {path}: {e}");
+                }
+            }
         }
     }
 
@@ -97,6 +135,26 @@ impl PlotViewState {
             buf_snapshot: Default::default(),
             snapshot_state: SnapshotState::Idle,
             last_snapshot_at: SystemTime::now(),
+            cursor: None,
+            export_path: format!("plot_{id}.csv"),
+            export_format: ExportFormat::Table(TableFormat::Csv),
+            export_options: ExportOptions::default(),
+        }
+    }
+
+    /// Writes the plotted signals to a table file, or to a directory of tables
+    fn export(&self, path: &str) -> Result<(), String> {
+        let mut series = self.buf_snapshot.iter()
+            .map(|(id, trace)| trace.series(id))
+            .collect::<Vec<_>>();
+        series.sort_by(|a, b| (a.id.0.0, &a.id.1).cmp(&(b.id.0.0, &b.id.1)));
+
+        match self.export_format {
+            ExportFormat::Table(format) => {
+                let mut out = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+                write_table(&mut out, &series, &ExportOptions { format, ..self.export_options }).map_err(|e| e.to_string())
+            }
+            ExportFormat::Columnar(format) => write_series(path, &series, format).map_err(|e| e.to_string()),
         }
     }
 }
@@ -114,9 +172,29 @@ impl View<PlotViewState, PlotViewAction, PlotViewParentAction> for PlotView {
 
     fn view(&mut self, ui: &mut Ui) -> InnerResponse<LinkedList<PlotViewAction>> {
         let mut result = LinkedList::<PlotViewAction>::new();
-        let plot = Plot::new(&self.plot_id)
+        let mut plot = Plot::new(&self.plot_id)
             .show_axes(true)
-            .show_grid(true);
+            .show_grid(true)
+            .x_axis_label("Time [s]");
+
+        // Label the value axis with the units of the plotted signals, and make sure their expected range is visible
+        let mut units = self.state.buf_snapshot.values()
+            .filter_map(|trace| trace.descriptor.as_ref().and_then(|d| d.unit.clone()))
+            .collect::<Vec<_>>();
+        units.sort();
+        units.dedup();
+        if !units.is_empty() {
+            plot = plot.y_axis_label(units.join(", "));
+        }
+
+        for descriptor in self.state.buf_snapshot.values().filter_map(|trace| trace.descriptor.as_ref()) {
+            if let Some(min) = descriptor.min {
+                plot = plot.include_y(min);
+            }
+            if let Some(max) = descriptor.max {
+                plot = plot.include_y(max);
+            }
+        }
 
         ui.with_layout(egui::Layout::top_down(egui::Align::Center).with_cross_justify(false).with_main_align(egui::Align::TOP), |ui| {
             ui.horizontal(|ui| {
@@ -126,19 +204,81 @@ impl View<PlotViewState, PlotViewAction, PlotViewParentAction> for PlotView {
                 if ui.selectable_label(self.state.id == self.state.active_id.load(Ordering::SeqCst), format!("Plot {}", self.state.id)).clicked() {
                     result.push_back(PlotViewAction::MakeActive)
                 }
-                ui.label(format!("Window: {} s", self.state.window))
+                match self.state.cursor {
+                    Some(cursor) => ui.label(format!("Cursor: {cursor:.3} s")),
+                    None => ui.label(format!("Window: {} s", self.state.window)),
+                }
             });
 
+            if let Some(cursor) = self.state.cursor {
+                ui.horizontal_wrapped(|ui| {
+                    for ((_, path), trace) in &self.state.buf_snapshot {
+                        if let Some(value) = trace.value_at(cursor) {
+                            ui.small(format!("{path} = {value:.4}"));
+                        }
+                    }
+                });
+            }
+
             if self.state.show_settings {
                 ui.group(|ui| {
                     egui::Grid::new(&self.settings_id)
                         .num_columns(2)
                         .spacing([40.0, 0.0])
                         .striped(true).show(ui, |ui| {
-                        ui.label("Window");
-                        ui.add(DragValue::new(&mut self.state.window)
-                            .range(1.0..=100.0)
-                            .speed(0.5));
+                        // The whole recorded session is shown, so there is no window to set
+                        if self.state.cursor.is_none() {
+                            ui.label("Window");
+                            ui.add(DragValue::new(&mut self.state.window)
+                                .range(1.0..=100.0)
+                                .speed(0.5));
+                            ui.end_row();
+                        }
+
+                        let options = &mut self.state.export_options;
+                        let export_format = &mut self.state.export_format;
+                        ui.label("Export");
+                        ui.horizontal(|ui| {
+                            ComboBox::from_id_source(format!("{}_format", self.settings_id))
+                                .selected_text(export_format.label())
+                                .show_ui(ui, |ui| {
+                                    for format in [
+                                        ExportFormat::Table(TableFormat::Csv),
+                                        ExportFormat::Table(TableFormat::Tsv),
+                                        ExportFormat::Columnar(ColumnarFormat::Parquet),
+                                        ExportFormat::Columnar(ColumnarFormat::ArrowIpc),
+                                    ] {
+                                        ui.selectable_value(export_format, format, format.label());
+                                    }
+                                });
+
+                            // Tables keep the raw values with a column per leaf, indexed by ticks and time
+                            if matches!(export_format, ExportFormat::Columnar(_)) {
+                                return;
+                            }
+                            ComboBox::from_id_source(format!("{}_time", self.settings_id))
+                                .selected_text(format!("{:?}", options.time))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut options.time, TimeFormat::Seconds, "Seconds");
+                                    ui.selectable_value(&mut options.time, TimeFormat::Ticks, "Ticks");
+                                });
+                            ComboBox::from_id_source(format!("{}_layout", self.settings_id))
+                                .selected_text(format!("{:?}", options.layout))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut options.layout, TableLayout::Aligned, "Aligned");
+                                    ui.selectable_value(&mut options.layout, TableLayout::Long, "Long");
+                                });
+                            ui.checkbox(&mut options.engineering_units, "Engineering units");
+                        });
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.state.export_path);
+                            if ui.button("Export").clicked() {
+                                result.push_back(PlotViewAction::Export);
+                            }
+                        });
                         ui.end_row();
                     });
                 });
@@ -147,8 +287,16 @@ impl View<PlotViewState, PlotViewAction, PlotViewParentAction> for PlotView {
             ui.ctx().request_repaint();
 
             plot.show(ui, |plot_ui| {
-                for ((_, name), values) in &self.state.buf_snapshot {
-                    plot_ui.line(Line::new(PlotPoints::from_iter(values.iter().map(|(t, v)| [*t as f64, v.clone().into()]))).name(name));
+                for ((_, path), trace) in &self.state.buf_snapshot {
+                    let name = trace.descriptor.as_ref()
+                        .map(|d| d.display_name(path))
+                        .unwrap_or(path.clone());
+
+                    plot_ui.line(Line::new(PlotPoints::from_iter(trace.points())).name(name));
+                }
+
+                if let Some(cursor) = self.state.cursor {
+                    plot_ui.vline(VLine::new(cursor).name("Cursor"));
                 }
             });
 
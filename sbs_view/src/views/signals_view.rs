@@ -4,6 +4,7 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use eframe::egui;
 use eframe::egui::{InnerResponse, Ui};
 use tokio::sync::Mutex;
+use sbs_core::error::Error;
 use sbs_core::sbs::{Client, FrameId, SignalFrameDescriptor, SignalId};
 use crate::view::{AsyncProcess, State, View};
 use crate::views::main_view::MainViewAction;
@@ -11,28 +12,28 @@ use crate::views::main_view::MainViewAction;
 pub enum SignalsViewAction {
     FetchSignals,
     FetchSignalsSuccess(Vec<SignalFrameDescriptor>),
-    FetchSignalsFailed(String),
+    FetchSignalsFailed(Error),
 
     EnableSignal(SignalId),
     EnableSignalSuccess(Vec<SignalFrameDescriptor>, SignalId),
-    EnableSignalFailed(String),
+    EnableSignalFailed(Error),
 
     DisableSignal(SignalId),
-    DisableSignalSuccess(Vec<SignalFrameDescriptor>, SignalId),
-    DisableSignalFailed(String),
+    DisableSignalSuccess(Vec<SignalFrameDescriptor>),
+    DisableSignalFailed(Error),
 }
 
 pub enum Signals {
     Initial,
-    Loading(AsyncProcess<Result<Vec<SignalFrameDescriptor>, String>>),
+    Loading(AsyncProcess<Result<Vec<SignalFrameDescriptor>, Error>>),
     Loaded(Vec<SignalFrameDescriptor>),
     Error(String),
 }
 
 pub enum EnableState {
     Idle,
-    EnablingSignal(AsyncProcess<Result<Vec<SignalFrameDescriptor>, String>>, SignalId),
-    DisablingSignal(AsyncProcess<Result<Vec<SignalFrameDescriptor>, String>>, SignalId),
+    EnablingSignal(AsyncProcess<Result<Vec<SignalFrameDescriptor>, Error>>, SignalId),
+    DisablingSignal(AsyncProcess<Result<Vec<SignalFrameDescriptor>, Error>>, SignalId),
 }
 
 
@@ -48,7 +49,7 @@ impl State<SignalsViewAction> for SignalsViewState {
     fn apply(&mut self, action: SignalsViewAction) {
         match action {
             SignalsViewAction::FetchSignals =>
-                self.signals = Signals::Loading(AsyncProcess::<Result<Vec<SignalFrameDescriptor>, String>>::new({
+                self.signals = Signals::Loading(AsyncProcess::<Result<Vec<SignalFrameDescriptor>, Error>>::new({
                     let client_mtx = self.client.clone();
                     async move {
                         let mut client = client_mtx.lock().await;
@@ -60,7 +61,7 @@ impl State<SignalsViewAction> for SignalsViewState {
             }
             SignalsViewAction::FetchSignalsFailed(errmsg) =>
                 {
-                    self.signals = Signals::Error(errmsg)
+                    self.signals = Signals::Error(errmsg.to_string())
                 }
 
             SignalsViewAction::EnableSignal(signal_id) => {
@@ -70,7 +71,7 @@ impl State<SignalsViewAction> for SignalsViewState {
                 if self.frame_is_enabled(signal_id.0) {
                     self.enable_signal(&signal_id);
                 } else {
-                    let enable_proc = AsyncProcess::<Result<Vec<SignalFrameDescriptor>, String>>::new({
+                    let enable_proc = AsyncProcess::<Result<Vec<SignalFrameDescriptor>, Error>>::new({
                         let client_mtx = self.client.clone();
                         async move {
                             let mut client = client_mtx.lock().await;
@@ -89,13 +90,18 @@ impl State<SignalsViewAction> for SignalsViewState {
             SignalsViewAction::EnableSignalFailed(err) => {
                 println!("{err}");
                 self.enable_state = EnableState::Idle;
+
+                // The device's frame list changed underneath us, reload it
+                if matches!(err, Error::UnknownFrame(_)) {
+                    self.apply(SignalsViewAction::FetchSignals);
+                }
             }
 
             SignalsViewAction::DisableSignal(signal_id) => {
                 self.disable_signal(&signal_id);
 
                 if !self.frame_has_enabled_signals(signal_id.0) {
-                    let disable_proc = AsyncProcess::<Result<Vec<SignalFrameDescriptor>, String>>::new({
+                    let disable_proc = AsyncProcess::<Result<Vec<SignalFrameDescriptor>, Error>>::new({
                         let client_mtx = self.client.clone();
                         async move {
                             let mut client = client_mtx.lock().await;
@@ -107,7 +113,7 @@ impl State<SignalsViewAction> for SignalsViewState {
                     self.enable_state = EnableState::DisablingSignal(disable_proc, signal_id);
                 }
             }
-            SignalsViewAction::DisableSignalSuccess(new_frames, _) => {
+            SignalsViewAction::DisableSignalSuccess(new_frames) => {
                 self.signals = Signals::Loaded(new_frames);
                 self.enable_state = EnableState::Idle;
             }
@@ -140,9 +146,9 @@ impl State<SignalsViewAction> for SignalsViewState {
                     Err(err) => SignalsViewAction::EnableSignalFailed(err)
                 })
             },
-            EnableState::DisablingSignal(ref mut proc, signal_id) => if proc.is_done() {
+            EnableState::DisablingSignal(ref mut proc, _) => if proc.is_done() {
                 result.push_back(match proc.get() {
-                    Ok(frames) => SignalsViewAction::DisableSignalSuccess(frames, signal_id.clone()),
+                    Ok(frames) => SignalsViewAction::DisableSignalSuccess(frames),
                     Err(err) => SignalsViewAction::DisableSignalFailed(err),
                 })
             }
@@ -183,7 +189,7 @@ impl SignalsViewState {
         if !self.enabled_signals.contains_key(signal_id) {
             self.enabled_signals.insert(signal_id.clone(), [active_id].into());
         } else {
-            self.enabled_signals.get_mut(&signal_id).unwrap().insert(active_id);
+            self.enabled_signals.get_mut(signal_id).unwrap().insert(active_id);
         }
     }
 
@@ -256,6 +262,14 @@ impl SignalsView {
         }
     }
 
+    /// Returns the frames of the device, once they were loaded
+    pub fn frames(&self) -> Option<&[SignalFrameDescriptor]> {
+        match &self.state.signals {
+            Signals::Loaded(frames) => Some(frames),
+            _ => None,
+        }
+    }
+
     fn signals_tree(
         &self,
         frames: &Vec<SignalFrameDescriptor>,
@@ -268,15 +282,19 @@ impl SignalsView {
                 let name = if frame.enabled {
                     format!("{} (enabled)", frame.name)
                 } else {
-                    format!("{}", frame.name)
+                    frame.name.to_string()
                 };
 
                 egui::CollapsingHeader::new(name)
-                    .id_source(frame.id.clone())
+                    .id_source(frame.id)
                     .default_open(true)
                     .show(ui, |ui| {
-                        for signal in &frame.signals {
-                            let signal_id = (frame.id, signal.name.clone());
+                        // Compound signals are listed by their scalar elements, which are plotted individually
+                        let leaves = frame.signals.iter()
+                            .flat_map(|signal| signal.ty.leaves(&signal.name).into_iter().map(move |(path, _)| (signal, path)));
+
+                        for (signal, path) in leaves {
+                            let signal_id = (frame.id, path.clone());
                             let signal_enabled = self.state.signal_enabled_for_current_plot(&signal_id);
 
                             ui.horizontal(|ui| {
@@ -298,7 +316,7 @@ impl SignalsView {
                                 }
 
 
-                                ui.label(&signal.name);
+                                ui.label(signal.display_name(&path));
                             });
                         }
                     });